@@ -210,6 +210,7 @@ fn process(args: &Args) -> Result<(), String> {
         },
         dpi: args.dpi.0 as f64,
         keep_named_groups: args.keep_named_groups,
+        .. usvg::Options::default()
     };
 
     let input_str = match in_svg {
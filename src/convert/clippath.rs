@@ -33,6 +33,7 @@ pub fn convert(
 pub fn convert_children(
     node: &svgdom::Node,
     parent: &tree::Node,
+    opt: &Options,
     tree: &mut tree::Tree,
 ) {
     for (id, node) in node.children().svg() {
@@ -42,14 +43,16 @@ pub fn convert_children(
             | EId::Polygon
             | EId::Circle
             | EId::Ellipse => {
-                if let Some(d) = shapes::convert(&node) {
-                    path::convert(&node, d, parent.clone(), tree);
+                if opt.keep_shapes {
+                    shapes::convert_shape(&node, parent.clone(), tree);
+                } else if let Some(d) = shapes::convert(&node) {
+                    path::convert(&node, d, parent.clone(), opt, tree);
                 }
             }
             EId::Path => {
                 let attrs = node.attributes();
                 if let Some(d) = attrs.get_path(AId::D) {
-                    path::convert(&node, d.clone(), parent.clone(), tree);
+                    path::convert(&node, d.clone(), parent.clone(), opt, tree);
                 }
             }
             EId::Text => {
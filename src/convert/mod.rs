@@ -12,6 +12,7 @@ use svgdom::{
 // self
 use tree;
 use tree::prelude::*;
+use tree::path_ops;
 use short::{
     AId,
     AValue,
@@ -25,6 +26,7 @@ use traits::{
 use geom::*;
 use {
     Options,
+    PathProfile,
 };
 
 
@@ -109,10 +111,36 @@ pub fn convert_doc(
     };
 
     let mut tree = tree::Tree::create(svg_kind);
+    tree.set_path_profile(opt.path_profile);
 
     convert_ref_nodes(svg_doc, opt, &mut tree);
     convert_nodes(&svg, tree.root(), opt, &mut tree);
 
+    // Shapes are converted into paths just like during the parsing.
+    let (profile, arc_tolerance) = (opt.path_profile, opt.arc_tolerance);
+
+    if let Some(tolerance) = opt.apply_clip_paths {
+        path_ops::apply_clip_paths(&mut tree, tolerance, profile, arc_tolerance);
+    }
+
+    if let Some(tolerance) = opt.outline_strokes {
+        path_ops::outline_strokes(&mut tree, tolerance, profile, arc_tolerance);
+    } else if opt.expand_dashes {
+        path_ops::expand_dashes(&mut tree, profile, arc_tolerance);
+    }
+
+    if let PathProfile::LinesOnly { tolerance } = profile {
+        path_ops::flatten_paths(&mut tree, tolerance, arc_tolerance);
+    }
+
+    if let Some(tolerance) = opt.simplify_paths {
+        tree.simplify_paths(tolerance);
+    }
+
+    if opt.normalize_paths {
+        tree.normalize_paths();
+    }
+
     tree
 }
 
@@ -168,7 +196,7 @@ fn convert_ref_nodes(
 
     for (node, mut new_node) in later_nodes {
         if node.is_tag_name(EId::ClipPath) {
-            clippath::convert_children(&node, &new_node, tree);
+            clippath::convert_children(&node, &new_node, opt, tree);
 
             // e-clipPath-022.svg
             // e-clipPath-016.svg
@@ -263,8 +291,10 @@ pub(super) fn convert_nodes(
             | EId::Polygon
             | EId::Circle
             | EId::Ellipse => {
-                if let Some(d) = shapes::convert(&node) {
-                    path::convert(&node, d, parent_node.clone(), tree);
+                if opt.keep_shapes {
+                    shapes::convert_shape(&node, parent_node.clone(), tree);
+                } else if let Some(d) = shapes::convert(&node) {
+                    path::convert(&node, d, parent_node.clone(), opt, tree);
                 }
             }
               EId::Use
@@ -275,7 +305,7 @@ pub(super) fn convert_nodes(
             EId::Path => {
                 let attrs = node.attributes();
                 if let Some(d) = attrs.get_path(AId::D) {
-                    path::convert(&node, d.clone(), parent_node.clone(), tree);
+                    path::convert(&node, d.clone(), parent_node.clone(), opt, tree);
                 }
             }
             EId::Text => {
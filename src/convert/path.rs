@@ -6,11 +6,17 @@ use std::f64;
 
 // external
 use svgdom;
-use lyon_geom;
 
 // self
+use geom::{
+    CubicBezier,
+    EllipticalArc,
+};
+use path_utils;
 use tree;
 use tree::prelude::*;
+use utils;
+use PathProfile;
 use super::prelude::*;
 use super::{
     fill,
@@ -22,43 +28,59 @@ pub fn convert(
     node: &svgdom::Node,
     d: svgdom::Path,
     mut parent: tree::Node,
+    opt: &Options,
     tree: &mut tree::Tree,
 ) {
-    let d = convert_path(d);
+    let d = convert_path(d, opt);
     if d.len() < 2 {
         return;
     }
 
     let has_bbox = has_bbox(&d);
+    let (fill, stroke) = try_opt!(convert_paint(node, has_bbox, tree), ());
+    let transform = node.attributes().get_transform(AId::Transform).unwrap_or_default();
+
+    parent.append_kind(tree::NodeKind::Path(tree::Path {
+        id: node.id().clone(),
+        transform,
+        fill,
+        stroke,
+        segments: d,
+    }));
+}
+
+/// Converts element's fill and stroke.
+///
+/// Returns `None` when there is nothing to render.
+pub fn convert_paint(
+    node: &svgdom::Node,
+    has_bbox: bool,
+    tree: &tree::Tree,
+) -> Option<(Option<tree::Fill>, Option<tree::Stroke>)> {
     let attrs = node.attributes();
     let fill = fill::convert(tree, &attrs, has_bbox);
     let stroke = stroke::convert(tree, &attrs, has_bbox);
-    let transform = attrs.get_transform(AId::Transform).unwrap_or_default();
 
     // Shapes without a bbox cannot be filled,
     // and if there is no stroke than there is nothing to render.
     if !has_bbox && stroke.is_none() {
-        return;
+        return None;
     }
 
     if fill.is_none() && stroke.is_none() {
-        return;
+        return None;
     }
 
-    parent.append_kind(tree::NodeKind::Path(tree::Path {
-        id: node.id().clone(),
-        transform,
-        fill,
-        stroke,
-        segments: d,
-    }));
+    Some((fill, stroke))
 }
 
-fn convert_path(mut path: svgdom::Path) -> Vec<tree::PathSegment> {
+fn convert_path(mut path: svgdom::Path, opt: &Options) -> Vec<tree::PathSegment> {
     let mut new_path = Vec::with_capacity(path.len());
 
     path.conv_to_absolute();
 
+    let is_extended = opt.path_profile == PathProfile::Extended;
+
     // Previous MoveTo coordinates.
     let mut pmx = 0.0;
     let mut pmy = 0.0;
@@ -116,7 +138,11 @@ fn convert_path(mut path: svgdom::Path) -> Vec<tree::PathSegment> {
                 ptx = Some(x * 2.0 - x1);
                 pty = Some(y * 2.0 - y1);
 
-                new_path.push(quad_to_curve(px, py, x1, y1, x, y));
+                if is_extended {
+                    new_path.push(tree::PathSegment::QuadTo { x1, y1, x, y });
+                } else {
+                    new_path.push(quad_to_curve(px, py, x1, y1, x, y));
+                }
             }
             svgdom::PathSegment::SmoothQuadratic { x, y, .. } => {
                 // 'The control point is assumed to be the reflection of
@@ -138,27 +164,47 @@ fn convert_path(mut path: svgdom::Path) -> Vec<tree::PathSegment> {
                     new_y1 = py;
                 }
 
-                new_path.push(quad_to_curve(px, py, new_x1, new_y1, x, y));
+                if is_extended {
+                    new_path.push(tree::PathSegment::QuadTo { x1: new_x1, y1: new_y1, x, y });
+                } else {
+                    new_path.push(quad_to_curve(px, py, new_x1, new_y1, x, y));
+                }
             }
             svgdom::PathSegment::EllipticalArc { rx, ry, x_axis_rotation, large_arc, sweep, x, y, .. } => {
-                let arc = lyon_geom::SvgArc {
-                    from: [px as f32, py as f32].into(),
-                    to: [x as f32, y as f32].into(),
-                    radii: [rx as f32, ry as f32].into(),
-                    x_rotation: lyon_geom::math::Angle::degrees(x_axis_rotation as f32),
-                    flags: lyon_geom::ArcFlags { large_arc, sweep },
-                };
+                if is_extended {
+                    // 'If the endpoints are identical, then this is equivalent
+                    // to omitting the elliptical arc segment entirely.'
+                    if px == x && py == y {
+                        continue;
+                    }
 
-                arc.for_each_quadratic_bezier(&mut |quad| {
-                    let cubic = quad.to_cubic();
-                    let curve = tree::PathSegment::CurveTo {
-                        x1: cubic.ctrl1.x as f64, y1: cubic.ctrl1.y as f64,
-                        x2: cubic.ctrl2.x as f64, y2: cubic.ctrl2.y as f64,
-                        x:  cubic.to.x as f64,    y:  cubic.to.y as f64,
+                    // 'If rx = 0 or ry = 0, then treat this as a straight line segment.'
+                    if rx.is_fuzzy_zero() || ry.is_fuzzy_zero() {
+                        new_path.push(tree::PathSegment::LineTo { x, y });
+                    } else {
+                        new_path.push(tree::PathSegment::ArcTo {
+                            rx: rx.abs(),
+                            ry: ry.abs(),
+                            x_axis_rotation,
+                            large_arc,
+                            sweep,
+                            x,
+                            y,
+                        });
+                    }
+                } else {
+                    let arc = EllipticalArc {
+                        from: Point::new(px, py),
+                        to: Point::new(x, y),
+                        rx,
+                        ry,
+                        x_axis_rotation,
+                        large_arc,
+                        sweep,
                     };
 
-                    new_path.push(curve);
-                });
+                    path_utils::arc_to_curves(&arc, opt.arc_tolerance, &mut new_path);
+                }
             }
             svgdom::PathSegment::ClosePath { .. } => {
                 new_path.push(tree::PathSegment::ClosePath);
@@ -178,7 +224,9 @@ fn convert_path(mut path: svgdom::Path) -> Vec<tree::PathSegment> {
                     px = x;
                     py = y;
                 }
-                tree::PathSegment::CurveTo { x, y, .. } => {
+                  tree::PathSegment::CurveTo { x, y, .. }
+                | tree::PathSegment::QuadTo { x, y, .. }
+                | tree::PathSegment::ArcTo { x, y, .. } => {
                     px = x;
                     py = y;
                 }
@@ -225,79 +273,23 @@ fn quad_to_curve(
     x: f64,
     y: f64
 ) -> tree::PathSegment {
-    let quad = lyon_geom::QuadraticBezierSegment {
-        from: [px as f32, py as f32].into(),
-        ctrl: [x1 as f32, y1 as f32].into(),
-        to:   [x  as f32,  y as f32].into(),
-    };
-
-    let cubic = quad.to_cubic();
+    let curve = CubicBezier::from_quad(Point::new(px, py), Point::new(x1, y1), Point::new(x, y));
+    curve_to_segment(&curve)
+}
 
+fn curve_to_segment(curve: &CubicBezier) -> tree::PathSegment {
     tree::PathSegment::CurveTo {
-        x1: cubic.ctrl1.x as f64, y1: cubic.ctrl1.y as f64,
-        x2: cubic.ctrl2.x as f64, y2: cubic.ctrl2.y as f64,
-        x:  cubic.to.x as f64,    y:  cubic.to.y as f64,
+        x1: curve.p1.x, y1: curve.p1.y,
+        x2: curve.p2.x, y2: curve.p2.y,
+        x:  curve.p3.x, y:  curve.p3.y,
     }
 }
 
-fn has_bbox(segments: &[tree::PathSegment]) -> bool {
+pub fn has_bbox(segments: &[tree::PathSegment]) -> bool {
     debug_assert!(!segments.is_empty());
 
-    let (mut prev_x, mut prev_y, mut minx, mut miny, mut maxx, mut maxy) = {
-        if let tree::PathSegment::MoveTo { x, y } = segments[0] {
-            (x as f32, y as f32, x as f32, y as f32, x as f32, y as f32)
-        } else {
-            unreachable!();
-        }
-    };
-
-    for seg in segments {
-        match *seg {
-            tree::PathSegment::MoveTo { x, y }
-            | tree::PathSegment::LineTo { x, y } => {
-                let x = x as f32;
-                let y = y as f32;
-                prev_x = x;
-                prev_y = y;
-
-                     if x > maxx { maxx = x; }
-                else if x < minx { minx = x; }
-
-                     if y > maxy { maxy = y; }
-                else if y < miny { miny = y; }
-            }
-            tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
-                let x = x as f32;
-                let y = y as f32;
-
-                let curve = lyon_geom::CubicBezierSegment {
-                    from: lyon_geom::math::Point::new(prev_x, prev_y),
-                    ctrl1: lyon_geom::math::Point::new(x1 as f32, y1 as f32),
-                    ctrl2: lyon_geom::math::Point::new(x2 as f32, y2 as f32),
-                    to: lyon_geom::math::Point::new(x, y),
-                };
-
-                prev_x = x;
-                prev_y = y;
-
-                let r = curve.bounding_rect();
-
-                let right = r.max_x();
-                let bottom = r.max_y();
-                if r.min_x() < minx { minx = r.min_x(); }
-                if right > maxx { maxx = right; }
-                if r.min_y() < miny { miny = r.min_y(); }
-                if bottom > maxy { maxy = bottom; }
-            }
-            tree::PathSegment::ClosePath => {}
-        }
-
-        let width = (maxx - minx) as f64;
-        let height = (maxy - miny) as f64;
-        if !(width.is_fuzzy_zero() || height.is_fuzzy_zero()) {
-            return true;
-        }
+    match utils::path_bbox(segments, None, &tree::Transform::default()) {
+        Some(r) => !(r.width.is_fuzzy_zero() || r.height.is_fuzzy_zero()),
+        None => false,
     }
-
-    false
 }
@@ -6,23 +6,87 @@
 use svgdom;
 
 // self
+use tree;
+use PathProfile;
 use super::prelude::*;
+use super::path;
 
 
 pub fn convert(node: &svgdom::Node) -> Option<svgdom::Path> {
+    let kind = try_opt!(resolve(node), None);
+    Some(shape_to_path(&kind))
+}
+
+/// Converts a shape element into `NodeKind::Shape`.
+pub fn convert_shape(
+    node: &svgdom::Node,
+    mut parent: tree::Node,
+    tree: &mut tree::Tree,
+) {
+    let kind = try_opt!(resolve(node), ());
+
+    let has_bbox = path::has_bbox(&kind.to_path(PathProfile::Extended, 0.0));
+    let (fill, stroke) = try_opt!(path::convert_paint(node, has_bbox, tree), ());
+
+    let attrs = node.attributes();
+    parent.append_kind(tree::NodeKind::Shape(Box::new(tree::Shape {
+        id: node.id().clone(),
+        transform: attrs.get_transform(AId::Transform).unwrap_or_default(),
+        fill,
+        stroke,
+        kind,
+    })));
+}
+
+/// Converts a shape into an absolute path with elliptical arcs.
+///
+/// Uses the same segments as `ShapeKind::to_path`, so shapes are converted
+/// just like with `Options::keep_shapes` and `Tree::shapes_to_paths`.
+fn shape_to_path(kind: &tree::ShapeKind) -> svgdom::Path {
+    let mut path = svgdom::Path::new();
+    for seg in kind.to_path(PathProfile::Extended, 0.0) {
+        let seg = match seg {
+            tree::PathSegment::MoveTo { x, y } => {
+                svgdom::PathSegment::MoveTo { abs: true, x, y }
+            }
+            tree::PathSegment::LineTo { x, y } => {
+                svgdom::PathSegment::LineTo { abs: true, x, y }
+            }
+            tree::PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                svgdom::PathSegment::CurveTo { abs: true, x1, y1, x2, y2, x, y }
+            }
+            tree::PathSegment::QuadTo { x1, y1, x, y } => {
+                svgdom::PathSegment::Quadratic { abs: true, x1, y1, x, y }
+            }
+            tree::PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+                svgdom::PathSegment::EllipticalArc {
+                    abs: true, rx, ry, x_axis_rotation, large_arc, sweep, x, y,
+                }
+            }
+            tree::PathSegment::ClosePath => {
+                svgdom::PathSegment::ClosePath { abs: true }
+            }
+        };
+        path.push(seg);
+    }
+
+    path
+}
+
+fn resolve(node: &svgdom::Node) -> Option<tree::ShapeKind> {
     match node.tag_id().unwrap() {
-        EId::Rect =>     convert_rect(node),
-        EId::Line =>     convert_line(node),
-        EId::Polyline => convert_polyline(node),
-        EId::Polygon =>  convert_polygon(node),
-        EId::Circle =>   convert_circle(node),
-        EId::Ellipse =>  convert_ellipse(node),
+        EId::Rect =>     resolve_rect(node),
+        EId::Line =>     resolve_line(node),
+        EId::Polyline => resolve_points(node, "Polyline", false),
+        EId::Polygon =>  resolve_points(node, "Polygon", true),
+        EId::Circle =>   resolve_circle(node),
+        EId::Ellipse =>  resolve_ellipse(node),
         _ => unreachable!(),
     }
 }
 
 // e-rect-001.svg
-fn convert_rect(node: &svgdom::Node) -> Option<svgdom::Path> {
+fn resolve_rect(node: &svgdom::Node) -> Option<tree::ShapeKind> {
     let attrs = node.attributes();
 
     // 'width' and 'height' attributes must be positive and non-zero.
@@ -93,34 +157,10 @@ fn convert_rect(node: &svgdom::Node) -> Option<svgdom::Path> {
     if ry > height / 2.0 { ry = height / 2.0; }
 
 
-    // Conversion according to https://www.w3.org/TR/SVG/shapes.html#RectElement
-    let path = if rx.fuzzy_eq(&0.0) {
-        svgdom::PathBuilder::with_capacity(5)
-            .move_to(x, y)
-            .hline_to(x + width)
-            .vline_to(y + height)
-            .hline_to(x)
-            .close_path()
-            .finalize()
-    } else {
-        // e-rect-004.svg
-        svgdom::PathBuilder::with_capacity(9)
-            .move_to(x + rx, y)
-            .line_to(x + width - rx, y)
-            .arc_to(rx, ry, 0.0, false, true, x + width, y + ry)
-            .line_to(x + width, y + height - ry)
-            .arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height)
-            .line_to(x + rx, y + height)
-            .arc_to(rx, ry, 0.0, false, true, x, y + height - ry)
-            .line_to(x, y + ry)
-            .arc_to(rx, ry, 0.0, false, true, x + rx, y)
-            .finalize()
-    };
-
-    Some(path)
+    Some(tree::ShapeKind::Rect { x, y, width, height, rx, ry })
 }
 
-fn convert_line(node: &svgdom::Node) -> Option<svgdom::Path> {
+fn resolve_line(node: &svgdom::Node) -> Option<tree::ShapeKind> {
     // e-line-001.svg
     // e-line-002.svg
     // e-line-003.svg
@@ -137,28 +177,10 @@ fn convert_line(node: &svgdom::Node) -> Option<svgdom::Path> {
     let x2 = attrs.get_number_or(AId::X2, 0.0);
     let y2 = attrs.get_number_or(AId::Y2, 0.0);
 
-    let path = svgdom::PathBuilder::new()
-        .move_to(x1, y1)
-        .line_to(x2, y2)
-        .finalize();
-
-    Some(path)
-}
-
-fn convert_polyline(node: &svgdom::Node) -> Option<svgdom::Path> {
-    points_to_path(node, "Polyline")
-}
-
-fn convert_polygon(node: &svgdom::Node) -> Option<svgdom::Path> {
-    if let Some(mut path) = points_to_path(node, "Polygon") {
-        path.push(svgdom::PathSegment::ClosePath { abs: true } );
-        Some(path)
-    } else {
-        None
-    }
+    Some(tree::ShapeKind::Line { x1, y1, x2, y2 })
 }
 
-fn points_to_path(node: &svgdom::Node, eid: &str) -> Option<svgdom::Path> {
+fn resolve_points(node: &svgdom::Node, eid: &str, closed: bool) -> Option<tree::ShapeKind> {
     let attrs = node.attributes();
     let points = if let Some(p) = attrs.get_points(AId::Points) {
         p
@@ -173,20 +195,11 @@ fn points_to_path(node: &svgdom::Node, eid: &str) -> Option<svgdom::Path> {
         return None;
     }
 
-    let mut path = svgdom::Path::new();
-    for (i, &(x, y)) in points.iter().enumerate() {
-        let seg = if i == 0 {
-            svgdom::PathSegment::MoveTo { abs: true, x, y }
-        } else {
-            svgdom::PathSegment::LineTo { abs: true, x, y }
-        };
-        path.push(seg);
-    }
-
-    Some(path)
+    let points = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+    Some(tree::ShapeKind::Polyline { points, closed })
 }
 
-fn convert_circle(node: &svgdom::Node) -> Option<svgdom::Path> {
+fn resolve_circle(node: &svgdom::Node) -> Option<tree::ShapeKind> {
     // e-circle-001.svg
     // e-circle-002.svg
     // e-circle-003.svg
@@ -205,10 +218,10 @@ fn convert_circle(node: &svgdom::Node) -> Option<svgdom::Path> {
         return None;
     }
 
-    Some(ellipse_to_path(cx, cy, r, r))
+    Some(tree::ShapeKind::Circle { cx, cy, r })
 }
 
-fn convert_ellipse(node: &svgdom::Node) -> Option<svgdom::Path> {
+fn resolve_ellipse(node: &svgdom::Node) -> Option<tree::ShapeKind> {
     // e-ellipse-001.svg
     // e-ellipse-002.svg
     // e-ellipse-003.svg
@@ -237,16 +250,5 @@ fn convert_ellipse(node: &svgdom::Node) -> Option<svgdom::Path> {
         return None;
     }
 
-    Some(ellipse_to_path(cx, cy, rx, ry))
-}
-
-fn ellipse_to_path(cx: f64, cy: f64, rx: f64, ry: f64) -> svgdom::Path {
-    svgdom::PathBuilder::with_capacity(6)
-        .move_to(cx + rx, cy)
-        .arc_to(rx, ry, 0.0, false, true, cx,      cy + ry)
-        .arc_to(rx, ry, 0.0, false, true, cx - rx, cy)
-        .arc_to(rx, ry, 0.0, false, true, cx,      cy - ry)
-        .arc_to(rx, ry, 0.0, false, true, cx + rx, cy)
-        .close_path()
-        .finalize()
+    Some(tree::ShapeKind::Ellipse { cx, cy, rx, ry })
 }
@@ -2,6 +2,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::iter::Peekable;
+use std::str::Chars;
+
 // external
 use svgdom;
 
@@ -258,7 +261,11 @@ fn convert_font(attrs: &svgdom::Attributes) -> tree::Font {
     let size = attrs.get_number_or(AId::FontSize, ::DEFAULT_FONT_SIZE);
     debug_assert!(size > 0.0);
 
-    let family = attrs.get_str_or(AId::FontFamily, ::DEFAULT_FONT_FAMILY).to_owned();
+    let mut family = parse_font_family(attrs.get_str_or(AId::FontFamily, ::DEFAULT_FONT_FAMILY));
+    if family.is_empty() {
+        warn!("'font-family' is invalid. Fallback to '{}'.", ::DEFAULT_FONT_FAMILY);
+        family.push(tree::FontFamily::Named(::DEFAULT_FONT_FAMILY.to_owned()));
+    }
 
     tree::Font {
         family,
@@ -269,3 +276,150 @@ fn convert_font(attrs: &svgdom::Attributes) -> tree::Font {
         stretch,
     }
 }
+
+/// Parses the `font-family` attribute value.
+///
+/// Invalid items are skipped.
+fn parse_font_family(text: &str) -> Vec<tree::FontFamily> {
+    let mut list = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        skip_spaces(&mut chars);
+
+        let family = match chars.peek().cloned() {
+            Some(quote @ '\'') | Some(quote @ '"') => {
+                chars.next();
+                let name = parse_font_family_string(&mut chars, quote);
+
+                // Nothing but spaces is allowed after a string.
+                skip_spaces(&mut chars);
+                match chars.peek() {
+                    Some(&',') | None => name.map(tree::FontFamily::Named),
+                    Some(_) => None,
+                }
+            }
+            Some(_) => parse_font_family_idents(&mut chars),
+            None => break,
+        };
+
+        match family {
+            Some(family) => list.push(family),
+            None => warn!("Invalid 'font-family' item in '{}'. Skipped.", text),
+        }
+
+        // Skip to the next item.
+        while let Some(c) = chars.next() {
+            if c == ',' {
+                break;
+            }
+        }
+    }
+
+    list
+}
+
+/// Parses a quoted family name.
+fn parse_font_family_string(chars: &mut Peekable<Chars>, quote: char) -> Option<String> {
+    let mut name = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                // An escaped new line is ignored.
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                } else {
+                    name.push(parse_escape(chars)?);
+                }
+            }
+            c if c == quote => return Some(name),
+            c => name.push(c),
+        }
+    }
+
+    // An unclosed string is closed at the end of the value.
+    Some(name)
+}
+
+/// Parses a family name as a sequence of identifiers or a generic family keyword.
+///
+/// Identifiers are joined by a single space.
+fn parse_font_family_idents(chars: &mut Peekable<Chars>) -> Option<tree::FontFamily> {
+    let mut idents: Vec<String> = Vec::new();
+    let mut has_escapes = false;
+    loop {
+        skip_spaces(chars);
+
+        let mut ident = String::new();
+        loop {
+            match chars.peek().cloned() {
+                Some('\\') => {
+                    chars.next();
+                    ident.push(parse_escape(chars)?);
+                    has_escapes = true;
+                }
+                // Strings cannot be a part of a name.
+                Some('\'') | Some('"') => return None,
+                Some(c) if c != ',' && !c.is_whitespace() => {
+                    chars.next();
+                    ident.push(c);
+                }
+                _ => break,
+            }
+        }
+
+        if ident.is_empty() {
+            break;
+        }
+
+        idents.push(ident);
+    }
+
+    if idents.is_empty() {
+        return None;
+    }
+
+    // Escaped keywords are family names.
+    if idents.len() == 1 && !has_escapes {
+        if let Some(generic) = tree::GenericFontFamily::from_name(&idents[0]) {
+            return Some(tree::FontFamily::Generic(generic));
+        }
+    }
+
+    Some(tree::FontFamily::Named(idents.join(" ")))
+}
+
+/// Parses an escape sequence after the `\`.
+fn parse_escape(chars: &mut Peekable<Chars>) -> Option<char> {
+    let mut code = String::new();
+    while code.len() < 6 {
+        match chars.peek().cloned() {
+            Some(c) if c.is_digit(16) => {
+                chars.next();
+                code.push(c);
+            }
+            _ => break,
+        }
+    }
+
+    if code.is_empty() {
+        return chars.next();
+    }
+
+    // A single space after a hex escape is a part of it.
+    if chars.peek().map(|c| c.is_whitespace()) == Some(true) {
+        chars.next();
+    }
+
+    // Zero and invalid code points are replaced with U+FFFD.
+    let code = u32::from_str_radix(&code, 16).ok()?;
+    match ::std::char::from_u32(code) {
+        Some(c) if c != '\0' => Some(c),
+        _ => Some('\u{FFFD}'),
+    }
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    while chars.peek().map(|c| c.is_whitespace()) == Some(true) {
+        chars.next();
+    }
+}
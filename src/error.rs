@@ -24,6 +24,11 @@ pub enum Error {
 
     /// Failed to parse an SVG data.
     ParsingFailed(svgdom::ParserError),
+
+    /// Failed to triangulate a path.
+    ///
+    /// Can occur on degenerate paths, because of rounding errors.
+    TessellationFailed,
 }
 
 impl fmt::Display for Error {
@@ -44,6 +49,9 @@ impl fmt::Display for Error {
             Error::ParsingFailed(ref e) => {
                 write!(f, "SVG data parsing failed cause {}", e)
             }
+            Error::TessellationFailed => {
+                write!(f, "failed to triangulate a path")
+            }
         }
     }
 }
@@ -5,7 +5,10 @@
 use std::f64;
 use std::fmt;
 
-use tree::Transform;
+use tree::{
+    FuzzyZero,
+    Transform,
+};
 
 /// Bounds `f64` number.
 #[inline]
@@ -62,6 +65,7 @@ impl Line {
 /// A 2D point representation.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Point {
     pub x: f64,
     pub y: f64,
@@ -96,6 +100,7 @@ impl fmt::Display for Point {
 /// A 2D size representation.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Size {
     pub width: f64,
     pub height: f64,
@@ -135,6 +140,7 @@ impl fmt::Display for Size {
 /// A rect representation.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rect {
     pub x: f64,
     pub y: f64,
@@ -153,12 +159,37 @@ impl Rect {
         Size::new(self.width, self.height)
     }
 
+    /// Returns a minimal rect that contains both rects.
+    pub fn union(&self, other: &Rect) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self::new(x, y, right - x, bottom - y)
+    }
+
     /// Returns transformed rect.
+    ///
+    /// All four corners are transformed, so the result is an axis-aligned
+    /// bounding box of the transformed rect. Which is important for rotation and skew.
     pub fn transform(&self, ts: Transform) -> Self {
-        let (x, y) = ts.apply(self.x, self.y);
-        let (sx, sy) = ts.get_scale();
-        let (w, h) = (self.width * sx, self.height * sy);
-        Self::new(x, y, w, h)
+        let points = [
+            ts.apply(self.x, self.y),
+            ts.apply(self.x + self.width, self.y),
+            ts.apply(self.x, self.y + self.height),
+            ts.apply(self.x + self.width, self.y + self.height),
+        ];
+
+        let (mut minx, mut miny) = points[0];
+        let (mut maxx, mut maxy) = points[0];
+        for &(x, y) in &points[1..] {
+            minx = minx.min(x);
+            miny = miny.min(y);
+            maxx = maxx.max(x);
+            maxy = maxy.max(y);
+        }
+
+        Self::new(minx, miny, maxx - minx, maxy - miny)
     }
 }
 
@@ -179,3 +210,478 @@ impl From<(f64, f64, f64, f64)> for Rect {
         Rect::new(v.0, v.1, v.2, v.3)
     }
 }
+
+
+/// A decomposed transform.
+///
+/// The original transform is equal to
+/// `translate(translate_x translate_y) rotate(angle) skewX(skew_x) scale(scale_x scale_y)`.
+///
+/// All angles are in degrees.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DecomposedTransform {
+    /// Translation along the X-axis.
+    pub translate_x: f64,
+    /// Translation along the Y-axis.
+    pub translate_y: f64,
+    /// Rotation angle.
+    pub angle: f64,
+    /// Skew angle along the X-axis.
+    pub skew_x: f64,
+    /// Scale along the X-axis.
+    ///
+    /// Can be negative when the transform is mirrored.
+    pub scale_x: f64,
+    /// Scale along the Y-axis.
+    pub scale_y: f64,
+}
+
+impl DecomposedTransform {
+    /// Composes a transform back.
+    pub fn to_transform(&self) -> Transform {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let skew = self.skew_x.to_radians().tan();
+
+        let mut ts = Transform::new(1.0, 0.0, 0.0, 1.0, self.translate_x, self.translate_y);
+        ts.append(&Transform::new(cos, sin, -sin, cos, 0.0, 0.0));
+        ts.append(&Transform::new(1.0, 0.0, skew, 1.0, 0.0, 0.0));
+        ts.append(&Transform::new(self.scale_x, 0.0, 0.0, self.scale_y, 0.0, 0.0));
+        ts
+    }
+}
+
+
+/// Additional `Transform` methods.
+pub trait TransformExt {
+    /// Returns an inverted transform.
+    ///
+    /// Returns `None` when the transform is not invertible.
+    fn inverse(&self) -> Option<Transform>;
+
+    /// Decomposes the transform into translate, rotate, skew and scale parts.
+    ///
+    /// Returns `None` when the transform is not invertible.
+    fn decompose(&self) -> Option<DecomposedTransform>;
+
+    /// Maps a point.
+    fn map_point(&self, p: Point) -> Point;
+
+    /// Maps a vector.
+    ///
+    /// Unlike `map_point`, translation is ignored.
+    fn map_vector(&self, v: Point) -> Point;
+}
+
+impl TransformExt for Transform {
+    fn inverse(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.is_fuzzy_zero() || !det.is_finite() {
+            return None;
+        }
+
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+        let e = -(a * self.e + c * self.f);
+        let f = -(b * self.e + d * self.f);
+
+        Some(Transform::new(a, b, c, d, e, f))
+    }
+
+    fn decompose(&self) -> Option<DecomposedTransform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.is_fuzzy_zero() || !det.is_finite() {
+            return None;
+        }
+
+        // Gram-Schmidt orthogonalization of the matrix columns.
+        let mut scale_x = (self.a * self.a + self.b * self.b).sqrt();
+        let (mut a, mut b) = (self.a / scale_x, self.b / scale_x);
+
+        let mut skew = a * self.c + b * self.d;
+        let (c, d) = (self.c - a * skew, self.d - b * skew);
+
+        let scale_y = (c * c + d * d).sqrt();
+        skew /= scale_y;
+
+        // Mirroring is represented by a negative X scale.
+        if det < 0.0 {
+            a = -a;
+            b = -b;
+            scale_x = -scale_x;
+            skew = -skew;
+        }
+
+        Some(DecomposedTransform {
+            translate_x: self.e,
+            translate_y: self.f,
+            angle: b.atan2(a).to_degrees(),
+            skew_x: skew.atan().to_degrees(),
+            scale_x,
+            scale_y,
+        })
+    }
+
+    fn map_point(&self, p: Point) -> Point {
+        self.apply(p.x, p.y).into()
+    }
+
+    fn map_vector(&self, v: Point) -> Point {
+        Point::new(self.a * v.x + self.c * v.y, self.b * v.x + self.d * v.y)
+    }
+}
+
+
+/// A cubic Bézier curve representation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CubicBezier {
+    pub p0: Point,
+    pub p1: Point,
+    pub p2: Point,
+    pub p3: Point,
+}
+
+impl CubicBezier {
+    /// Creates a new curve.
+    pub fn new(p0: Point, p1: Point, p2: Point, p3: Point) -> Self {
+        CubicBezier { p0, p1, p2, p3 }
+    }
+
+    /// Creates a curve from a quadratic one.
+    ///
+    /// The conversion is exact.
+    pub fn from_quad(p0: Point, p1: Point, p2: Point) -> Self {
+        CubicBezier {
+            p0,
+            p1: Point::new(p0.x + (p1.x - p0.x) * 2.0 / 3.0, p0.y + (p1.y - p0.y) * 2.0 / 3.0),
+            p2: Point::new(p2.x + (p1.x - p2.x) * 2.0 / 3.0, p2.y + (p1.y - p2.y) * 2.0 / 3.0),
+            p3: p2,
+        }
+    }
+
+    /// Returns a point on the curve at `t`.
+    pub fn eval(&self, t: f64) -> Point {
+        let mt = 1.0 - t;
+        let a = mt * mt * mt;
+        let b = 3.0 * mt * mt * t;
+        let c = 3.0 * mt * t * t;
+        let d = t * t * t;
+
+        Point::new(
+            a * self.p0.x + b * self.p1.x + c * self.p2.x + d * self.p3.x,
+            a * self.p0.y + b * self.p1.y + c * self.p2.y + d * self.p3.y,
+        )
+    }
+
+    /// Returns a tight curve bounding box.
+    ///
+    /// Unlike the control points bbox, takes only the curve extremes into account.
+    pub fn bbox(&self) -> Rect {
+        let mut min = Point::new(self.p0.x.min(self.p3.x), self.p0.y.min(self.p3.y));
+        let mut max = Point::new(self.p0.x.max(self.p3.x), self.p0.y.max(self.p3.y));
+
+        let mut roots = [0.0; 4];
+        let x_len = cubic_extremes(self.p0.x, self.p1.x, self.p2.x, self.p3.x, &mut roots[0..2]);
+        let y_len = cubic_extremes(self.p0.y, self.p1.y, self.p2.y, self.p3.y, &mut roots[2..4]);
+
+        let roots = roots[0..x_len].iter().chain(roots[2..2 + y_len].iter());
+        for t in roots {
+            let p = self.eval(*t);
+            min.x = min.x.min(p.x);
+            min.y = min.y.min(p.y);
+            max.x = max.x.max(p.x);
+            max.y = max.y.max(p.y);
+        }
+
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// Returns a curve derivative at `t`.
+    pub fn derivative(&self, t: f64) -> Point {
+        let mt = 1.0 - t;
+        let a = 3.0 * mt * mt;
+        let b = 6.0 * mt * t;
+        let c = 3.0 * t * t;
+
+        Point::new(
+            a * (self.p1.x - self.p0.x) + b * (self.p2.x - self.p1.x) + c * (self.p3.x - self.p2.x),
+            a * (self.p1.y - self.p0.y) + b * (self.p2.y - self.p1.y) + c * (self.p3.y - self.p2.y),
+        )
+    }
+
+    /// Returns a curve direction at `t`.
+    ///
+    /// Unlike `derivative`, handles control points that are equal to the end points.
+    /// Returns a zero vector only when all the points are equal.
+    pub fn tangent(&self, t: f64) -> Point {
+        let d = self.derivative(t);
+        if !d.x.is_fuzzy_zero() || !d.y.is_fuzzy_zero() {
+            return d;
+        }
+
+        let (from, to) = if t < 0.5 {
+            (self.p0, if self.p2 != self.p0 { self.p2 } else { self.p3 })
+        } else {
+            (if self.p1 != self.p3 { self.p1 } else { self.p0 }, self.p3)
+        };
+
+        Point::new(to.x - from.x, to.y - from.y)
+    }
+
+    /// Splits the curve at `t`.
+    pub fn split(&self, t: f64) -> (CubicBezier, CubicBezier) {
+        let lerp = |a: Point, b: Point| Point::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+
+        let p01 = lerp(self.p0, self.p1);
+        let p12 = lerp(self.p1, self.p2);
+        let p23 = lerp(self.p2, self.p3);
+        let p012 = lerp(p01, p12);
+        let p123 = lerp(p12, p23);
+        let p = lerp(p012, p123);
+
+        (CubicBezier::new(self.p0, p01, p012, p), CubicBezier::new(p, p123, p23, self.p3))
+    }
+
+    /// Returns a curve length.
+    pub fn length(&self) -> f64 {
+        self.length_impl(0)
+    }
+
+    fn length_impl(&self, depth: u8) -> f64 {
+        let chord = distance(self.p0, self.p3);
+        let polygon = distance(self.p0, self.p1) + distance(self.p1, self.p2)
+                    + distance(self.p2, self.p3);
+
+        // The curve length is always between the chord and the control polygon lengths.
+        if polygon - chord <= polygon * 1e-6 || depth == 16 {
+            return (chord + polygon) / 2.0;
+        }
+
+        let (left, right) = self.split(0.5);
+        left.length_impl(depth + 1) + right.length_impl(depth + 1)
+    }
+
+    /// Returns `t` at which the curve has the specified length.
+    ///
+    /// `length` will be clamped to the curve length.
+    pub fn t_at_length(&self, length: f64) -> f64 {
+        if length <= 0.0 {
+            return 0.0;
+        }
+
+        let (mut min, mut max) = (0.0, 1.0);
+        for _ in 0..40 {
+            let t = (min + max) / 2.0;
+            if self.split(t).0.length() < length {
+                min = t;
+            } else {
+                max = t;
+            }
+        }
+
+        (min + max) / 2.0
+    }
+
+    /// Appends points of the flattened curve to the `points`.
+    ///
+    /// The start point is not appended. `tolerance` is the maximum allowed
+    /// distance between the curve and the resulting polyline.
+    pub fn flatten(&self, tolerance: f64, points: &mut Vec<Point>) {
+        // The deviation of the uniformly subdivided cubic curve is bounded
+        // by `3/4 * max(second difference) / n^2`.
+        let ddx = (self.p0.x - 2.0 * self.p1.x + self.p2.x).abs()
+            .max((self.p1.x - 2.0 * self.p2.x + self.p3.x).abs());
+        let ddy = (self.p0.y - 2.0 * self.p1.y + self.p2.y).abs()
+            .max((self.p1.y - 2.0 * self.p2.y + self.p3.y).abs());
+        let dd = (ddx * ddx + ddy * ddy).sqrt();
+
+        let n = (0.75 * dd / tolerance).sqrt().ceil().max(1.0).min(1000.0) as usize;
+        for i in 1..(n + 1) {
+            points.push(self.eval(i as f64 / n as f64));
+        }
+    }
+}
+
+/// An SVG elliptical arc representation.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct EllipticalArc {
+    pub from: Point,
+    pub to: Point,
+    pub rx: f64,
+    pub ry: f64,
+    /// An X axis rotation in degrees.
+    pub x_axis_rotation: f64,
+    pub large_arc: bool,
+    pub sweep: bool,
+}
+
+impl EllipticalArc {
+    /// Appends cubic curves that approximate the arc to the `curves`.
+    ///
+    /// Follows the SVG implementation notes: nothing is appended when the end points
+    /// are equal and a straight curve is appended when one of the radii is zero.
+    /// Radii that are too small to reach the end point are scaled up.
+    ///
+    /// Each curve covers at most 90 degrees. `tolerance` is the maximum allowed
+    /// distance between the arc and the curves. It's clamped to be at least
+    /// a billionth of the arc radius, so non-positive values are allowed.
+    pub fn to_cubics(&self, tolerance: f64, curves: &mut Vec<CubicBezier>) {
+        let (from, to) = (self.from, self.to);
+        if from == to {
+            return;
+        }
+
+        let mut rx = self.rx.abs();
+        let mut ry = self.ry.abs();
+        if rx.is_fuzzy_zero() || ry.is_fuzzy_zero() {
+            curves.push(CubicBezier::new(from, from, to, to));
+            return;
+        }
+
+        let (sin_phi, cos_phi) = self.x_axis_rotation.to_radians().sin_cos();
+
+        // Conversion from the endpoint to the center parametrization.
+        let dx2 = (from.x - to.x) / 2.0;
+        let dy2 = (from.y - to.y) / 2.0;
+        let x1 = cos_phi * dx2 + sin_phi * dy2;
+        let y1 = -sin_phi * dx2 + cos_phi * dy2;
+
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            let s = lambda.sqrt();
+            rx *= s;
+            ry *= s;
+        }
+
+        let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coef = (num / den).max(0.0).sqrt();
+        if self.large_arc == self.sweep {
+            coef = -coef;
+        }
+
+        let cx1 = coef * rx * y1 / ry;
+        let cy1 = -coef * ry * x1 / rx;
+        let cx = cos_phi * cx1 - sin_phi * cy1 + (from.x + to.x) / 2.0;
+        let cy = sin_phi * cx1 + cos_phi * cy1 + (from.y + to.y) / 2.0;
+
+        let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+        let start_angle = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut sweep_angle = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+        if !self.sweep && sweep_angle > 0.0 {
+            sweep_angle -= 2.0 * f64::consts::PI;
+        } else if self.sweep && sweep_angle < 0.0 {
+            sweep_angle += 2.0 * f64::consts::PI;
+        }
+
+        // The radial error of a curve that approximates a circular arc of `a` radians
+        // is `r * 2/27 * sin(a/4)^6 / cos(a/4)^2`.
+        let max_radius = rx.max(ry);
+        // A non-positive tolerance would require an infinite number of curves.
+        let tolerance = tolerance.max(max_radius * 1e-9);
+        let error = |a: f64| {
+            let (sin, cos) = (a / 4.0).sin_cos();
+            max_radius * 2.0 / 27.0 * sin.powi(6) / (cos * cos)
+        };
+
+        let mut n = (sweep_angle.abs() / f64::consts::FRAC_PI_2).ceil().max(1.0) as usize;
+        while n < 1000 && error(sweep_angle.abs() / n as f64) > tolerance {
+            n += 1;
+        }
+
+        let step = sweep_angle / n as f64;
+        let k = 4.0 / 3.0 * (step / 4.0).tan();
+        let map = |x: f64, y: f64| {
+            Point::new(cx + rx * cos_phi * x - ry * sin_phi * y,
+                       cy + rx * sin_phi * x + ry * cos_phi * y)
+        };
+
+        let mut p0 = from;
+        for i in 0..n {
+            let a1 = start_angle + step * i as f64;
+            let a2 = a1 + step;
+            let (sin1, cos1) = a1.sin_cos();
+            let (sin2, cos2) = a2.sin_cos();
+
+            // The end point is used as is to prevent rounding errors.
+            let p3 = if i == n - 1 { to } else { map(cos2, sin2) };
+            let p1 = map(cos1 - k * sin1, sin1 + k * cos1);
+            let p2 = map(cos2 + k * sin2, sin2 - k * cos2);
+            curves.push(CubicBezier::new(p0, p1, p2, p3));
+            p0 = p3;
+        }
+    }
+}
+
+/// Applies a transform to an elliptical arc.
+///
+/// Returns new radii, X axis rotation and sweep flag. The end point must be transformed separately.
+pub(crate) fn transform_arc(
+    ts: &Transform,
+    rx: f64,
+    ry: f64,
+    x_axis_rotation: f64,
+    sweep: bool,
+) -> (f64, f64, f64, bool) {
+    // The transformed ellipse axes are conjugate diameters of the new ellipse.
+    let (sin, cos) = x_axis_rotation.to_radians().sin_cos();
+    let u = ts.map_vector(Point::new(rx * cos, rx * sin));
+    let v = ts.map_vector(Point::new(-ry * sin, ry * cos));
+
+    // The new axes are the eigenvectors of the `M * M^T` matrix.
+    let a = u.x * u.x + v.x * v.x;
+    let b = u.x * u.y + v.x * v.y;
+    let c = u.y * u.y + v.y * v.y;
+    let mid = (a + c) / 2.0;
+    let d = (((a - c) / 2.0).powi(2) + b * b).sqrt();
+
+    let new_rx = (mid + d).sqrt();
+    let new_ry = (mid - d).max(0.0).sqrt();
+    let angle = (2.0 * b).atan2(a - c).to_degrees() / 2.0;
+
+    // A mirroring transform changes the arc direction.
+    let is_mirrored = ts.a * ts.d - ts.b * ts.c < 0.0;
+
+    (new_rx, new_ry, angle, sweep != is_mirrored)
+}
+
+/// Returns a distance between two points.
+pub(crate) fn distance(a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Finds the `t` values of the cubic curve extremes along a single axis.
+///
+/// Only values in the (0, 1) range will be written. Returns the number of written values.
+fn cubic_extremes(p0: f64, p1: f64, p2: f64, p3: f64, roots: &mut [f64]) -> usize {
+    // Coefficients of the derivative: a*t^2 + b*t + c.
+    let a = 3.0 * (-p0 + 3.0 * p1 - 3.0 * p2 + p3);
+    let b = 6.0 * (p0 - 2.0 * p1 + p2);
+    let c = 3.0 * (p1 - p0);
+
+    let mut candidates = [-1.0; 2];
+    if a.abs() < 1e-12 {
+        if b.abs() > 1e-12 {
+            candidates[0] = -c / b;
+        }
+    } else {
+        let d = b * b - 4.0 * a * c;
+        if d >= 0.0 {
+            let d = d.sqrt();
+            candidates[0] = (-b + d) / (2.0 * a);
+            candidates[1] = (-b - d) / (2.0 * a);
+        }
+    }
+
+    let mut len = 0;
+    for t in &candidates {
+        if *t > 0.0 && *t < 1.0 {
+            roots[len] = *t;
+            len += 1;
+        }
+    }
+
+    len
+}
@@ -70,11 +70,12 @@ Full spec can be found [here](https://github.com/RazrFalcon/usvg/blob/master/doc
 
 
 pub extern crate svgdom;
-pub extern crate lyon_geom;
 extern crate base64;
 extern crate libflate;
+#[cfg(feature = "text")] extern crate ttf_parser;
 extern crate unicode_segmentation;
 #[macro_use] extern crate log;
+#[cfg(feature = "serde")] #[macro_use] extern crate serde;
 
 
 /// Task, return value.
@@ -124,6 +125,9 @@ macro_rules! debug_panic {
 }
 
 
+pub mod path_utils;
+#[cfg(feature = "tessellation")] pub mod tessellation;
+#[cfg(feature = "text")] pub mod text;
 pub mod utils;
 mod convert;
 mod error;
@@ -4,6 +4,16 @@
 
 use std::path::PathBuf;
 
+// self
+use tree::{
+    FontFamily,
+    GenericFontFamily,
+};
+
+
+/// The default `Options::arc_tolerance`.
+pub(crate) const DEFAULT_ARC_TOLERANCE: f64 = 0.01;
+
 
 /// Processing options.
 pub struct Options {
@@ -22,6 +32,75 @@ pub struct Options {
     /// If set to `true`, all non-empty groups with `id` attribute will not
     /// be removed.
     pub keep_named_groups: bool,
+
+    /// Family names for the generic font families.
+    ///
+    /// Generic families are preserved in the `Font::family` list,
+    /// so this mapping is used only by the text conversion.
+    pub font_families: GenericFontFamilies,
+
+    /// Keep basic shapes.
+    ///
+    /// If set to `true`, `rect`, `circle`, `ellipse`, `line`, `polyline` and `polygon`
+    /// elements will be converted into `NodeKind::Shape` and not into paths.
+    ///
+    /// Passes that change the geometry, like `outline_strokes`, will still convert
+    /// the affected shapes into paths, using `path_profile` and `arc_tolerance`.
+    pub keep_shapes: bool,
+
+    /// Path segments profile.
+    ///
+    /// Controls which segments the output paths can contain.
+    pub path_profile: PathProfile,
+
+    /// Elliptical arcs approximation tolerance.
+    ///
+    /// The maximum distance between an arc and the curves that replace it,
+    /// in the path coordinates. Arcs are split into segments of at most 90 degrees
+    /// in any case, so this is important only for arcs with a large radius.
+    ///
+    /// Not used with `PathProfile::Extended`, which preserves arcs.
+    ///
+    /// Values smaller than a billionth of the arc radius, including zero
+    /// and negative ones, are clamped to it.
+    ///
+    /// Default: 0.01
+    pub arc_tolerance: f64,
+
+    /// Converts strokes into filled paths.
+    ///
+    /// Contains a flattening tolerance, since the resulting paths consist of lines only.
+    /// See `Tree::outline_strokes` for details.
+    ///
+    /// Disabled by default.
+    pub outline_strokes: Option<f64>,
+
+    /// Applies dash patterns to the paths geometry.
+    ///
+    /// See `Tree::expand_dashes` for details.
+    ///
+    /// Ignored when `outline_strokes` is set, since dashes are already applied during outlining.
+    pub expand_dashes: bool,
+
+    /// Replaces clip paths with the actual clipping of the paths geometry.
+    ///
+    /// Contains a flattening tolerance, since the clipped paths consist of lines only.
+    /// See `Tree::apply_clip_paths` for details.
+    ///
+    /// Disabled by default.
+    pub apply_clip_paths: Option<f64>,
+
+    /// Simplifies paths with the specified tolerance in the canvas coordinates.
+    ///
+    /// See `Tree::simplify_paths` for details.
+    ///
+    /// Disabled by default.
+    pub simplify_paths: Option<f64>,
+
+    /// Removes redundant path segments without changing the geometry.
+    ///
+    /// See `Tree::normalize_paths` for details.
+    pub normalize_paths: bool,
 }
 
 impl Default for Options {
@@ -30,6 +109,85 @@ impl Default for Options {
             path: None,
             dpi: 96.0,
             keep_named_groups: false,
+            font_families: GenericFontFamilies::default(),
+            keep_shapes: false,
+            path_profile: PathProfile::Default,
+            arc_tolerance: DEFAULT_ARC_TOLERANCE,
+            outline_strokes: None,
+            expand_dashes: false,
+            apply_clip_paths: None,
+            simplify_paths: None,
+            normalize_paths: false,
+        }
+    }
+}
+
+
+/// A path segments profile.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathProfile {
+    /// Paths will contain only `MoveTo`, `LineTo`, `CurveTo` and `ClosePath` segments.
+    Default,
+    /// Paths will contain only `MoveTo`, `LineTo` and `ClosePath` segments.
+    ///
+    /// Curves will be flattened using the specified tolerance,
+    /// which is the maximum distance between a curve and its polyline
+    /// in the path coordinates.
+    LinesOnly {
+        /// Flattening tolerance.
+        tolerance: f64,
+    },
+    /// Like `Default`, but paths will also contain `QuadTo` and `ArcTo` segments
+    /// instead of curves that approximate them.
+    Extended,
+}
+
+
+/// Family names for the generic font families.
+#[allow(missing_docs)]
+#[derive(Clone, Debug)]
+pub struct GenericFontFamilies {
+    pub serif: String,
+    pub sans_serif: String,
+    pub monospace: String,
+    pub cursive: String,
+    pub fantasy: String,
+    pub system_ui: String,
+}
+
+impl GenericFontFamilies {
+    /// Returns a family name for the generic family.
+    pub fn get(&self, family: GenericFontFamily) -> &str {
+        match family {
+            GenericFontFamily::Serif => &self.serif,
+            GenericFontFamily::SansSerif => &self.sans_serif,
+            GenericFontFamily::Monospace => &self.monospace,
+            GenericFontFamily::Cursive => &self.cursive,
+            GenericFontFamily::Fantasy => &self.fantasy,
+            GenericFontFamily::SystemUi => &self.system_ui,
+        }
+    }
+
+    /// Converts a list of font families into a list of family names.
+    pub fn resolve<'a>(&'a self, families: &'a [FontFamily]) -> Vec<&'a str> {
+        families.iter().map(|family| {
+            match *family {
+                FontFamily::Named(ref name) => name.as_str(),
+                FontFamily::Generic(generic) => self.get(generic),
+            }
+        }).collect()
+    }
+}
+
+impl Default for GenericFontFamilies {
+    fn default() -> Self {
+        GenericFontFamilies {
+            serif: "Times New Roman".to_string(),
+            sans_serif: "Arial".to_string(),
+            monospace: "Courier New".to_string(),
+            cursive: "Comic Sans MS".to_string(),
+            fantasy: "Impact".to_string(),
+            system_ui: "Arial".to_string(),
         }
     }
 }
@@ -0,0 +1,442 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+// self
+use super::*;
+
+
+/// A boolean path operation.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BooleanOp {
+    Union,
+    Intersection,
+    Difference,
+    Xor,
+}
+
+impl BooleanOp {
+    fn apply(&self, a: bool, b: bool) -> bool {
+        match *self {
+            BooleanOp::Union => a || b,
+            BooleanOp::Intersection => a && b,
+            BooleanOp::Difference => a && !b,
+            BooleanOp::Xor => a != b,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Edge {
+    p0: Point,
+    p1: Point,
+}
+
+/// Applies a boolean operation to the filled areas of two paths.
+///
+/// Each path is filled using its own fill rule. All subpaths are treated as closed.
+///
+/// Curves are flattened, so the result contains only `MoveTo`, `LineTo`
+/// and `ClosePath` segments. `tolerance` is the maximum distance between a curve
+/// and its polyline and must be positive.
+///
+/// The result must be filled using the `NonZero` rule. All its subpaths
+/// are oriented in a way that a winding number inside the area is always 1.
+pub fn boolean(
+    a: &[PathSegment],
+    a_rule: FillRule,
+    b: &[PathSegment],
+    b_rule: FillRule,
+    op: BooleanOp,
+    tolerance: f64,
+) -> Vec<PathSegment> {
+    let a_edges = edges(a, tolerance);
+    let b_edges = edges(b, tolerance);
+
+    let mut all_edges = a_edges.clone();
+    all_edges.extend_from_slice(&b_edges);
+
+    if all_edges.is_empty() {
+        return Vec::new();
+    }
+
+    // The distance to the left and to the right of an edge at which
+    // the filling will be checked.
+    let eps = {
+        let (mut min, mut max) = (all_edges[0].p0, all_edges[0].p0);
+        for e in &all_edges {
+            for p in &[e.p0, e.p1] {
+                min = Point::new(min.x.min(p.x), min.y.min(p.y));
+                max = Point::new(max.x.max(p.x), max.y.max(p.y));
+            }
+        }
+
+        distance(min, max) * 1e-8
+    };
+
+    // Split edges don't intersect each other, which is required by `WindingIndex`.
+    let split = split_edges(&all_edges);
+    let a_index = WindingIndex::new(split.iter().filter(|v| v.0 < a_edges.len()).map(|v| v.1));
+    let b_index = WindingIndex::new(split.iter().filter(|v| v.0 >= a_edges.len()).map(|v| v.1));
+
+    let is_result_filled = |p: Point| {
+        op.apply(is_filled(a_rule, a_index.winding(p)), is_filled(b_rule, b_index.winding(p)))
+    };
+
+    let mut fragments = Vec::new();
+    for (_, e) in split {
+        let len = distance(e.p0, e.p1);
+        if !(len > eps) {
+            continue;
+        }
+
+        let m = Point::new((e.p0.x + e.p1.x) / 2.0, (e.p0.y + e.p1.y) / 2.0);
+        let n = Point::new(-(e.p1.y - e.p0.y) / len * eps, (e.p1.x - e.p0.x) / len * eps);
+        let left = Point::new(m.x + n.x, m.y + n.y);
+        let right = Point::new(m.x - n.x, m.y - n.y);
+
+        let is_left_filled = is_result_filled(left);
+        let is_right_filled = is_result_filled(right);
+
+        // Only the result boundary is kept. The filled area must be on the left.
+        if is_left_filled && !is_right_filled {
+            fragments.push(e);
+        } else if !is_left_filled && is_right_filled {
+            fragments.push(Edge { p0: e.p1, p1: e.p0 });
+        }
+    }
+
+    link_fragments(fragments)
+}
+
+/// Converts segments into a list of edges.
+///
+/// All subpaths are closed. Subpaths with non-finite coordinates are skipped.
+fn edges(segments: &[PathSegment], tolerance: f64) -> Vec<Edge> {
+    let mut list = Vec::new();
+    for polyline in polylines(segments, tolerance) {
+        let points = &polyline.points;
+        if !points.iter().all(|p| p.x.is_finite() && p.y.is_finite()) {
+            continue;
+        }
+
+        for i in 0..points.len() {
+            let p0 = points[i];
+            let p1 = points[(i + 1) % points.len()];
+            if p0 != p1 {
+                list.push(Edge { p0, p1 });
+            }
+        }
+    }
+
+    list
+}
+
+/// Splits edges at all the intersection points.
+///
+/// Intersection points are shared between edges, so the resulting edges
+/// will have bitwise equal end points. Each edge is returned with the index
+/// of the original one.
+fn split_edges(edges: &[Edge]) -> Vec<(usize, Edge)> {
+    // `t` values and points at which the edges should be split.
+    let mut splits: Vec<Vec<(f64, Point)>> = vec![Vec::new(); edges.len()];
+
+    // Sweep along the X axis, so only edges with overlapping X ranges are tested.
+    let min_x = |e: &Edge| e.p0.x.min(e.p1.x);
+    let max_x = |e: &Edge| e.p0.x.max(e.p1.x);
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|a, b| {
+        min_x(&edges[*a]).partial_cmp(&min_x(&edges[*b])).unwrap_or(Ordering::Equal)
+    });
+
+    for (k, &i) in order.iter().enumerate() {
+        let end = max_x(&edges[i]);
+        for &j in &order[(k + 1)..] {
+            if min_x(&edges[j]) > end {
+                break;
+            }
+
+            // Keep the same edges order as in the original list.
+            if i < j {
+                intersect(edges, i, j, &mut splits);
+            } else {
+                intersect(edges, j, i, &mut splits);
+            }
+        }
+    }
+
+    let mut list = Vec::new();
+    for (idx, (e, points)) in edges.iter().zip(splits.iter_mut()).enumerate() {
+        points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        let mut prev = e.p0;
+        for &(_, p) in points.iter() {
+            if p != prev {
+                list.push((idx, Edge { p0: prev, p1: p }));
+                prev = p;
+            }
+        }
+
+        if prev != e.p1 {
+            list.push((idx, Edge { p0: prev, p1: e.p1 }));
+        }
+    }
+
+    list
+}
+
+fn intersect(edges: &[Edge], i: usize, j: usize, splits: &mut [Vec<(f64, Point)>]) {
+    const T_EPS: f64 = 1e-9;
+
+    let e1 = edges[i];
+    let e2 = edges[j];
+
+    // Quick rejection.
+    if e1.p0.x.max(e1.p1.x) < e2.p0.x.min(e2.p1.x) || e2.p0.x.max(e2.p1.x) < e1.p0.x.min(e1.p1.x)
+        || e1.p0.y.max(e1.p1.y) < e2.p0.y.min(e2.p1.y) || e2.p0.y.max(e2.p1.y) < e1.p0.y.min(e1.p1.y)
+    {
+        return;
+    }
+
+    let r = Point::new(e1.p1.x - e1.p0.x, e1.p1.y - e1.p0.y);
+    let s = Point::new(e2.p1.x - e2.p0.x, e2.p1.y - e2.p0.y);
+    let qp = Point::new(e2.p0.x - e1.p0.x, e2.p0.y - e1.p0.y);
+    let denom = cross(r, s);
+
+    let is_inner = |t: f64| t > T_EPS && t < 1.0 - T_EPS;
+    // Projects a point onto an edge.
+    let param = |e: &Edge, d: Point, p: Point| {
+        ((p.x - e.p0.x) * d.x + (p.y - e.p0.y) * d.y) / (d.x * d.x + d.y * d.y)
+    };
+
+    let r_len = (r.x * r.x + r.y * r.y).sqrt();
+    let s_len = (s.x * s.x + s.y * s.y).sqrt();
+
+    if denom.abs() > 1e-12 * r_len * s_len {
+        let t = cross(qp, s) / denom;
+        let u = cross(qp, r) / denom;
+        if t < -T_EPS || t > 1.0 + T_EPS || u < -T_EPS || u > 1.0 + T_EPS {
+            return;
+        }
+
+        match (is_inner(t), is_inner(u)) {
+            (true, true) => {
+                let p = Point::new(e1.p0.x + r.x * t, e1.p0.y + r.y * t);
+                splits[i].push((t, p));
+                splits[j].push((u, p));
+            }
+            (true, false) => {
+                // An end point of the second edge touches the first one.
+                let p = if u < 0.5 { e2.p0 } else { e2.p1 };
+                splits[i].push((t, p));
+            }
+            (false, true) => {
+                let p = if t < 0.5 { e1.p0 } else { e1.p1 };
+                splits[j].push((u, p));
+            }
+            (false, false) => {}
+        }
+    } else if cross(qp, r).abs() <= 1e-12 * r_len * (qp.x * qp.x + qp.y * qp.y).sqrt().max(r_len) {
+        // Collinear edges. Split each one at the end points of the other.
+        for &p in &[e2.p0, e2.p1] {
+            let t = param(&e1, r, p);
+            if is_inner(t) {
+                splits[i].push((t, p));
+            }
+        }
+
+        for &p in &[e1.p0, e1.p1] {
+            let u = param(&e2, s, p);
+            if is_inner(u) {
+                splits[j].push((u, p));
+            }
+        }
+    }
+}
+
+fn cross(a: Point, b: Point) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+fn is_filled(rule: FillRule, winding: i32) -> bool {
+    match rule {
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+/// A non-horizontal edge directed from top to bottom.
+#[derive(Clone, Copy)]
+struct BandEdge {
+    top: Point,
+    bottom: Point,
+    /// 1 when the original edge goes down and -1 otherwise.
+    dir: i32,
+}
+
+impl BandEdge {
+    fn x_at(&self, y: f64) -> f64 {
+        let t = (y - self.top.y) / (self.bottom.y - self.top.y);
+        self.top.x + (self.bottom.x - self.top.x) * t
+    }
+
+    /// Checks that the edge is to the right of `p`.
+    fn is_right_of(&self, p: Point) -> bool {
+        let (a, b) = (self.top, self.bottom);
+        (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y) > 0.0
+    }
+}
+
+/// Calculates winding numbers using horizontal bands.
+///
+/// Bands are formed by the Y coordinates of all edge end points, so each edge
+/// either spans the whole band or doesn't cross it at all. Since edges do not
+/// intersect each other, their order along the X axis is the same in the whole band.
+/// This allows finding edges to the right of a point using a binary search,
+/// instead of checking all of them.
+struct WindingIndex {
+    /// Band boundaries. The band `i` is between `ys[i]` and `ys[i + 1]`.
+    ys: Vec<f64>,
+    /// Edges in each band, ordered from left to right.
+    bands: Vec<Vec<BandEdge>>,
+    /// Winding numbers in each band. The value `j` is for a point
+    /// between the edges `j - 1` and `j`.
+    windings: Vec<Vec<i32>>,
+}
+
+impl WindingIndex {
+    /// Creates a new index. Edges must not intersect each other.
+    fn new<I>(edges: I) -> Self
+        where I: Iterator<Item=Edge>
+    {
+        let mut list = Vec::new();
+        for e in edges {
+            if e.p0.y < e.p1.y {
+                list.push(BandEdge { top: e.p0, bottom: e.p1, dir: 1 });
+            } else if e.p0.y > e.p1.y {
+                list.push(BandEdge { top: e.p1, bottom: e.p0, dir: -1 });
+            }
+        }
+
+        let mut ys = Vec::with_capacity(list.len() * 2);
+        for e in &list {
+            ys.push(e.top.y);
+            ys.push(e.bottom.y);
+        }
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        ys.dedup();
+
+        let mut bands = vec![Vec::new(); ys.len().saturating_sub(1)];
+        {
+            let band_idx = |y: f64| {
+                ys.binary_search_by(|v| v.partial_cmp(&y).unwrap_or(Ordering::Equal)).unwrap_or_else(|i| i)
+            };
+
+            for e in &list {
+                for band in &mut bands[band_idx(e.top.y)..band_idx(e.bottom.y)] {
+                    band.push(*e);
+                }
+            }
+        }
+
+        let mut windings = Vec::with_capacity(bands.len());
+        for (i, band) in bands.iter_mut().enumerate() {
+            let y = (ys[i] + ys[i + 1]) / 2.0;
+            band.sort_by(|a, b| a.x_at(y).partial_cmp(&b.x_at(y)).unwrap_or(Ordering::Equal));
+
+            let mut sums = vec![0; band.len() + 1];
+            for (j, e) in band.iter().enumerate().rev() {
+                sums[j] = sums[j + 1] + e.dir;
+            }
+            windings.push(sums);
+        }
+
+        WindingIndex { ys, bands, windings }
+    }
+
+    /// Returns the winding number at the point.
+    ///
+    /// Like with the ray casting, an edge is taken into account only when
+    /// the point is not above its top end and is above its bottom end.
+    fn winding(&self, p: Point) -> i32 {
+        let band = match self.ys.binary_search_by(|v| v.partial_cmp(&p.y).unwrap_or(Ordering::Equal)) {
+            Ok(i) => i,
+            Err(0) => return 0,
+            Err(i) => i - 1,
+        };
+
+        if band >= self.bands.len() {
+            return 0;
+        }
+
+        // The first edge to the right of the point.
+        let edges = &self.bands[band];
+        let (mut lo, mut hi) = (0, edges.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if edges[mid].is_right_of(p) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        self.windings[band][lo]
+    }
+}
+
+/// Links boundary fragments into closed subpaths.
+fn link_fragments(fragments: Vec<Edge>) -> Vec<PathSegment> {
+    fn key(p: Point) -> (u64, u64) {
+        (p.x.to_bits(), p.y.to_bits())
+    }
+
+    // Coincident edges of both paths produce duplicated fragments.
+    let mut unique = HashSet::new();
+    let fragments: Vec<Edge> = fragments.into_iter()
+        .filter(|e| unique.insert((key(e.p0), key(e.p1))))
+        .collect();
+
+    let mut by_start: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+    for (i, e) in fragments.iter().enumerate() {
+        by_start.entry(key(e.p0)).or_insert_with(Vec::new).push(i);
+    }
+
+    let mut is_used = vec![false; fragments.len()];
+    let mut segments = Vec::new();
+    for i in 0..fragments.len() {
+        if is_used[i] {
+            continue;
+        }
+
+        let start = fragments[i].p0;
+        segments.push(PathSegment::MoveTo { x: start.x, y: start.y });
+
+        let mut idx = i;
+        loop {
+            is_used[idx] = true;
+            let p = fragments[idx].p1;
+            if p == start {
+                break;
+            }
+
+            segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+
+            let next = by_start.get(&key(p))
+                .and_then(|list| list.iter().find(|n| !is_used[**n]).cloned());
+            match next {
+                Some(n) => idx = n,
+                None => break,
+            }
+        }
+
+        segments.push(PathSegment::ClosePath);
+    }
+
+    segments
+}
@@ -0,0 +1,188 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use super::*;
+
+
+/// The maximum number of dashes a path can be split into.
+const MAX_DASHES: f64 = 100000.0;
+
+/// Applies a dash pattern to the path.
+///
+/// Each dash will be converted into a separate subpath. Curves are preserved.
+///
+/// The pattern is restarted for each subpath. On closed subpaths, dashes that touch
+/// the subpath start will be merged. A closed subpath that is not split at all
+/// will be kept as is.
+///
+/// Zero-length dashes will be converted into zero-length subpaths,
+/// which are rendered with round and square caps.
+///
+/// Returns the original path when the dash pattern is invalid: contains negative values
+/// or their sum is zero. Also returns the original path when the pattern is so small
+/// relative to the path length that it would produce more than 100000 dashes.
+pub fn apply_dashes(segments: &[PathSegment], dasharray: &[f64], offset: f64) -> Vec<PathSegment> {
+    if dasharray.iter().any(|n| *n < 0.0) {
+        return segments.to_vec();
+    }
+
+    // An odd number of values is repeated to yield an even one.
+    let mut dasharray = dasharray.to_vec();
+    if dasharray.len() % 2 != 0 {
+        let tmp = dasharray.clone();
+        dasharray.extend_from_slice(&tmp);
+    }
+
+    let sum = dasharray.iter().fold(0.0, |sum, n| sum + *n);
+    if !(sum > 0.0) || !sum.is_finite() {
+        return segments.to_vec();
+    }
+
+    let subpaths = subpaths(segments);
+    let total_len = subpaths.iter().flat_map(|s| s.iter()).fold(0.0, |len, p| len + p.length);
+    if total_len / sum * (dasharray.len() / 2) as f64 > MAX_DASHES {
+        warn!("Dash pattern is too small for a path with the {} length. Skipped.", total_len);
+        return segments.to_vec();
+    }
+
+    let mut new_segments = Vec::new();
+    for subpath in subpaths {
+        let len = subpath.iter().fold(0.0, |len, p| len + p.length);
+        let is_closed = match segments[subpath[subpath.len() - 1].idx] {
+            PathSegment::ClosePath => true,
+            _ => false,
+        };
+
+        let dashes = dash_intervals(&dasharray, sum, offset, len);
+        if dashes.is_empty() {
+            continue;
+        }
+
+        if is_closed && dashes.len() == 1 && dashes[0].0 <= 0.0 && dashes[0].1 >= len {
+            // Keep the subpath closed. The last piece is the ClosePath itself.
+            let last = subpath.len() - 1;
+            if last > 0 {
+                extract(&subpath[..last], 0.0, len - subpath[last].length, &mut new_segments);
+                new_segments.push(PathSegment::ClosePath);
+            }
+
+            continue;
+        }
+
+        let last = dashes.len() - 1;
+        let is_merged = is_closed && last > 0 && dashes[0].0 <= 0.0 && dashes[last].1 >= len;
+        let range = if is_merged { 1..last } else { 0..(last + 1) };
+
+        for &(start, end) in &dashes[range] {
+            extract(&subpath, start, end, &mut new_segments);
+        }
+
+        if is_merged {
+            // The last dash continues with the first one.
+            extract(&subpath, dashes[last].0, len, &mut new_segments);
+            let idx = new_segments.len();
+            extract(&subpath, 0.0, dashes[0].1, &mut new_segments);
+            // Remove the MoveTo, since the first dash starts at the subpath start.
+            if new_segments.len() > idx {
+                new_segments.remove(idx);
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Splits pieces by subpaths.
+fn subpaths(segments: &[PathSegment]) -> Vec<Vec<Piece>> {
+    let mut list: Vec<Vec<Piece>> = Vec::new();
+    for (piece, is_subpath_start) in pieces(segments) {
+        if is_subpath_start {
+            list.push(Vec::new());
+        }
+
+        if let Some(subpath) = list.last_mut() {
+            subpath.push(piece);
+        }
+    }
+
+    list
+}
+
+/// Returns `(start, end)` lengths of all dashes on a subpath with the specified length.
+fn dash_intervals(dasharray: &[f64], sum: f64, offset: f64, len: f64) -> Vec<(f64, f64)> {
+    // Find the dash at the start of the subpath.
+    let mut idx = 0;
+    let mut rem = offset % sum;
+    if rem < 0.0 {
+        rem += sum;
+    }
+
+    while rem >= dasharray[idx] && rem > 0.0 {
+        rem -= dasharray[idx];
+        idx = (idx + 1) % dasharray.len();
+    }
+
+    let mut list = Vec::new();
+    let mut pos = 0.0;
+    let mut dash_len = dasharray[idx] - rem;
+    while pos < len {
+        let end = pos + dash_len;
+        if idx % 2 == 0 {
+            list.push((pos, end.min(len)));
+        }
+
+        pos = end;
+        idx = (idx + 1) % dasharray.len();
+        dash_len = dasharray[idx];
+    }
+
+    list
+}
+
+/// Appends a part of the subpath between `start` and `end` lengths
+/// as a new subpath to `segments`.
+fn extract(subpath: &[Piece], start: f64, end: f64, segments: &mut Vec<PathSegment>) {
+    let mut is_empty = true;
+    let mut offset = 0.0;
+    for piece in subpath {
+        let (piece_start, piece_end) = (offset, offset + piece.length);
+        offset = piece_end;
+
+        // Pieces that only touch a non-empty dash are skipped.
+        let is_touching = end > start && (piece_end <= start || piece_start >= end);
+        if piece_end < start || piece_start > end || is_touching {
+            continue;
+        }
+
+        let t0 = piece_t(piece, start - piece_start);
+        let t1 = piece_t(piece, end - piece_start);
+
+        if is_empty {
+            let p = piece_point(piece, t0);
+            segments.push(PathSegment::MoveTo { x: p.x, y: p.y });
+            is_empty = false;
+
+            if end <= start {
+                segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+                return;
+            }
+        } else if piece.length.is_fuzzy_zero() {
+            continue;
+        }
+
+        if piece.is_line {
+            let p = piece_point(piece, t1);
+            segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+        } else {
+            let curve = piece.curve.split(t1).0;
+            let curve = if t1 > 0.0 { curve.split(t0 / t1).1 } else { curve };
+            segments.push(PathSegment::CurveTo {
+                x1: curve.p1.x, y1: curve.p1.y,
+                x2: curve.p2.x, y2: curve.p2.y,
+                x: curve.p3.x, y: curve.p3.y,
+            });
+        }
+    }
+}
@@ -0,0 +1,593 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Path geometry utilities.
+//!
+//! All lengths are in the path coordinates. Like in the SVG `getPointAtLength`,
+//! the length of a path is the sum of all its subpaths lengths.
+//! `MoveTo` segments do not contribute to it, while `ClosePath` ones do.
+
+// self
+use geom::*;
+use tree::{
+    FillRule,
+    FuzzyZero,
+    LineCap,
+    LineJoin,
+    PathSegment,
+    Stroke,
+    Transform,
+};
+
+pub use self::boolean::{
+    boolean,
+    BooleanOp,
+};
+pub use self::dash::apply_dashes;
+pub use self::normalize::normalize;
+pub use self::simplify::simplify;
+pub use self::stroke::stroke_to_path;
+
+mod boolean;
+mod dash;
+mod normalize;
+mod simplify;
+mod stroke;
+
+
+/// A drawable segment piece.
+struct Piece {
+    /// Index of the segment in the original list.
+    idx: usize,
+    /// Segment geometry. Lines are represented as curves too.
+    curve: CubicBezier,
+    /// Checks that the piece is a line.
+    is_line: bool,
+    /// Start point of the subpath.
+    start: Point,
+    /// Length of the segment.
+    length: f64,
+}
+
+/// Iterates over drawable segments.
+///
+/// Returns `(piece, is_subpath_start)` pairs.
+fn pieces(segments: &[PathSegment]) -> Vec<(Piece, bool)> {
+    let mut list = Vec::new();
+    let mut prev = Point::new(0.0, 0.0);
+    let mut start = prev;
+    let mut is_subpath_start = true;
+
+    for (idx, seg) in segments.iter().enumerate() {
+        let (curve, is_line) = match *seg {
+            PathSegment::MoveTo { x, y } => {
+                prev = Point::new(x, y);
+                start = prev;
+                is_subpath_start = true;
+                continue;
+            }
+            PathSegment::LineTo { x, y } => {
+                (line_curve(prev, Point::new(x, y)), true)
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let curve = CubicBezier::new(prev, Point::new(x1, y1), Point::new(x2, y2),
+                                             Point::new(x, y));
+                (curve, false)
+            }
+            PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                for curve in extended_curves(prev, seg) {
+                    let piece = Piece { idx, curve, is_line: false, start, length: curve.length() };
+                    list.push((piece, is_subpath_start));
+                    is_subpath_start = false;
+                }
+
+                prev = Point::new(x, y);
+                continue;
+            }
+            PathSegment::ClosePath => {
+                let curve = line_curve(prev, start);
+                let piece = Piece { idx, curve, is_line: true, start, length: curve.length() };
+                list.push((piece, is_subpath_start));
+                prev = start;
+                // A segment after the ClosePath starts a new subpath.
+                is_subpath_start = true;
+                continue;
+            }
+        };
+
+        list.push((Piece { idx, curve, is_line, start, length: curve.length() }, is_subpath_start));
+        prev = curve.p3;
+        is_subpath_start = false;
+    }
+
+    list
+}
+
+fn is_extended(seg: &PathSegment) -> bool {
+    match *seg {
+        PathSegment::QuadTo { .. } | PathSegment::ArcTo { .. } => true,
+        _ => false,
+    }
+}
+
+/// Converts a line into a curve with a uniform parametrization.
+fn line_curve(p0: Point, p1: Point) -> CubicBezier {
+    let (dx, dy) = (p1.x - p0.x, p1.y - p0.y);
+    CubicBezier::new(
+        p0,
+        Point::new(p0.x + dx / 3.0, p0.y + dy / 3.0),
+        Point::new(p0.x + dx * 2.0 / 3.0, p0.y + dy * 2.0 / 3.0),
+        p1,
+    )
+}
+
+/// Converts a `QuadTo` or an `ArcTo` segment that starts at `prev` into curves.
+///
+/// Returns an empty list for other segments and for arcs with equal end points.
+pub(crate) fn extended_curves(prev: Point, seg: &PathSegment) -> Vec<CubicBezier> {
+    let mut curves = Vec::new();
+    match *seg {
+        PathSegment::QuadTo { x1, y1, x, y } => {
+            curves.push(CubicBezier::from_quad(prev, Point::new(x1, y1), Point::new(x, y)));
+        }
+        PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+            let to = Point::new(x, y);
+            let arc = EllipticalArc { from: prev, to, rx, ry, x_axis_rotation, large_arc, sweep };
+
+            // The tolerance is relative, so it's small enough for any computations.
+            let tolerance = rx.max(ry).max(distance(prev, to)) * 1e-6;
+            arc.to_cubics(tolerance, &mut curves);
+        }
+        _ => {}
+    }
+
+    curves
+}
+
+/// Approximates an elliptical arc by curves using the specified tolerance.
+pub(crate) fn arc_to_curves(
+    arc: &EllipticalArc,
+    tolerance: f64,
+    segments: &mut Vec<PathSegment>,
+) {
+    let mut curves = Vec::new();
+    arc.to_cubics(tolerance, &mut curves);
+    for curve in curves {
+        segments.push(PathSegment::CurveTo {
+            x1: curve.p1.x, y1: curve.p1.y,
+            x2: curve.p2.x, y2: curve.p2.y,
+            x: curve.p3.x, y: curve.p3.y,
+        });
+    }
+}
+
+/// Converts `ArcTo` segments into `CurveTo` ones using the specified tolerance.
+///
+/// Unlike `to_curves`, which uses a tiny relative tolerance,
+/// arcs are approximated exactly like the parser does.
+pub(crate) fn arcs_to_curves(segments: &[PathSegment], tolerance: f64) -> Vec<PathSegment> {
+    let mut new_segments = Vec::with_capacity(segments.len());
+    let mut prev = Point::new(0.0, 0.0);
+    let mut start = prev;
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                prev = Point::new(x, y);
+                start = prev;
+                new_segments.push(*seg);
+            }
+              PathSegment::LineTo { x, y }
+            | PathSegment::CurveTo { x, y, .. }
+            | PathSegment::QuadTo { x, y, .. } => {
+                prev = Point::new(x, y);
+                new_segments.push(*seg);
+            }
+            PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+                let to = Point::new(x, y);
+                let arc = EllipticalArc { from: prev, to, rx, ry, x_axis_rotation, large_arc, sweep };
+                arc_to_curves(&arc, tolerance, &mut new_segments);
+                prev = to;
+            }
+            PathSegment::ClosePath => {
+                prev = start;
+                new_segments.push(*seg);
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Converts `QuadTo` and `ArcTo` segments into `CurveTo` ones.
+fn to_curves(segments: &[PathSegment]) -> Vec<PathSegment> {
+    let mut new_segments = Vec::with_capacity(segments.len());
+    let mut prev = Point::new(0.0, 0.0);
+    let mut start = prev;
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                prev = Point::new(x, y);
+                start = prev;
+                new_segments.push(*seg);
+            }
+            PathSegment::LineTo { x, y } | PathSegment::CurveTo { x, y, .. } => {
+                prev = Point::new(x, y);
+                new_segments.push(*seg);
+            }
+            PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                for curve in extended_curves(prev, seg) {
+                    new_segments.push(PathSegment::CurveTo {
+                        x1: curve.p1.x, y1: curve.p1.y,
+                        x2: curve.p2.x, y2: curve.p2.y,
+                        x: curve.p3.x, y: curve.p3.y,
+                    });
+                }
+
+                prev = Point::new(x, y);
+            }
+            PathSegment::ClosePath => {
+                prev = start;
+                new_segments.push(*seg);
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Returns a point on the piece at `t`.
+fn piece_point(piece: &Piece, t: f64) -> Point {
+    if piece.is_line {
+        // Lerp directly to prevent rounding errors.
+        let (p0, p1) = (piece.curve.p0, piece.curve.p3);
+        Point::new(p0.x + (p1.x - p0.x) * t, p0.y + (p1.y - p0.y) * t)
+    } else {
+        piece.curve.eval(t)
+    }
+}
+
+/// Returns `t` at which the piece has the specified length.
+fn piece_t(piece: &Piece, length: f64) -> f64 {
+    if piece.is_line {
+        if piece.length > 0.0 { f64_bound(0.0, length / piece.length, 1.0) } else { 0.0 }
+    } else {
+        piece.curve.t_at_length(length)
+    }
+}
+
+/// Returns the path length.
+pub fn length(segments: &[PathSegment]) -> f64 {
+    pieces(segments).iter().fold(0.0, |len, v| len + v.0.length)
+}
+
+/// Returns lengths of all subpaths.
+///
+/// Subpaths without drawable segments, like a single `MoveTo`, are ignored.
+pub fn subpath_lengths(segments: &[PathSegment]) -> Vec<f64> {
+    let mut list: Vec<f64> = Vec::new();
+    for (piece, is_subpath_start) in pieces(segments) {
+        if is_subpath_start {
+            list.push(piece.length);
+        } else if let Some(len) = list.last_mut() {
+            *len += piece.length;
+        }
+    }
+
+    list
+}
+
+/// Finds a piece and a `t` at the specified length.
+///
+/// `length` will be clamped to the path length.
+fn piece_at_length(segments: &[PathSegment], length: f64) -> Option<(Piece, f64)> {
+    let mut list = pieces(segments);
+    let mut offset = 0.0;
+    let mut idx = None;
+    for (i, &(ref piece, _)) in list.iter().enumerate() {
+        if offset + piece.length >= length {
+            idx = Some(i);
+            break;
+        }
+
+        offset += piece.length;
+    }
+
+    match idx {
+        Some(idx) => {
+            let piece = list.swap_remove(idx).0;
+            let t = piece_t(&piece, length - offset);
+            Some((piece, t))
+        }
+        None => {
+            // The length is bigger than the path length.
+            let piece = try_opt!(list.pop(), None).0;
+            Some((piece, 1.0))
+        }
+    }
+}
+
+/// Returns a point at the specified length.
+///
+/// `length` will be clamped to the path length.
+///
+/// Returns `None` when the path doesn't have drawable segments.
+pub fn point_at_length(segments: &[PathSegment], length: f64) -> Option<Point> {
+    let (piece, t) = try_opt!(piece_at_length(segments, length), None);
+    Some(piece_point(&piece, t))
+}
+
+/// Returns a tangent angle at the specified length.
+///
+/// The angle is in degrees and is measured from the X-axis,
+/// like in the `rotate` transform.
+///
+/// `length` will be clamped to the path length.
+///
+/// Returns `None` when the path doesn't have drawable segments.
+pub fn angle_at_length(segments: &[PathSegment], length: f64) -> Option<f64> {
+    let (piece, t) = try_opt!(piece_at_length(segments, length), None);
+    let d = piece.curve.tangent(t);
+    Some(d.y.atan2(d.x).to_degrees())
+}
+
+/// Splits the path at the specified length.
+///
+/// The second part will start with a `MoveTo` to the split point.
+/// When the split point is on a `ClosePath` segment, it will be replaced by a `LineTo`.
+///
+/// When `length` is not bigger than zero, the first part will be empty.
+/// When `length` is not smaller than the path length, the second part will be empty.
+/// Otherwise, `QuadTo` and `ArcTo` segments will be converted into curves.
+pub fn split_at_length(
+    segments: &[PathSegment],
+    length: f64,
+) -> (Vec<PathSegment>, Vec<PathSegment>) {
+    if length <= 0.0 {
+        return (Vec::new(), segments.to_vec());
+    }
+
+    if length >= self::length(segments) {
+        return (segments.to_vec(), Vec::new());
+    }
+
+    // An arc can consist of multiple curves, while the split must produce a single one.
+    let curves;
+    let segments = if segments.iter().any(is_extended) {
+        curves = to_curves(segments);
+        &curves[..]
+    } else {
+        segments
+    };
+
+    let (piece, t) = match piece_at_length(segments, length) {
+        Some(v) => v,
+        None => return (segments.to_vec(), Vec::new()),
+    };
+
+    let (left, right) = piece.curve.split(t);
+    let p = piece_point(&piece, t);
+
+    let mut first = segments[0..piece.idx].to_vec();
+    let mut second = vec![PathSegment::MoveTo { x: p.x, y: p.y }];
+
+    match segments[piece.idx] {
+        PathSegment::CurveTo { .. } => {
+            first.push(PathSegment::CurveTo {
+                x1: left.p1.x, y1: left.p1.y,
+                x2: left.p2.x, y2: left.p2.y,
+                x: p.x, y: p.y,
+            });
+            second.push(PathSegment::CurveTo {
+                x1: right.p1.x, y1: right.p1.y,
+                x2: right.p2.x, y2: right.p2.y,
+                x: right.p3.x, y: right.p3.y,
+            });
+        }
+        _ => {
+            first.push(PathSegment::LineTo { x: p.x, y: p.y });
+            second.push(PathSegment::LineTo { x: right.p3.x, y: right.p3.y });
+        }
+    }
+
+    // The second part of the subpath starts at the split point now,
+    // so its ClosePath must still lead to the original start.
+    // And segments after it must start a new subpath at the original start too.
+    let rest = &segments[(piece.idx + 1)..];
+    let restart = |second: &mut Vec<PathSegment>, next: Option<&PathSegment>| {
+        match next {
+            Some(&PathSegment::MoveTo { .. }) | None => {}
+            Some(_) => second.push(PathSegment::MoveTo { x: piece.start.x, y: piece.start.y }),
+        }
+    };
+
+    let mut is_split_subpath = match segments[piece.idx] {
+        PathSegment::ClosePath => {
+            restart(&mut second, rest.first());
+            false
+        }
+        _ => true,
+    };
+
+    for (i, seg) in rest.iter().enumerate() {
+        match *seg {
+            PathSegment::MoveTo { .. } => {
+                is_split_subpath = false;
+            }
+            PathSegment::ClosePath if is_split_subpath => {
+                is_split_subpath = false;
+                second.push(PathSegment::LineTo { x: piece.start.x, y: piece.start.y });
+                restart(&mut second, rest.get(i + 1));
+                continue;
+            }
+            _ => {}
+        }
+
+        second.push(*seg);
+    }
+
+    (first, second)
+}
+
+/// Converts all curves into lines.
+///
+/// `tolerance` is the maximum distance between a curve and its polyline
+/// and must be positive.
+pub fn flatten(segments: &[PathSegment], tolerance: f64) -> Vec<PathSegment> {
+    let mut new_segments = Vec::with_capacity(segments.len());
+    let mut points = Vec::new();
+    let mut prev = Point::new(0.0, 0.0);
+    let mut start = prev;
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                prev = Point::new(x, y);
+                start = prev;
+                new_segments.push(*seg);
+            }
+            PathSegment::LineTo { x, y } => {
+                prev = Point::new(x, y);
+                new_segments.push(*seg);
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let curve = CubicBezier::new(prev, Point::new(x1, y1), Point::new(x2, y2),
+                                             Point::new(x, y));
+
+                points.clear();
+                curve.flatten(tolerance, &mut points);
+                for p in &points {
+                    new_segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+                }
+
+                prev = curve.p3;
+            }
+            PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                points.clear();
+                for curve in extended_curves(prev, seg) {
+                    curve.flatten(tolerance, &mut points);
+                }
+
+                for p in &points {
+                    new_segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+                }
+
+                prev = Point::new(x, y);
+            }
+            PathSegment::ClosePath => {
+                prev = start;
+                new_segments.push(*seg);
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Applies a transform to all segments.
+pub(crate) fn transform_segments(segments: &[PathSegment], ts: &Transform) -> Vec<PathSegment> {
+    segments.iter().map(|seg| {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                let (x, y) = ts.apply(x, y);
+                PathSegment::MoveTo { x, y }
+            }
+            PathSegment::LineTo { x, y } => {
+                let (x, y) = ts.apply(x, y);
+                PathSegment::LineTo { x, y }
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let (x1, y1) = ts.apply(x1, y1);
+                let (x2, y2) = ts.apply(x2, y2);
+                let (x, y) = ts.apply(x, y);
+                PathSegment::CurveTo { x1, y1, x2, y2, x, y }
+            }
+            PathSegment::QuadTo { x1, y1, x, y } => {
+                let (x1, y1) = ts.apply(x1, y1);
+                let (x, y) = ts.apply(x, y);
+                PathSegment::QuadTo { x1, y1, x, y }
+            }
+            PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+                let (rx, ry, x_axis_rotation, sweep) = transform_arc(ts, rx, ry, x_axis_rotation, sweep);
+                let (x, y) = ts.apply(x, y);
+                PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y }
+            }
+            PathSegment::ClosePath => PathSegment::ClosePath,
+        }
+    }).collect()
+}
+
+/// A flattened subpath.
+pub(crate) struct Polyline {
+    pub points: Vec<Point>,
+    pub closed: bool,
+}
+
+/// Flattens segments into polylines.
+///
+/// Each subpath will be converted into a separate polyline.
+pub(crate) fn polylines(segments: &[PathSegment], tolerance: f64) -> Vec<Polyline> {
+    let mut polylines: Vec<Polyline> = Vec::new();
+    let mut prev = Point::new(0.0, 0.0);
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                prev = Point::new(x, y);
+                polylines.push(Polyline {
+                    points: vec![prev],
+                    closed: false,
+                });
+            }
+            PathSegment::LineTo { x, y } => {
+                prev = Point::new(x, y);
+                current_polyline(&mut polylines, prev).points.push(prev);
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                let curve = CubicBezier::new(prev, Point::new(x1, y1), Point::new(x2, y2),
+                                             Point::new(x, y));
+                curve.flatten(tolerance, &mut current_polyline(&mut polylines, prev).points);
+                prev = curve.p3;
+            }
+            PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                let curves = extended_curves(prev, seg);
+                let polyline = current_polyline(&mut polylines, prev);
+                for curve in curves {
+                    curve.flatten(tolerance, &mut polyline.points);
+                }
+
+                prev = Point::new(x, y);
+            }
+            PathSegment::ClosePath => {
+                if let Some(polyline) = polylines.last_mut() {
+                    polyline.closed = true;
+                    prev = polyline.points[0];
+                }
+            }
+        }
+    }
+
+    polylines
+}
+
+/// Returns the current polyline.
+///
+/// A new one, starting at `start`, will be created if there are no polylines
+/// or the last one is already closed.
+fn current_polyline(polylines: &mut Vec<Polyline>, start: Point) -> &mut Polyline {
+    let is_new = match polylines.last() {
+        Some(polyline) => polyline.closed,
+        None => true,
+    };
+
+    if is_new {
+        polylines.push(Polyline {
+            points: vec![start],
+            closed: false,
+        });
+    }
+
+    polylines.last_mut().unwrap()
+}
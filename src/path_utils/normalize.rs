@@ -0,0 +1,249 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use super::*;
+
+
+/// Removes redundant segments without changing the path geometry.
+///
+/// - Consecutive and trailing `MoveTo` segments are removed.
+/// - Zero-length `LineTo` and `CurveTo` segments are removed. A subpath that consists
+///   only of them is replaced with a single zero-length `LineTo`, since it is still
+///   rendered with round and square caps.
+/// - Curves with all control points on a line between the end points are converted into lines.
+/// - Arcs with equal end points are removed and arcs with a zero radius are converted into lines,
+///   like the SVG spec requires.
+/// - Consecutive lines in the same direction are merged.
+/// - Lines that will be drawn by the `ClosePath` segment anyway are removed.
+///
+/// Stroke joins, caps and dashes are preserved as well.
+///
+/// Coordinates differences smaller than a millionth of the largest absolute coordinate
+/// are treated as rounding errors.
+pub fn normalize(segments: &[PathSegment]) -> Vec<PathSegment> {
+    let eps = segments.iter().fold(1.0f64, |max, seg| {
+        match *seg {
+              PathSegment::MoveTo { x, y }
+            | PathSegment::LineTo { x, y }
+            | PathSegment::ArcTo { x, y, .. } => {
+                max.max(x.abs()).max(y.abs())
+            }
+            PathSegment::QuadTo { x1, y1, x, y } => {
+                max.max(x1.abs()).max(y1.abs()).max(x.abs()).max(y.abs())
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                max.max(x1.abs()).max(y1.abs()).max(x2.abs()).max(y2.abs()).max(x.abs()).max(y.abs())
+            }
+            PathSegment::ClosePath => max,
+        }
+    }) * 1e-6;
+
+    let mut normalizer = Normalizer {
+        eps,
+        segments: Vec::with_capacity(segments.len()),
+        start: Point::new(0.0, 0.0),
+        current: Point::new(0.0, 0.0),
+        line_from: None,
+        has_drawable: false,
+        has_zero_length: false,
+    };
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => normalizer.move_to(Point::new(x, y)),
+            PathSegment::LineTo { x, y } => normalizer.line_to(Point::new(x, y)),
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                normalizer.curve_to(Point::new(x1, y1), Point::new(x2, y2), Point::new(x, y));
+            }
+            PathSegment::QuadTo { x1, y1, x, y } => {
+                normalizer.quad_to(Point::new(x1, y1), Point::new(x, y));
+            }
+            PathSegment::ArcTo { rx, ry, x, y, .. } => {
+                normalizer.arc_to(*seg, rx, ry, Point::new(x, y));
+            }
+            PathSegment::ClosePath => normalizer.close_path(),
+        }
+    }
+
+    normalizer.finish_subpath();
+
+    let mut segments = normalizer.segments;
+    if let Some(&PathSegment::MoveTo { .. }) = segments.last() {
+        segments.pop();
+    }
+
+    segments
+}
+
+struct Normalizer {
+    eps: f64,
+    segments: Vec<PathSegment>,
+    /// The current subpath start.
+    start: Point,
+    current: Point,
+    /// The start point of the last segment, when it is a `LineTo`.
+    line_from: Option<Point>,
+    /// Indicates that the current subpath has non-zero-length segments.
+    has_drawable: bool,
+    /// Indicates that a zero-length segment was removed from the current subpath.
+    has_zero_length: bool,
+}
+
+impl Normalizer {
+    fn move_to(&mut self, p: Point) {
+        self.finish_subpath();
+
+        if let Some(&PathSegment::MoveTo { .. }) = self.segments.last() {
+            self.segments.pop();
+        }
+
+        self.segments.push(PathSegment::MoveTo { x: p.x, y: p.y });
+        self.start = p;
+        self.current = p;
+        self.line_from = None;
+    }
+
+    fn line_to(&mut self, p: Point) {
+        if self.is_same_point(self.current, p) {
+            self.has_zero_length = true;
+            return;
+        }
+
+        let is_merged = match self.line_from {
+            Some(from) => self.is_on_line(from, p, self.current),
+            None => false,
+        };
+
+        if is_merged {
+            self.segments.pop();
+        } else {
+            self.line_from = Some(self.current);
+        }
+
+        self.segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+        self.current = p;
+        self.has_drawable = true;
+    }
+
+    fn curve_to(&mut self, p1: Point, p2: Point, p: Point) {
+        let p0 = self.current;
+        if self.is_same_point(p0, p1) && self.is_same_point(p0, p2) && self.is_same_point(p0, p) {
+            self.has_zero_length = true;
+            return;
+        }
+
+        if !self.is_same_point(p0, p) && self.is_on_line(p0, p, p1) && self.is_on_line(p0, p, p2) {
+            // Control points on the chord make the curve go straight to the end point.
+            self.line_to(p);
+            return;
+        }
+
+        self.push_drawable(PathSegment::CurveTo {
+            x1: p1.x, y1: p1.y,
+            x2: p2.x, y2: p2.y,
+            x: p.x, y: p.y,
+        }, p);
+    }
+
+    fn quad_to(&mut self, p1: Point, p: Point) {
+        let p0 = self.current;
+        if self.is_same_point(p0, p1) && self.is_same_point(p0, p) {
+            self.has_zero_length = true;
+            return;
+        }
+
+        if !self.is_same_point(p0, p) && self.is_on_line(p0, p, p1) {
+            self.line_to(p);
+            return;
+        }
+
+        self.push_drawable(PathSegment::QuadTo { x1: p1.x, y1: p1.y, x: p.x, y: p.y }, p);
+    }
+
+    fn arc_to(&mut self, seg: PathSegment, rx: f64, ry: f64, p: Point) {
+        // Unlike other segments, an arc with almost equal end points is an almost full ellipse.
+        if self.current == p {
+            return;
+        }
+
+        if rx.is_fuzzy_zero() || ry.is_fuzzy_zero() {
+            self.line_to(p);
+            return;
+        }
+
+        self.push_drawable(seg, p);
+    }
+
+    fn push_drawable(&mut self, seg: PathSegment, p: Point) {
+        self.segments.push(seg);
+        self.current = p;
+        self.line_from = None;
+        self.has_drawable = true;
+    }
+
+    fn close_path(&mut self) {
+        // Lines that will be drawn by the closing segment are removed.
+        while self.segments.len() > 1 {
+            let len = self.segments.len();
+            let p = match self.segments[len - 1] {
+                PathSegment::LineTo { x, y } => Point::new(x, y),
+                _ => break,
+            };
+
+            let from = match self.segments[len - 2] {
+                  PathSegment::MoveTo { x, y }
+                | PathSegment::LineTo { x, y }
+                | PathSegment::CurveTo { x, y, .. }
+                | PathSegment::QuadTo { x, y, .. }
+                | PathSegment::ArcTo { x, y, .. } => Point::new(x, y),
+                PathSegment::ClosePath => self.start,
+            };
+
+            if self.is_same_point(p, self.start) || self.is_on_line(from, self.start, p) {
+                self.segments.pop();
+            } else {
+                break;
+            }
+        }
+
+        // `ClosePath` itself makes the subpath drawable.
+        self.segments.push(PathSegment::ClosePath);
+        self.current = self.start;
+        self.line_from = None;
+        self.has_drawable = false;
+        self.has_zero_length = false;
+    }
+
+    /// Restores a zero-length subpath when all its segments were removed.
+    fn finish_subpath(&mut self) {
+        if !self.has_drawable && self.has_zero_length {
+            if let Some(&PathSegment::ClosePath) = self.segments.last() {
+                self.segments.push(PathSegment::MoveTo { x: self.current.x, y: self.current.y });
+            }
+
+            self.segments.push(PathSegment::LineTo { x: self.current.x, y: self.current.y });
+        }
+
+        self.has_drawable = false;
+        self.has_zero_length = false;
+    }
+
+    fn is_same_point(&self, a: Point, b: Point) -> bool {
+        (a.x - b.x).abs() <= self.eps && (a.y - b.y).abs() <= self.eps
+    }
+
+    /// Checks that `p` lies on the `a`-`b` line between the end points.
+    fn is_on_line(&self, a: Point, b: Point, p: Point) -> bool {
+        let len = distance(a, b);
+        if len.is_fuzzy_zero() {
+            return false;
+        }
+
+        let (dx, dy) = ((b.x - a.x) / len, (b.y - a.y) / len);
+        let offset = ((p.x - a.x) * dy - (p.y - a.y) * dx).abs();
+        let pos = (p.x - a.x) * dx + (p.y - a.y) * dy;
+        offset <= self.eps && pos >= -self.eps && pos <= len + self.eps
+    }
+}
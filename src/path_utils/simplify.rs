@@ -0,0 +1,408 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use super::*;
+
+
+/// The minimal cosine of an angle between curves that can be refitted together.
+const SMOOTH_JOIN_COS: f64 = 0.99;
+
+
+/// Simplifies the path with the specified error tolerance.
+///
+/// - Runs of consecutive lines are simplified using the Douglas-Peucker algorithm.
+/// - Runs of smoothly connected curves are refitted into fewer curves using
+///   the Schneider's algorithm.
+///
+/// Subpath start and end points, line and curve boundaries and corners
+/// between curves are preserved. `QuadTo` and `ArcTo` segments are kept as is,
+/// so the `PathProfile::Extended` paths will stay valid.
+///
+/// `tolerance` is the maximum distance between the original and the resulting path
+/// and must be positive.
+pub fn simplify(segments: &[PathSegment], tolerance: f64) -> Vec<PathSegment> {
+    let mut new_segments = Vec::with_capacity(segments.len());
+    let mut start = Point::new(0.0, 0.0);
+    let mut current = start;
+
+    let mut i = 0;
+    while i < segments.len() {
+        match segments[i] {
+            PathSegment::MoveTo { x, y } => {
+                new_segments.push(segments[i]);
+                start = Point::new(x, y);
+                current = start;
+                i += 1;
+            }
+            PathSegment::LineTo { .. } => {
+                let mut points = vec![current];
+                while let Some(&PathSegment::LineTo { x, y }) = segments.get(i) {
+                    points.push(Point::new(x, y));
+                    i += 1;
+                }
+
+                current = points[points.len() - 1];
+                simplify_lines(&points, tolerance, &mut new_segments);
+            }
+            PathSegment::CurveTo { .. } => {
+                let mut curves = Vec::new();
+                while let Some(&PathSegment::CurveTo { x1, y1, x2, y2, x, y }) = segments.get(i) {
+                    let p0 = current;
+                    current = Point::new(x, y);
+                    curves.push(CubicBezier::new(p0, Point::new(x1, y1), Point::new(x2, y2), current));
+                    i += 1;
+                }
+
+                simplify_curves(&curves, tolerance, &mut new_segments);
+            }
+            PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                new_segments.push(segments[i]);
+                current = Point::new(x, y);
+                i += 1;
+            }
+            PathSegment::ClosePath => {
+                new_segments.push(PathSegment::ClosePath);
+                current = start;
+                i += 1;
+            }
+        }
+    }
+
+    new_segments
+}
+
+/// Appends the simplified polyline without its first point.
+fn simplify_lines(points: &[Point], tolerance: f64, segments: &mut Vec<PathSegment>) {
+    let last = points.len() - 1;
+    let mut is_kept = vec![false; points.len()];
+    is_kept[0] = true;
+    is_kept[last] = true;
+
+    // The Douglas-Peucker algorithm without recursion, since paths can be huge.
+    let mut stack = vec![(0, last)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_dist = 0.0;
+        let mut idx = first;
+        for i in (first + 1)..last {
+            let d = segment_distance(points[i], points[first], points[last]);
+            if d > max_dist {
+                max_dist = d;
+                idx = i;
+            }
+        }
+
+        if max_dist > tolerance {
+            is_kept[idx] = true;
+            stack.push((first, idx));
+            stack.push((idx, last));
+        }
+    }
+
+    for (p, _) in points.iter().zip(is_kept.iter()).skip(1).filter(|&(_, keep)| *keep) {
+        segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+    }
+}
+
+/// Returns a distance between the point and the `a`-`b` line segment.
+fn segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    if len2.is_fuzzy_zero() {
+        return distance(p, a);
+    }
+
+    let t = f64_bound(0.0, ((p.x - a.x) * dx + (p.y - a.y) * dy) / len2, 1.0);
+    distance(p, Point::new(a.x + dx * t, a.y + dy * t))
+}
+
+fn simplify_curves(curves: &[CubicBezier], tolerance: f64, segments: &mut Vec<PathSegment>) {
+    // Split curves into smooth runs.
+    let mut first = 0;
+    for i in 1..(curves.len() + 1) {
+        let is_smooth = i < curves.len() && {
+            let d0 = unit_vector(curves[i - 1].tangent(1.0));
+            let d1 = unit_vector(curves[i].tangent(0.0));
+            d0.x * d1.x + d0.y * d1.y >= SMOOTH_JOIN_COS
+        };
+
+        if !is_smooth {
+            refit_curves(&curves[first..i], tolerance, segments);
+            first = i;
+        }
+    }
+}
+
+/// Appends curves that approximate the smooth run of curves.
+///
+/// The original curves will be used when refitting doesn't reduce their number
+/// or the result deviates from them more than the tolerance allows.
+fn refit_curves(curves: &[CubicBezier], tolerance: f64, segments: &mut Vec<PathSegment>) {
+    let mut new_curves = Vec::new();
+    let mut is_valid = false;
+    if curves.len() > 1 {
+        // Both the original and the new curves are flattened with a fraction of the tolerance,
+        // so the sum of flattening, sampling and fitting errors stays in bounds.
+        let flatten_tolerance = tolerance * 0.025;
+
+        let mut points = vec![curves[0].p0];
+        for curve in curves {
+            curve.flatten(flatten_tolerance, &mut points);
+        }
+
+        let t1 = unit_vector(curves[0].tangent(0.0));
+        let t2 = unit_vector(curves[curves.len() - 1].tangent(1.0));
+        fit_curves(&points, t1, Point::new(-t2.x, -t2.y), tolerance * 0.9, &mut new_curves);
+
+        // `fit_curves` checks only the distance at the sampled points and accepts
+        // some fits as is, so the result should be checked separately.
+        if !new_curves.is_empty() && new_curves.len() < curves.len() {
+            let mut new_points = vec![new_curves[0].p0];
+            for curve in &new_curves {
+                curve.flatten(flatten_tolerance, &mut new_points);
+            }
+
+            let max_dist = tolerance * 0.93;
+            is_valid = is_near(&new_points, &points, max_dist, flatten_tolerance)
+                    && is_near(&points, &new_points, max_dist, flatten_tolerance);
+        }
+    }
+
+    let curves = if is_valid { &new_curves[..] } else { curves };
+
+    for curve in curves {
+        segments.push(PathSegment::CurveTo {
+            x1: curve.p1.x, y1: curve.p1.y,
+            x2: curve.p2.x, y2: curve.p2.y,
+            x: curve.p3.x, y: curve.p3.y,
+        });
+    }
+}
+
+/// Fits curves into points using the Schneider's algorithm.
+///
+/// `t1` is the start direction and `t2` is the reversed end direction.
+fn fit_curves(points: &[Point], t1: Point, t2: Point, tolerance: f64, curves: &mut Vec<CubicBezier>) {
+    // Ranges are processed without recursion and in order: the left part of a split range
+    // is pushed last.
+    let mut stack = vec![(0, points.len() - 1, t1, t2)];
+    while let Some((first, last, t1, t2)) = stack.pop() {
+        let points = &points[first..(last + 1)];
+        let (p0, p3) = (points[0], points[points.len() - 1]);
+
+        if points.len() == 2 {
+            let d = distance(p0, p3) / 3.0;
+            curves.push(CubicBezier::new(
+                p0,
+                Point::new(p0.x + t1.x * d, p0.y + t1.y * d),
+                Point::new(p3.x + t2.x * d, p3.y + t2.y * d),
+                p3,
+            ));
+            continue;
+        }
+
+        let mut params = chord_params(points);
+        let mut curve = fit_curve(points, &params, t1, t2);
+        let (mut error, mut split) = max_error(points, &params, &curve);
+
+        // Improve the parametrization when the error is not too big.
+        if error > tolerance && error < tolerance * 16.0 {
+            for _ in 0..20 {
+                params = reparametrize(points, &params, &curve);
+                curve = fit_curve(points, &params, t1, t2);
+                let (e, s) = max_error(points, &params, &curve);
+                error = e;
+                split = s;
+
+                if error <= tolerance {
+                    break;
+                }
+            }
+        }
+
+        if error <= tolerance {
+            curves.push(curve);
+            continue;
+        }
+
+        // Split at the point with the maximum error.
+        let mut tc = Point::new(points[split - 1].x - points[split + 1].x,
+                                points[split - 1].y - points[split + 1].y);
+        if tc.x.is_fuzzy_zero() && tc.y.is_fuzzy_zero() {
+            tc = Point::new(points[split - 1].x - points[split].x,
+                            points[split - 1].y - points[split].y);
+        }
+        let tc = unit_vector(tc);
+
+        stack.push((first + split, last, Point::new(-tc.x, -tc.y), t2));
+        stack.push((first, first + split, t1, tc));
+    }
+}
+
+/// Returns normalized chord length parameters of the points.
+fn chord_params(points: &[Point]) -> Vec<f64> {
+    let mut params = Vec::with_capacity(points.len());
+    params.push(0.0);
+    for i in 1..points.len() {
+        let prev = params[i - 1];
+        params.push(prev + distance(points[i - 1], points[i]));
+    }
+
+    let len = params[params.len() - 1];
+    if len > 0.0 {
+        for p in &mut params {
+            *p /= len;
+        }
+    }
+
+    params
+}
+
+/// Finds a curve with the specified end directions that fits the points
+/// using the least squares method.
+fn fit_curve(points: &[Point], params: &[f64], t1: Point, t2: Point) -> CubicBezier {
+    let (p0, p3) = (points[0], points[points.len() - 1]);
+    let dot = |a: Point, b: Point| a.x * b.x + a.y * b.y;
+
+    let mut c = [[0.0; 2]; 2];
+    let mut x = [0.0; 2];
+    for (p, &u) in points.iter().zip(params.iter()) {
+        let mu = 1.0 - u;
+        let b0 = mu * mu * mu;
+        let b1 = 3.0 * u * mu * mu;
+        let b2 = 3.0 * u * u * mu;
+        let b3 = u * u * u;
+
+        let a1 = Point::new(t1.x * b1, t1.y * b1);
+        let a2 = Point::new(t2.x * b2, t2.y * b2);
+
+        c[0][0] += dot(a1, a1);
+        c[0][1] += dot(a1, a2);
+        c[1][1] += dot(a2, a2);
+
+        let tmp = Point::new(
+            p.x - (p0.x * (b0 + b1) + p3.x * (b2 + b3)),
+            p.y - (p0.y * (b0 + b1) + p3.y * (b2 + b3)),
+        );
+
+        x[0] += dot(a1, tmp);
+        x[1] += dot(a2, tmp);
+    }
+
+    let det = c[0][0] * c[1][1] - c[0][1] * c[0][1];
+    let (mut alpha1, mut alpha2) = if det.is_fuzzy_zero() {
+        (0.0, 0.0)
+    } else {
+        ((x[0] * c[1][1] - x[1] * c[0][1]) / det, (c[0][0] * x[1] - c[0][1] * x[0]) / det)
+    };
+
+    // Fall back to a simple heuristic when the solution is degenerate.
+    let len = distance(p0, p3);
+    if alpha1 < len * 1e-6 || alpha2 < len * 1e-6 {
+        alpha1 = len / 3.0;
+        alpha2 = len / 3.0;
+    }
+
+    CubicBezier::new(
+        p0,
+        Point::new(p0.x + t1.x * alpha1, p0.y + t1.y * alpha1),
+        Point::new(p3.x + t2.x * alpha2, p3.y + t2.y * alpha2),
+        p3,
+    )
+}
+
+/// Improves the parameters using the Newton-Raphson method.
+fn reparametrize(points: &[Point], params: &[f64], curve: &CubicBezier) -> Vec<f64> {
+    points.iter().zip(params.iter()).map(|(p, &u)| {
+        let q = curve.eval(u);
+        let d1 = curve.derivative(u);
+        let d2 = second_derivative(curve, u);
+
+        let numerator = (q.x - p.x) * d1.x + (q.y - p.y) * d1.y;
+        let denominator = d1.x * d1.x + d1.y * d1.y + (q.x - p.x) * d2.x + (q.y - p.y) * d2.y;
+        if denominator.is_fuzzy_zero() {
+            u
+        } else {
+            f64_bound(0.0, u - numerator / denominator, 1.0)
+        }
+    }).collect()
+}
+
+fn second_derivative(curve: &CubicBezier, t: f64) -> Point {
+    let mt = 1.0 - t;
+    let (p0, p1, p2, p3) = (curve.p0, curve.p1, curve.p2, curve.p3);
+    Point::new(
+        6.0 * (mt * (p2.x - 2.0 * p1.x + p0.x) + t * (p3.x - 2.0 * p2.x + p1.x)),
+        6.0 * (mt * (p2.y - 2.0 * p1.y + p0.y) + t * (p3.y - 2.0 * p2.y + p1.y)),
+    )
+}
+
+/// Returns the maximum distance between the points and the curve
+/// and the index of the furthest inner point.
+fn max_error(points: &[Point], params: &[f64], curve: &CubicBezier) -> (f64, usize) {
+    let mut error = 0.0;
+    let mut idx = points.len() / 2;
+    for i in 1..(points.len() - 1) {
+        let d = distance(curve.eval(params[i]), points[i]);
+        if d > error {
+            error = d;
+            idx = i;
+        }
+    }
+
+    (error, idx)
+}
+
+/// Checks that all points of the `a` polyline are not further than `max_dist`
+/// from the `b` polyline.
+///
+/// Edges of `a` are checked at points that are not further than `step` from each other.
+///
+/// Both polylines must go in the same direction, so points are matched in order
+/// and the check takes a linear time. Because of this, a polyline that
+/// loops near itself can be rejected even when it's close enough.
+fn is_near(a: &[Point], b: &[Point], max_dist: f64, step: f64) -> bool {
+    if b.len() < 2 {
+        return false;
+    }
+
+    // The current edge of `b`.
+    let mut idx = 0;
+    let mut is_near_edge = |p: Point| {
+        while segment_distance(p, b[idx], b[idx + 1]) > max_dist {
+            idx += 1;
+            if idx + 1 == b.len() {
+                return false;
+            }
+        }
+
+        true
+    };
+
+    if !is_near_edge(a[0]) {
+        return false;
+    }
+
+    for i in 1..a.len() {
+        let (p0, p1) = (a[i - 1], a[i]);
+        let count = (distance(p0, p1) / step).ceil().max(1.0) as usize;
+        for j in 1..(count + 1) {
+            let t = j as f64 / count as f64;
+            if !is_near_edge(Point::new(p0.x + (p1.x - p0.x) * t, p0.y + (p1.y - p0.y) * t)) {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn unit_vector(v: Point) -> Point {
+    let len = (v.x * v.x + v.y * v.y).sqrt();
+    if len.is_fuzzy_zero() {
+        v
+    } else {
+        Point::new(v.x / len, v.y / len)
+    }
+}
@@ -0,0 +1,326 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::f64;
+
+// self
+use super::*;
+
+
+/// Converts a stroke into a path that should be filled using the `NonZero` rule.
+///
+/// Width, caps, joins, miter limit and dashes are taken into account.
+///
+/// Curves are flattened, so the result contains only `MoveTo`, `LineTo`
+/// and `ClosePath` segments. `tolerance` is the maximum distance between
+/// the original and the resulting outline and must be positive.
+///
+/// Each subpath will be converted into a separate outline, so they can overlap
+/// and inner corners of joins can produce self-intersections.
+/// All outlines have the same orientation, so they will not cancel each other.
+pub fn stroke_to_path(
+    segments: &[PathSegment],
+    stroke: &Stroke,
+    tolerance: f64,
+) -> Vec<PathSegment> {
+    let hw = stroke.width / 2.0;
+    if !(hw > 0.0) {
+        return Vec::new();
+    }
+
+    let dashed;
+    let segments = match stroke.dasharray {
+        Some(ref list) => {
+            dashed = apply_dashes(segments, list, stroke.dashoffset);
+            &dashed[..]
+        }
+        None => segments,
+    };
+
+    let stroker = Stroker {
+        hw,
+        linecap: stroke.linecap,
+        linejoin: stroke.linejoin,
+        miterlimit: stroke.miterlimit,
+        arc_step: arc_step(hw, tolerance),
+    };
+
+    let mut new_segments = Vec::new();
+    for polyline in polylines(segments, tolerance) {
+        let points = dedup_points(&polyline);
+
+        let mut outlines = Vec::new();
+        if points.len() == 1 {
+            // A zero-length subpath.
+            if polyline.closed || polyline.points.len() > 1 {
+                stroker.dot(points[0], &mut outlines);
+            }
+        } else if polyline.closed {
+            stroker.closed(&points, &mut outlines);
+        } else {
+            stroker.open(&points, &mut outlines);
+        }
+
+        // Make all outlines positively oriented, so overlapping subpaths
+        // will not produce holes.
+        let area = outlines.iter().fold(0.0, |area, o| area + signed_area(o));
+        for mut outline in outlines {
+            if area < 0.0 {
+                outline.reverse();
+            }
+
+            push_polygon(&outline, &mut new_segments);
+        }
+    }
+
+    new_segments
+}
+
+struct Stroker {
+    hw: f64,
+    linecap: LineCap,
+    linejoin: LineJoin,
+    miterlimit: f64,
+    arc_step: f64,
+}
+
+impl Stroker {
+    fn dot(&self, p: Point, outlines: &mut Vec<Vec<Point>>) {
+        let hw = self.hw;
+        match self.linecap {
+            LineCap::Butt => {}
+            LineCap::Round => {
+                let mut outline = Vec::new();
+                self.arc(p, Point::new(hw, 0.0), 2.0 * f64::consts::PI, &mut outline);
+                // The last point is equal to the first one.
+                outline.pop();
+                outlines.push(outline);
+            }
+            LineCap::Square => {
+                outlines.push(vec![
+                    Point::new(p.x - hw, p.y - hw),
+                    Point::new(p.x + hw, p.y - hw),
+                    Point::new(p.x + hw, p.y + hw),
+                    Point::new(p.x - hw, p.y + hw),
+                ]);
+            }
+        }
+    }
+
+    fn open(&self, points: &[Point], outlines: &mut Vec<Vec<Point>>) {
+        let mut reversed = points.to_vec();
+        reversed.reverse();
+
+        let mut outline = Vec::new();
+        self.side(points, false, &mut outline);
+        self.cap(points, &mut outline);
+        self.side(&reversed, false, &mut outline);
+        self.cap(&reversed, &mut outline);
+        outlines.push(outline);
+    }
+
+    fn closed(&self, points: &[Point], outlines: &mut Vec<Vec<Point>>) {
+        let mut reversed = points.to_vec();
+        reversed.reverse();
+
+        let mut outline = Vec::new();
+        self.side(points, true, &mut outline);
+        outlines.push(outline);
+
+        let mut outline = Vec::new();
+        self.side(&reversed, true, &mut outline);
+        outlines.push(outline);
+    }
+
+    /// Appends the left offset of the polyline with joins.
+    fn side(&self, points: &[Point], closed: bool, outline: &mut Vec<Point>) {
+        let len = points.len();
+        if closed {
+            for i in 0..len {
+                let prev = points[(i + len - 1) % len];
+                let next = points[(i + 1) % len];
+                self.join(prev, points[i], next, outline);
+            }
+        } else {
+            let n = normal(points[0], points[1], self.hw);
+            outline.push(add(points[0], n));
+
+            for i in 1..(len - 1) {
+                self.join(points[i - 1], points[i], points[i + 1], outline);
+            }
+
+            let n = normal(points[len - 2], points[len - 1], self.hw);
+            outline.push(add(points[len - 1], n));
+        }
+    }
+
+    /// Appends a join at `p` on the left side.
+    fn join(&self, prev: Point, p: Point, next: Point, outline: &mut Vec<Point>) {
+        let n0 = normal(prev, p, self.hw);
+        let n1 = normal(p, next, self.hw);
+        let d0 = unit(prev, p);
+        let d1 = unit(p, next);
+        let cross = d0.x * d1.y - d0.y * d1.x;
+        let dot = d0.x * d1.x + d0.y * d1.y;
+
+        if cross.abs() < 1e-9 && dot > 0.0 {
+            // No turn.
+            outline.push(add(p, n0));
+            return;
+        }
+
+        if cross > 1e-9 {
+            // An inner join. The pivot point prevents gaps between the segments.
+            outline.push(add(p, n0));
+            outline.push(p);
+            outline.push(add(p, n1));
+            return;
+        }
+
+        outline.push(add(p, n0));
+
+        match self.linejoin {
+            LineJoin::Miter => {
+                // `miterlimit` is compared with `1 / sin(theta / 2)`,
+                // where `theta` is the angle between the segments.
+                let ratio = 1.0 / ((1.0 + dot) / 2.0).sqrt();
+                if ratio <= self.miterlimit {
+                    let m = Point::new(n0.x + n1.x, n0.y + n1.y);
+                    let m_len = (m.x * m.x + m.y * m.y).sqrt();
+                    let k = self.hw * ratio / m_len;
+                    outline.push(Point::new(p.x + m.x * k, p.y + m.y * k));
+                }
+            }
+            LineJoin::Round => {
+                let mut sweep = (n0.x * n1.y - n0.y * n1.x).atan2(n0.x * n1.x + n0.y * n1.y);
+                if sweep > 0.0 {
+                    // A U-turn.
+                    sweep = -sweep;
+                }
+
+                self.arc_between(p, n0, sweep, outline);
+            }
+            LineJoin::Bevel => {}
+        }
+
+        outline.push(add(p, n1));
+    }
+
+    /// Appends a cap at the last point of the polyline.
+    ///
+    /// Goes from the left side to the right one.
+    fn cap(&self, points: &[Point], outline: &mut Vec<Point>) {
+        let len = points.len();
+        let p = points[len - 1];
+        let n = normal(points[len - 2], p, self.hw);
+        match self.linecap {
+            LineCap::Butt => {}
+            LineCap::Round => {
+                self.arc_between(p, n, -f64::consts::PI, outline);
+            }
+            LineCap::Square => {
+                // The extension vector is the normal rotated by -90 degrees.
+                let d = Point::new(n.y, -n.x);
+                outline.push(Point::new(p.x + n.x + d.x, p.y + n.y + d.y));
+                outline.push(Point::new(p.x - n.x + d.x, p.y - n.y + d.y));
+            }
+        }
+    }
+
+    /// Appends an arc without its start and end points.
+    fn arc_between(&self, center: Point, from: Point, sweep: f64, outline: &mut Vec<Point>) {
+        let mut arc = Vec::new();
+        self.arc(center, from, sweep, &mut arc);
+        outline.extend_from_slice(&arc[1..(arc.len() - 1)]);
+    }
+
+    /// Returns the number of points in an arc with the specified sweep.
+    fn arc_points(&self, sweep: f64) -> usize {
+        (sweep.abs() / self.arc_step).ceil().max(1.0) as usize + 1
+    }
+
+    /// Appends an arc around `center`, starting at the `from` offset.
+    ///
+    /// Both the start and the end points are included.
+    fn arc(&self, center: Point, from: Point, sweep: f64, outline: &mut Vec<Point>) {
+        let count = self.arc_points(sweep);
+        let start = from.y.atan2(from.x);
+        for i in 0..count {
+            let a = start + sweep * i as f64 / (count - 1) as f64;
+            let (sin, cos) = a.sin_cos();
+            outline.push(Point::new(center.x + cos * self.hw, center.y + sin * self.hw));
+        }
+    }
+}
+
+/// Returns the maximum arc angle step that satisfies the tolerance.
+fn arc_step(radius: f64, tolerance: f64) -> f64 {
+    if tolerance >= radius {
+        f64::consts::FRAC_PI_2
+    } else {
+        (2.0 * (1.0 - tolerance / radius).acos()).max(0.01)
+    }
+}
+
+/// Returns polyline points without consecutive duplicates.
+///
+/// The closing point of a closed polyline is removed too.
+fn dedup_points(polyline: &Polyline) -> Vec<Point> {
+    let mut points: Vec<Point> = Vec::with_capacity(polyline.points.len());
+    for p in &polyline.points {
+        let is_dup = match points.last() {
+            Some(prev) => distance(*prev, *p).is_fuzzy_zero(),
+            None => false,
+        };
+
+        if !is_dup {
+            points.push(*p);
+        }
+    }
+
+    if polyline.closed && points.len() > 1 && distance(points[0], points[points.len() - 1]).is_fuzzy_zero() {
+        points.pop();
+    }
+
+    points
+}
+
+/// Returns a unit direction vector from `a` to `b`.
+fn unit(a: Point, b: Point) -> Point {
+    let len = distance(a, b);
+    Point::new((b.x - a.x) / len, (b.y - a.y) / len)
+}
+
+/// Returns a left normal of the `a`-`b` line with the `hw` length.
+fn normal(a: Point, b: Point, hw: f64) -> Point {
+    let d = unit(a, b);
+    Point::new(-d.y * hw, d.x * hw)
+}
+
+fn add(p: Point, v: Point) -> Point {
+    Point::new(p.x + v.x, p.y + v.y)
+}
+
+fn signed_area(points: &[Point]) -> f64 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+
+    area / 2.0
+}
+
+fn push_polygon(points: &[Point], segments: &mut Vec<PathSegment>) {
+    if points.len() < 3 {
+        return;
+    }
+
+    segments.push(PathSegment::MoveTo { x: points[0].x, y: points[0].y });
+    for p in &points[1..] {
+        segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+    }
+    segments.push(PathSegment::ClosePath);
+}
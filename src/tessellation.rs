@@ -0,0 +1,651 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Paths tessellation into triangle meshes.
+//!
+//! Available only with the `tessellation` feature.
+//!
+//! Fills and strokes are converted into polygons using [`path_utils::boolean`]
+//! and [`path_utils::stroke_to_path`] and then triangulated using ear clipping.
+//!
+//! [`path_utils::boolean`]: ../path_utils/fn.boolean.html
+//! [`path_utils::stroke_to_path`]: ../path_utils/fn.stroke_to_path.html
+
+use std::cmp::Ordering;
+use std::f64;
+
+// self
+use geom::*;
+use path_utils::{
+    self,
+    BooleanOp,
+};
+use tree::{
+    self,
+    Color,
+    FillRule,
+    FuzzyZero,
+    NodeExt,
+    NodeKind,
+    Opacity,
+    Paint,
+    PathSegment,
+    Transform,
+    Tree,
+    Units,
+};
+use utils;
+use {
+    Error,
+    PathProfile,
+};
+
+
+/// A path part that was tessellated.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MeshKind {
+    Fill,
+    Stroke,
+}
+
+
+/// A triangle mesh of a path fill or stroke.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    /// ID of the source path.
+    ///
+    /// Can be empty.
+    pub id: String,
+    /// Tessellated path part.
+    pub kind: MeshKind,
+    /// Fill or stroke paint.
+    pub paint: Paint,
+    /// Fill or stroke opacity.
+    pub opacity: Opacity,
+    /// Mesh vertices.
+    pub vertices: Vec<Vertex>,
+    /// Vertex indices. Each three indices form a triangle.
+    ///
+    /// All triangles have the same orientation.
+    pub indices: Vec<u32>,
+}
+
+
+/// A mesh vertex.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Vertex {
+    /// X position in the canvas coordinates.
+    pub x: f64,
+    /// Y position in the canvas coordinates.
+    pub y: f64,
+    /// Paint at the vertex.
+    pub paint: VertexPaint,
+}
+
+
+/// A vertex paint info.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VertexPaint {
+    /// A solid color.
+    #[allow(missing_docs)]
+    Color { color: Color, opacity: Opacity },
+    /// A linear gradient position.
+    ///
+    /// `t` is 0 at the gradient start point and 1 at the end one.
+    /// Spread method is not applied, so it can be outside the 0..1 range.
+    LinearGradient {
+        #[allow(missing_docs)]
+        t: f64,
+    },
+    /// A position in the radial gradient coordinates.
+    ///
+    /// The same coordinates in which `cx`, `cy`, `r`, `fx` and `fy` are defined.
+    #[allow(missing_docs)]
+    RadialGradient { x: f64, y: f64 },
+    /// An unsupported paint.
+    ///
+    /// Patterns, links to missing elements and gradients that cannot be mapped
+    /// onto the path, like `objectBoundingBox` ones on a zero-sized path.
+    None,
+}
+
+
+/// Tessellates all renderable paths and shapes.
+///
+/// Each fill and stroke becomes a separate mesh. Meshes are returned in the rendering order.
+///
+/// `tolerance` is the maximum distance between a curve and its polyline
+/// in the canvas coordinates and must be positive.
+///
+/// Group opacity, clip paths and masks are ignored.
+///
+/// Fills and strokes that cannot be triangulated are skipped with a warning,
+/// so a single broken path will not prevent the rest of the tree from being tessellated.
+pub fn tessellate(tree: &Tree, tolerance: f64) -> Vec<Mesh> {
+    let mut meshes = Vec::new();
+    for child in tree.root().children() {
+        if let NodeKind::Defs = *child.borrow() {
+            continue;
+        }
+
+        for node in child.descendants() {
+            match *node.borrow() {
+                NodeKind::Path(ref path) => {
+                    tessellate_path(tree, path, node.canvas_transform(), tolerance, &mut meshes);
+                }
+                NodeKind::Shape(ref shape) => {
+                    tessellate_path(tree, &shape.to_path(PathProfile::Extended, 0.0), node.canvas_transform(), tolerance,
+                                    &mut meshes);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    meshes
+}
+
+/// Triangulates the filled area of a path.
+///
+/// Returns a list of points and a list of triangles indices. All triangles
+/// have a positive signed area.
+///
+/// `tolerance` is the maximum distance between a curve and its polyline
+/// and must be positive.
+///
+/// Returns `Error::TessellationFailed` when a hole cannot be connected to its outline
+/// or the polygon cannot be split into triangles, instead of an incomplete mesh.
+pub fn triangulate(
+    segments: &[PathSegment],
+    rule: FillRule,
+    tolerance: f64,
+) -> Result<(Vec<Point>, Vec<u32>), Error> {
+    // A union with an empty path resolves the fill rule and self-intersections.
+    // The result consists of non-overlapping outlines, where holes have an opposite orientation.
+    let segments = path_utils::boolean(segments, rule, &[], FillRule::NonZero,
+                                       BooleanOp::Union, tolerance);
+
+    let mut points = Vec::new();
+    let mut outers = Vec::new();
+    let mut holes = Vec::new();
+    for contour in contours(&segments).into_iter().flat_map(split_touching) {
+        let start = points.len();
+        points.extend_from_slice(&contour);
+        let contour: Vec<usize> = (start..points.len()).collect();
+
+        let area = signed_area(&points, &contour);
+        if area.is_fuzzy_zero() {
+            continue;
+        }
+
+        if area > 0.0 {
+            outers.push((contour, area, Vec::new()));
+        } else {
+            holes.push(contour);
+        }
+    }
+
+    // Assign each hole to the smallest outline that contains it.
+    for hole in holes {
+        let p = points[hole[0]];
+        let mut parent: Option<usize> = None;
+        for (i, &(ref outer, area, _)) in outers.iter().enumerate() {
+            let is_smaller = match parent {
+                Some(idx) => area < outers[idx].1,
+                None => true,
+            };
+
+            if is_smaller && is_in_polygon(&points, outer, p) {
+                parent = Some(i);
+            }
+        }
+
+        if let Some(idx) = parent {
+            outers[idx].2.push(hole);
+        }
+    }
+
+    let mut indices = Vec::new();
+    for (outer, _, holes) in outers {
+        let polygon = bridge_holes(&points, outer, holes)?;
+        clip_ears(&points, polygon, &mut indices)?;
+    }
+
+    Ok((points, indices))
+}
+
+fn tessellate_path(
+    tree: &Tree,
+    path: &tree::Path,
+    ts: Transform,
+    tolerance: f64,
+    meshes: &mut Vec<Mesh>,
+) {
+    // The object bounding box doesn't include the stroke.
+    let bbox = utils::path_bbox(&path.segments, None, &Transform::default());
+
+    let new_mesh = |kind: MeshKind, paint: &Paint, opacity: Opacity, points: Vec<Point>,
+                    indices: Vec<u32>| {
+        if indices.is_empty() {
+            return None;
+        }
+
+        let mapper = PaintMapper::new(tree, paint, opacity, ts, bbox);
+        let vertices = points.into_iter().map(|p| {
+            Vertex {
+                x: p.x,
+                y: p.y,
+                paint: mapper.map(p),
+            }
+        }).collect();
+
+        Some(Mesh {
+            id: path.id.clone(),
+            kind,
+            paint: paint.clone(),
+            opacity,
+            vertices,
+            indices,
+        })
+    };
+
+    if let Some(ref fill) = path.fill {
+        let segments = path_utils::transform_segments(&path.segments, &ts);
+        match triangulate(&segments, fill.rule, tolerance) {
+            Ok((points, indices)) => {
+                meshes.extend(new_mesh(MeshKind::Fill, &fill.paint, fill.opacity, points, indices));
+            }
+            Err(_) => warn!("Failed to tessellate the '{}' path fill. Skipped.", path.id),
+        }
+    }
+
+    if let Some(ref stroke) = path.stroke {
+        // The stroke is outlined in the path coordinates, since its width is defined in them.
+        if (ts.a * ts.d - ts.b * ts.c).is_fuzzy_zero() {
+            return;
+        }
+
+        let scale = utils::max_scale(&ts);
+        let outline = path_utils::stroke_to_path(&path.segments, stroke, tolerance / scale);
+        let outline = path_utils::transform_segments(&outline, &ts);
+        match triangulate(&outline, FillRule::NonZero, tolerance) {
+            Ok((points, indices)) => {
+                meshes.extend(new_mesh(MeshKind::Stroke, &stroke.paint, stroke.opacity, points, indices));
+            }
+            Err(_) => warn!("Failed to tessellate the '{}' path stroke. Skipped.", path.id),
+        }
+    }
+}
+
+
+/// Maps canvas points to the vertex paint.
+enum PaintMapper {
+    Color(Color, Opacity),
+    /// A canvas to gradient transform and the gradient vector.
+    LinearGradient(Transform, Point, Point),
+    /// A canvas to gradient transform.
+    RadialGradient(Transform),
+    None,
+}
+
+impl PaintMapper {
+    /// `ts` is the path canvas transform and `bbox` is the path bounding box.
+    fn new(tree: &Tree, paint: &Paint, opacity: Opacity, ts: Transform, bbox: Option<Rect>) -> Self {
+        let id = match *paint {
+            Paint::Color(c) => return PaintMapper::Color(c, opacity),
+            Paint::Link(ref id) => id,
+        };
+
+        let node = try_opt!(tree.defs_by_id(id), PaintMapper::None);
+        let kind = node.borrow();
+
+        let gradient_ts = |units: Units, transform: Transform| {
+            let mut ts = ts;
+            if units == Units::ObjectBoundingBox {
+                let bbox = match bbox {
+                    Some(bbox) => bbox,
+                    None => return None,
+                };
+
+                ts.append(&Transform::new(bbox.width, 0.0, 0.0, bbox.height, bbox.x, bbox.y));
+            }
+
+            ts.append(&transform);
+            ts.inverse()
+        };
+
+        match *kind {
+            NodeKind::LinearGradient(ref lg) => {
+                let ts = try_opt!(gradient_ts(lg.units, lg.transform), PaintMapper::None);
+                PaintMapper::LinearGradient(ts, Point::new(lg.x1, lg.y1), Point::new(lg.x2, lg.y2))
+            }
+            NodeKind::RadialGradient(ref rg) => {
+                let ts = try_opt!(gradient_ts(rg.units, rg.transform), PaintMapper::None);
+                PaintMapper::RadialGradient(ts)
+            }
+            _ => PaintMapper::None,
+        }
+    }
+
+    fn map(&self, p: Point) -> VertexPaint {
+        match *self {
+            PaintMapper::Color(color, opacity) => VertexPaint::Color { color, opacity },
+            PaintMapper::LinearGradient(ts, p1, p2) => {
+                let p = ts.map_point(p);
+                let (dx, dy) = (p2.x - p1.x, p2.y - p1.y);
+                let len2 = dx * dx + dy * dy;
+                if len2.is_fuzzy_zero() {
+                    // A zero-length gradient vector is rendered using the last stop.
+                    VertexPaint::LinearGradient { t: 1.0 }
+                } else {
+                    let t = ((p.x - p1.x) * dx + (p.y - p1.y) * dy) / len2;
+                    VertexPaint::LinearGradient { t }
+                }
+            }
+            PaintMapper::RadialGradient(ts) => {
+                let p = ts.map_point(p);
+                VertexPaint::RadialGradient { x: p.x, y: p.y }
+            }
+            PaintMapper::None => VertexPaint::None,
+        }
+    }
+}
+
+
+/// Splits line-only segments into contours.
+fn contours(segments: &[PathSegment]) -> Vec<Vec<Point>> {
+    let mut list = Vec::new();
+    let mut contour = Vec::new();
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                if contour.len() > 2 {
+                    list.push(contour);
+                }
+
+                contour = vec![Point::new(x, y)];
+            }
+            PathSegment::LineTo { x, y } => {
+                contour.push(Point::new(x, y));
+            }
+              PathSegment::CurveTo { x, y, .. }
+            | PathSegment::QuadTo { x, y, .. }
+            | PathSegment::ArcTo { x, y, .. } => {
+                // Not produced by `boolean`.
+                contour.push(Point::new(x, y));
+            }
+            PathSegment::ClosePath => {}
+        }
+    }
+
+    if contour.len() > 2 {
+        list.push(contour);
+    }
+
+    list
+}
+
+/// Splits a contour that touches itself into simple ones.
+///
+/// `boolean` doesn't produce intersections, but contours can still share vertices,
+/// like the tips of an even-odd star. Ear clipping cannot handle such vertices,
+/// so each loop becomes a separate contour. Loops that bound holes have
+/// an opposite orientation, so they are still treated as holes.
+fn split_touching(contour: Vec<Point>) -> Vec<Vec<Point>> {
+    let mut list = Vec::new();
+    let mut stack = vec![contour];
+    while let Some(contour) = stack.pop() {
+        match find_touching(&contour) {
+            Some((i, j)) => {
+                // Both loops start after the shared vertex, so their first point
+                // can be used to check what outline contains them.
+                stack.push(contour[(i + 1)..(j + 1)].to_vec());
+
+                let mut rest = contour[(j + 1)..].to_vec();
+                rest.extend_from_slice(&contour[..(i + 1)]);
+                stack.push(rest);
+            }
+            None => list.push(contour),
+        }
+    }
+
+    list
+}
+
+/// Returns the positions of two vertices of the contour with the same coordinates.
+fn find_touching(contour: &[Point]) -> Option<(usize, usize)> {
+    let mut order: Vec<usize> = (0..contour.len()).collect();
+    order.sort_by(|a, b| {
+        let (a, b) = (contour[*a], contour[*b]);
+        a.x.partial_cmp(&b.x).unwrap_or(Ordering::Equal)
+            .then(a.y.partial_cmp(&b.y).unwrap_or(Ordering::Equal))
+    });
+
+    for pair in order.windows(2) {
+        if contour[pair[0]] == contour[pair[1]] {
+            return Some((pair[0].min(pair[1]), pair[0].max(pair[1])));
+        }
+    }
+
+    None
+}
+
+/// Connects holes with the outline, producing a single weakly simple polygon.
+fn bridge_holes(
+    points: &[Point],
+    outer: Vec<usize>,
+    mut holes: Vec<Vec<usize>>,
+) -> Result<Vec<usize>, Error> {
+    let max_x = |hole: &Vec<usize>| hole.iter().fold(f64::NEG_INFINITY, |x, i| x.max(points[*i].x));
+
+    // Process holes from right to left.
+    holes.sort_by(|a, b| max_x(b).partial_cmp(&max_x(a)).unwrap_or(Ordering::Equal));
+
+    let mut polygon = outer;
+    for i in 0..holes.len() {
+        let hole = &holes[i];
+
+        // The rightmost hole vertex.
+        let mut m_pos = 0;
+        for (pos, idx) in hole.iter().enumerate() {
+            if points[*idx].x > points[hole[m_pos]].x {
+                m_pos = pos;
+            }
+        }
+        let m = hole[m_pos];
+
+        // Find the closest polygon vertex, that can be connected to the hole
+        // without crossing any edge.
+        let mut bridge: Option<(usize, f64)> = None;
+        for (pos, idx) in polygon.iter().enumerate() {
+            let d = distance(points[m], points[*idx]);
+            let is_closer = match bridge {
+                Some((_, min)) => d < min,
+                None => true,
+            };
+
+            if !is_closer {
+                continue;
+            }
+
+            let is_visible = is_locally_inside(points, &polygon, pos, points[m])
+                && is_locally_inside(points, hole, m_pos, points[*idx])
+                && !crosses_contour(points, &polygon, m, *idx)
+                && holes[i..].iter().all(|h| !crosses_contour(points, h, m, *idx));
+            if is_visible {
+                bridge = Some((pos, d));
+            }
+        }
+
+        // Skipping the hole would fill it, so the whole path fails instead.
+        let pos = match bridge {
+            Some((pos, _)) => pos,
+            None => return Err(Error::TessellationFailed),
+        };
+
+        let mut new_polygon = polygon[..(pos + 1)].to_vec();
+        new_polygon.extend_from_slice(&hole[m_pos..]);
+        new_polygon.extend_from_slice(&hole[..(m_pos + 1)]);
+        new_polygon.extend_from_slice(&polygon[pos..]);
+        polygon = new_polygon;
+    }
+
+    Ok(polygon)
+}
+
+/// Checks that the direction from the contour vertex at `pos` to `p` points
+/// into the filled area.
+fn is_locally_inside(points: &[Point], contour: &[usize], pos: usize, p: Point) -> bool {
+    let len = contour.len();
+    let prev = points[contour[(pos + len - 1) % len]];
+    let curr = points[contour[pos]];
+    let next = points[contour[(pos + 1) % len]];
+
+    if cross(prev, curr, next) >= 0.0 {
+        cross(prev, curr, p) > 0.0 && cross(curr, next, p) > 0.0
+    } else {
+        cross(prev, curr, p) > 0.0 || cross(curr, next, p) > 0.0
+    }
+}
+
+/// Checks that the `a`-`b` segment intersects any edge of the contour
+/// or passes through any of its vertices.
+///
+/// Edges that share an end point with the segment are ignored.
+fn crosses_contour(points: &[Point], contour: &[usize], a: usize, b: usize) -> bool {
+    let (pa, pb) = (points[a], points[b]);
+    let len2 = (pb.x - pa.x) * (pb.x - pa.x) + (pb.y - pa.y) * (pb.y - pa.y);
+    for i in 0..contour.len() {
+        let p1 = points[contour[i]];
+        let p2 = points[contour[(i + 1) % contour.len()]];
+        if p1 == pa || p1 == pb {
+            continue;
+        }
+
+        // A vertex on the segment.
+        let d1 = cross(pa, pb, p1);
+        if d1.abs() <= 1e-12 * len2 {
+            let t = ((p1.x - pa.x) * (pb.x - pa.x) + (p1.y - pa.y) * (pb.y - pa.y)) / len2;
+            if t > 0.0 && t < 1.0 {
+                return true;
+            }
+        }
+
+        if p2 == pa || p2 == pb {
+            continue;
+        }
+
+        let d2 = cross(pa, pb, p2);
+        let d3 = cross(p1, p2, pa);
+        let d4 = cross(p1, p2, pb);
+        if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+            && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Triangulates a positively oriented polygon using ear clipping.
+///
+/// Fails when no ear can be found, which can happen because of rounding errors.
+fn clip_ears(points: &[Point], mut polygon: Vec<usize>, indices: &mut Vec<u32>) -> Result<(), Error> {
+    let mut idx = 0;
+    // The number of vertices checked since the last clipped one.
+    let mut checked = 0;
+    while polygon.len() > 3 {
+        let len = polygon.len();
+        idx %= len;
+        let prev = polygon[(idx + len - 1) % len];
+        let curr = polygon[idx];
+        let next = polygon[(idx + 1) % len];
+
+        let (a, b, c) = (points[prev], points[curr], points[next]);
+        let area = cross(a, b, c);
+        let is_degenerate = area.abs() <= 1e-12 * distance(a, b) * distance(b, c);
+
+        if is_degenerate {
+            // Collinear and duplicated points are removed without producing a triangle.
+            polygon.remove(idx);
+            checked = 0;
+        } else if area > 0.0 && is_ear(points, &polygon, a, b, c) {
+            indices.extend_from_slice(&[prev as u32, curr as u32, next as u32]);
+            polygon.remove(idx);
+            checked = 0;
+        } else if checked > len {
+            // Clipping a vertex that is not an ear would produce
+            // overlapping triangles or cracks.
+            return Err(Error::TessellationFailed);
+        } else {
+            idx += 1;
+            checked += 1;
+        }
+    }
+
+    if polygon.len() == 3 && cross(points[polygon[0]], points[polygon[1]], points[polygon[2]]) > 0.0 {
+        indices.extend_from_slice(&[polygon[0] as u32, polygon[1] as u32, polygon[2] as u32]);
+    }
+
+    Ok(())
+}
+
+/// Checks that no reflex vertex of the polygon is inside the `a`-`b`-`c` triangle.
+fn is_ear(points: &[Point], polygon: &[usize], a: Point, b: Point, c: Point) -> bool {
+    let len = polygon.len();
+    for i in 0..len {
+        let p = points[polygon[i]];
+        if p == a || p == b || p == c {
+            continue;
+        }
+
+        let prev = points[polygon[(i + len - 1) % len]];
+        let next = points[polygon[(i + 1) % len]];
+        if cross(prev, p, next) > 0.0 {
+            // Convex vertices cannot be the only ones inside the triangle.
+            continue;
+        }
+
+        if cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0 {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn is_in_polygon(points: &[Point], polygon: &[usize], p: Point) -> bool {
+    let mut winding = 0;
+    for i in 0..polygon.len() {
+        let a = points[polygon[i]];
+        let b = points[polygon[(i + 1) % polygon.len()]];
+        if a.y <= p.y {
+            if b.y > p.y && cross(a, b, p) > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= p.y && cross(a, b, p) < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    winding != 0
+}
+
+/// Returns a positive value when `p` is to the left of the `a`-`b` line.
+fn cross(a: Point, b: Point, p: Point) -> f64 {
+    (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y)
+}
+
+fn signed_area(points: &[Point], polygon: &[usize]) -> f64 {
+    let mut area = 0.0;
+    for i in 0..polygon.len() {
+        let a = points[polygon[i]];
+        let b = points[polygon[(i + 1) % polygon.len()]];
+        area += a.x * b.y - b.x * a.y;
+    }
+
+    area / 2.0
+}
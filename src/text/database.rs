@@ -0,0 +1,298 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+
+// external
+use ttf_parser;
+
+// self
+use tree::{
+    Font,
+    FontStretch,
+    FontStyle,
+    FontWeight,
+};
+use {
+    Error,
+    GenericFontFamilies,
+    DEFAULT_FONT_FAMILY,
+};
+
+
+/// A font face.
+///
+/// A single file can contain multiple faces, like TrueType collections.
+#[derive(Clone)]
+pub struct FaceInfo {
+    /// Font family name.
+    ///
+    /// The typographic family name is preferred over the regular one.
+    pub family: String,
+    /// Font style.
+    pub style: FontStyle,
+    /// Font weight in the 1..1000 range.
+    pub weight: u16,
+    /// Font stretch.
+    ///
+    /// Never `Wider` or `Narrower`.
+    pub stretch: FontStretch,
+    data: Rc<Vec<u8>>,
+    index: u32,
+}
+
+impl FaceInfo {
+    /// Parses the face data.
+    pub(crate) fn font(&self) -> Option<ttf_parser::Face> {
+        ttf_parser::Face::from_slice(&self.data, self.index).ok()
+    }
+}
+
+
+/// A list of fonts available for the text conversion.
+#[derive(Default)]
+pub struct FontDatabase {
+    faces: Vec<FaceInfo>,
+}
+
+impl FontDatabase {
+    /// Creates an empty database.
+    pub fn new() -> Self {
+        FontDatabase {
+            faces: Vec::new(),
+        }
+    }
+
+    /// Returns all loaded faces.
+    pub fn faces(&self) -> &[FaceInfo] {
+        &self.faces
+    }
+
+    /// Loads TrueType or OpenType font data.
+    ///
+    /// All faces of a font collection will be loaded.
+    /// Faces that cannot be parsed are skipped.
+    pub fn load_font_data(&mut self, data: Vec<u8>) {
+        let data = Rc::new(data);
+        let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+        for index in 0..count {
+            match parse_face(data.clone(), index) {
+                Some(face) => self.faces.push(face),
+                None => warn!("Failed to parse a font face #{}. Skipped.", index),
+            }
+        }
+    }
+
+    /// Loads a TrueType or OpenType font file.
+    pub fn load_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let mut file = fs::File::open(path.as_ref()).map_err(|_| Error::FileOpenFailed)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).map_err(|_| Error::FileOpenFailed)?;
+        self.load_font_data(data);
+        Ok(())
+    }
+
+    /// Loads all font files from the directory and its subdirectories.
+    ///
+    /// Only files with `ttf`, `otf`, `ttc` and `otc` extensions are loaded.
+    pub fn load_fonts_dir<P: AsRef<Path>>(&mut self, dir: P) {
+        let entries = try_opt_warn!(fs::read_dir(dir.as_ref()).ok(), (),
+                                    "Failed to read the '{}' directory.", dir.as_ref().display());
+
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(_) => continue,
+            };
+
+            if path.is_dir() {
+                self.load_fonts_dir(&path);
+                continue;
+            }
+
+            let is_font = match path.extension().and_then(|e| e.to_str()) {
+                Some(ext) => {
+                    let ext = ext.to_lowercase();
+                    ext == "ttf" || ext == "otf" || ext == "ttc" || ext == "otc"
+                }
+                None => false,
+            };
+
+            if is_font && self.load_font_file(&path).is_err() {
+                warn!("Failed to load the '{}' font.", path.display());
+            }
+        }
+    }
+
+    /// Selects a face that matches the font the best.
+    ///
+    /// Families are checked in order and the first one with at least one face is used.
+    /// Generic families are mapped to the family names using `families`.
+    /// Faces in the family are matched using the CSS font matching algorithm.
+    ///
+    /// When no family matches, the default one is used, and then any loaded face.
+    pub(crate) fn select(&self, font: &Font, families: &GenericFontFamilies) -> Option<&FaceInfo> {
+        let names = families.resolve(&font.family);
+        for name in &names {
+            if let Some(face) = self.select_in_family(name, font) {
+                return Some(face);
+            }
+        }
+
+        warn!("No match for the '{}' font family.", names.join(", "));
+
+        match self.select_in_family(DEFAULT_FONT_FAMILY, font) {
+            Some(face) => Some(face),
+            None => match_face(self.faces.iter().collect(), font),
+        }
+    }
+
+    fn select_in_family(&self, family: &str, font: &Font) -> Option<&FaceInfo> {
+        let family = family.to_lowercase();
+        let candidates = self.faces.iter().filter(|f| f.family.to_lowercase() == family).collect();
+        match_face(candidates, font)
+    }
+}
+
+fn parse_face(data: Rc<Vec<u8>>, index: u32) -> Option<FaceInfo> {
+    let (family, style, weight, stretch) = {
+        let font = ttf_parser::Face::from_slice(&data, index).ok()?;
+
+        let style = if font.is_italic() {
+            FontStyle::Italic
+        } else if font.is_oblique() {
+            FontStyle::Oblique
+        } else {
+            FontStyle::Normal
+        };
+
+        let stretch = match font.width() {
+            ttf_parser::Width::UltraCondensed => FontStretch::UltraCondensed,
+            ttf_parser::Width::ExtraCondensed => FontStretch::ExtraCondensed,
+            ttf_parser::Width::Condensed => FontStretch::Condensed,
+            ttf_parser::Width::SemiCondensed => FontStretch::SemiCondensed,
+            ttf_parser::Width::Normal => FontStretch::Normal,
+            ttf_parser::Width::SemiExpanded => FontStretch::SemiExpanded,
+            ttf_parser::Width::Expanded => FontStretch::Expanded,
+            ttf_parser::Width::ExtraExpanded => FontStretch::ExtraExpanded,
+            ttf_parser::Width::UltraExpanded => FontStretch::UltraExpanded,
+        };
+
+        (family_name(&font)?, style, font.weight().to_number(), stretch)
+    };
+
+    Some(FaceInfo {
+        family,
+        style,
+        weight,
+        stretch,
+        data,
+        index,
+    })
+}
+
+/// Returns the typographic family name or the family name, when the first one is not set.
+fn family_name(font: &ttf_parser::Face) -> Option<String> {
+    let mut family = None;
+    for name in font.names() {
+        if !name.is_unicode() {
+            continue;
+        }
+
+        if name.name_id() == ttf_parser::name_id::TYPOGRAPHIC_FAMILY {
+            return name.to_string();
+        } else if name.name_id() == ttf_parser::name_id::FAMILY && family.is_none() {
+            family = name.to_string();
+        }
+    }
+
+    family
+}
+
+/// Selects a face using the
+/// [CSS font matching algorithm](https://www.w3.org/TR/css-fonts-3/#font-style-matching).
+fn match_face<'a>(mut candidates: Vec<&'a FaceInfo>, font: &Font) -> Option<&'a FaceInfo> {
+    if candidates.is_empty() {
+        return None;
+    }
+
+    // Stretch. Narrower widths are preferred for the normal and condensed ones.
+    let stretch = stretch_to_number(font.stretch);
+    let best_stretch = {
+        let values: Vec<u16> = candidates.iter().map(|f| stretch_to_number(f.stretch)).collect();
+        let narrower = values.iter().cloned().filter(|v| *v < stretch).max();
+        let wider = values.iter().cloned().filter(|v| *v > stretch).min();
+
+        if values.contains(&stretch) {
+            stretch
+        } else if stretch <= 5 {
+            narrower.or(wider)?
+        } else {
+            wider.or(narrower)?
+        }
+    };
+    candidates.retain(|f| stretch_to_number(f.stretch) == best_stretch);
+
+    // Style.
+    let styles = match font.style {
+        FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+        FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+        FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+    };
+    let best_style = *styles.iter().find(|s| candidates.iter().any(|f| f.style == **s))?;
+    candidates.retain(|f| f.style == best_style);
+
+    // Weight.
+    let weight = weight_to_number(font.weight);
+    let best_weight = {
+        let values: Vec<u16> = candidates.iter().map(|f| f.weight).collect();
+        let lighter = values.iter().cloned().filter(|v| *v < weight).max();
+        let heavier = values.iter().cloned().filter(|v| *v > weight).min();
+
+        if values.contains(&weight) {
+            weight
+        } else if weight == 400 && values.contains(&500) {
+            500
+        } else if weight == 500 && values.contains(&400) {
+            400
+        } else if weight <= 500 {
+            lighter.or(heavier)?
+        } else {
+            heavier.or(lighter)?
+        }
+    };
+
+    candidates.into_iter().find(|f| f.weight == best_weight)
+}
+
+fn stretch_to_number(stretch: FontStretch) -> u16 {
+    match stretch {
+        FontStretch::UltraCondensed => 1,
+        FontStretch::ExtraCondensed => 2,
+        FontStretch::Condensed => 3,
+        FontStretch::SemiCondensed | FontStretch::Narrower => 4,
+        FontStretch::Normal => 5,
+        FontStretch::SemiExpanded | FontStretch::Wider => 6,
+        FontStretch::Expanded => 7,
+        FontStretch::ExtraExpanded => 8,
+        FontStretch::UltraExpanded => 9,
+    }
+}
+
+fn weight_to_number(weight: FontWeight) -> u16 {
+    match weight {
+        FontWeight::W100 => 100,
+        FontWeight::W200 => 200,
+        FontWeight::W300 => 300,
+        FontWeight::W400 => 400,
+        FontWeight::W500 => 500,
+        FontWeight::W600 => 600,
+        FontWeight::W700 => 700,
+        FontWeight::W800 => 800,
+        FontWeight::W900 => 900,
+    }
+}
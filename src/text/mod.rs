@@ -0,0 +1,443 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Text to paths conversion.
+//!
+//! Available only with the `text` feature.
+//!
+//! Fonts are loaded into a [`FontDatabase`] from files or memory
+//! and then [`text_to_paths`] replaces `Text` nodes with glyph outlines.
+//!
+//! The layout is intentionally simple: grapheme clusters are positioned
+//! using `Text::clusters`, glyphs are mapped one-to-one from characters
+//! and only the `kern` table is used for kerning.
+//! There is no bidi, complex scripts shaping, font fallback for missing glyphs
+//! or `font-variant` support.
+//!
+//! [`FontDatabase`]: struct.FontDatabase.html
+//! [`text_to_paths`]: fn.text_to_paths.html
+
+// external
+use ttf_parser::{
+    self,
+    GlyphId,
+};
+
+// self
+use geom::*;
+use path_utils;
+use tree::{
+    FuzzyEq,
+    FuzzyZero,
+    Node,
+    NodeKind,
+    Path,
+    PathSegment,
+    Text,
+    TextAnchor,
+    TextCluster,
+    TextDecorationStyle,
+    TextSpan,
+    Transform,
+    Tree,
+};
+use {
+    GenericFontFamilies,
+    Options,
+};
+
+mod database;
+
+pub use self::database::{
+    FaceInfo,
+    FontDatabase,
+};
+
+
+/// Replaces all `Text` nodes with paths, including the `defs` ones.
+///
+/// Each text span becomes a separate path with the span fill and stroke
+/// and each text decoration becomes a separate path with its own style.
+/// Underline and overline are placed before the span path and line-through after it.
+/// Paths have the text transform and the first one has the text ID.
+///
+/// Generic font families are mapped to the family names using `Options::font_families`.
+///
+/// Text nodes are left untouched when the database is empty.
+pub fn text_to_paths(tree: &mut Tree, db: &FontDatabase, opt: &Options) {
+    if db.faces().is_empty() {
+        warn!("No fonts are loaded. Text conversion is skipped.");
+        return;
+    }
+
+    tree.invalidate_reference_index();
+
+    let nodes: Vec<Node> = tree.root().descendants().filter(|node| {
+        if let NodeKind::Text(_) = *node.borrow() { true } else { false }
+    }).collect();
+
+    for mut node in nodes {
+        let paths = match *node.borrow() {
+            NodeKind::Text(ref text) => outline_text(text, db, &opt.font_families),
+            _ => continue,
+        };
+
+        for path in paths {
+            node.insert_before(Node::new(NodeKind::Path(path)));
+        }
+
+        node.detach();
+    }
+}
+
+/// A cluster placed on the canvas.
+struct PlacedCluster {
+    span_index: usize,
+    x: f64,
+    y: f64,
+    rotate: f64,
+    /// A font units to the user units scale.
+    scale: f64,
+    /// An advance in the user units.
+    advance: f64,
+    /// Glyphs with offsets in the font units.
+    glyphs: Vec<(GlyphId, f64)>,
+}
+
+impl PlacedCluster {
+    /// Returns a transform from the cluster font units to the text coordinates.
+    fn transform(&self) -> Transform {
+        let mut ts = Transform::new(1.0, 0.0, 0.0, 1.0, self.x, self.y);
+        if !self.rotate.is_fuzzy_zero() {
+            let (sin, cos) = self.rotate.to_radians().sin_cos();
+            ts.append(&Transform::new(cos, sin, -sin, cos, 0.0, 0.0));
+        }
+
+        // Fonts use the Y-up coordinate system.
+        ts.append(&Transform::new(self.scale, 0.0, 0.0, -self.scale, 0.0, 0.0));
+        ts
+    }
+}
+
+fn outline_text(text: &Text, db: &FontDatabase, families: &GenericFontFamilies) -> Vec<Path> {
+    let spans: Vec<&TextSpan> = text.chunks.iter().flat_map(|c| c.spans.iter()).collect();
+    let fonts: Vec<Option<ttf_parser::Face>> = spans.iter().map(|span| {
+        db.select(&span.font, families)
+            .and_then(|face| face.font())
+            .and_then(|font| if font.units_per_em().is_some() { Some(font) } else { None })
+    }).collect();
+
+    let clusters = text.clusters();
+    let mut placed = layout_clusters(&clusters, &fonts);
+
+    // Each absolute position starts a new anchored chunk.
+    let mut start = 0;
+    for i in 1..clusters.len() + 1 {
+        if i == clusters.len() || clusters[i].x.is_some() || clusters[i].y.is_some() {
+            apply_anchor(&mut placed[start..i], clusters[start].anchor);
+            start = i;
+        }
+    }
+
+    let mut paths = Vec::new();
+    for (idx, span) in spans.iter().enumerate() {
+        let font = match fonts[idx] {
+            Some(ref font) => font,
+            None => continue,
+        };
+
+        let span_clusters: Vec<&PlacedCluster> = placed.iter()
+            .filter(|c| c.span_index == idx)
+            .collect();
+
+        let metrics = DecorationMetrics::new(font);
+        let decoration = |style: &Option<TextDecorationStyle>, line: (f64, f64)| {
+            decoration_path(style, &span_clusters, line, text.transform)
+        };
+
+        paths.extend(decoration(&span.decoration.underline, metrics.underline));
+        paths.extend(decoration(&span.decoration.overline, metrics.overline));
+
+        if span.fill.is_some() || span.stroke.is_some() {
+            let mut segments = Vec::new();
+            for cluster in &span_clusters {
+                let ts = cluster.transform();
+                for &(id, offset) in &cluster.glyphs {
+                    let mut glyph_ts = ts;
+                    glyph_ts.append(&Transform::new(1.0, 0.0, 0.0, 1.0, offset, 0.0));
+                    segments.extend(path_utils::transform_segments(&outline_glyph(font, id),
+                                                                   &glyph_ts));
+                }
+            }
+
+            paths.push(Path {
+                id: String::new(),
+                transform: text.transform,
+                fill: span.fill.clone(),
+                stroke: span.stroke.clone(),
+                segments,
+            });
+        }
+
+        paths.extend(decoration(&span.decoration.line_through, metrics.line_through));
+    }
+
+    // Spaces and empty decorations.
+    paths.retain(|p| !p.segments.is_empty());
+
+    if let Some(path) = paths.first_mut() {
+        path.id = text.id.clone();
+    }
+
+    paths
+}
+
+/// Places clusters one after another starting at the (0 0) position.
+fn layout_clusters(
+    clusters: &[TextCluster],
+    fonts: &[Option<ttf_parser::Face>],
+) -> Vec<PlacedCluster> {
+    let mut placed = Vec::with_capacity(clusters.len());
+    let mut x = 0.0;
+    let mut y = 0.0;
+    // Shifts are accumulated, so only the difference with the previous one is applied.
+    let mut prev_dx = 0.0;
+    let mut prev_dy = 0.0;
+    let mut prev_glyph: Option<(usize, GlyphId)> = None;
+    for cluster in clusters {
+        if let Some(v) = cluster.x {
+            x = v;
+            prev_dx = 0.0;
+        }
+
+        if let Some(v) = cluster.y {
+            y = v;
+            prev_dy = 0.0;
+        }
+
+        x += cluster.dx - prev_dx;
+        y += cluster.dy - prev_dy;
+        prev_dx = cluster.dx;
+        prev_dy = cluster.dy;
+
+        let mut glyphs = Vec::new();
+        let mut advance = 0.0;
+        let mut scale = 1.0;
+        if let Some(ref font) = fonts[cluster.span_index] {
+            scale = cluster.span.font.size / font.units_per_em().unwrap_or(1000) as f64;
+
+            for c in cluster.text.chars() {
+                let id = font.glyph_index(c).unwrap_or(GlyphId(0));
+
+                // Kerning is applied only between clusters of the same span
+                // that are not positioned explicitly.
+                if glyphs.is_empty() && cluster.x.is_none() {
+                    if let Some((span_index, prev_id)) = prev_glyph {
+                        if span_index == cluster.span_index {
+                            x += kerning(font, prev_id, id) * scale;
+                        }
+                    }
+                }
+
+                glyphs.push((id, advance));
+                advance += font.glyph_hor_advance(id).unwrap_or(0) as f64;
+                prev_glyph = Some((cluster.span_index, id));
+            }
+        }
+
+        placed.push(PlacedCluster {
+            span_index: cluster.span_index,
+            x,
+            y,
+            rotate: cluster.rotate,
+            scale,
+            advance: advance * scale,
+            glyphs,
+        });
+
+        x += advance * scale;
+    }
+
+    placed
+}
+
+fn apply_anchor(clusters: &mut [PlacedCluster], anchor: TextAnchor) {
+    let width = match (clusters.first(), clusters.last()) {
+        (Some(first), Some(last)) => last.x + last.advance - first.x,
+        _ => return,
+    };
+
+    let shift = match anchor {
+        TextAnchor::Start => return,
+        TextAnchor::Middle => -width / 2.0,
+        TextAnchor::End => -width,
+    };
+
+    for cluster in clusters {
+        cluster.x += shift;
+    }
+}
+
+fn kerning(font: &ttf_parser::Face, left: GlyphId, right: GlyphId) -> f64 {
+    font.kerning_subtables()
+        .filter(|st| st.is_horizontal() && !st.has_cross_stream())
+        .filter_map(|st| st.glyphs_kerning(left, right))
+        .next()
+        .map(f64::from)
+        .unwrap_or(0.0)
+}
+
+/// Text decoration lines as a top position and a thickness in the font units.
+struct DecorationMetrics {
+    underline: (f64, f64),
+    overline: (f64, f64),
+    line_through: (f64, f64),
+}
+
+impl DecorationMetrics {
+    fn new(font: &ttf_parser::Face) -> Self {
+        let units_per_em = font.units_per_em().unwrap_or(1000) as f64;
+
+        let line = |metrics: Option<ttf_parser::LineMetrics>, default_position: f64| {
+            match metrics {
+                Some(m) if m.thickness > 0 => (m.position as f64, m.thickness as f64),
+                _ => (default_position, units_per_em / 20.0),
+            }
+        };
+
+        let underline = line(font.underline_metrics(), -units_per_em / 10.0);
+        let line_through = line(font.strikeout_metrics(), units_per_em / 3.0);
+
+        DecorationMetrics {
+            underline,
+            overline: (font.ascender() as f64, underline.1),
+            line_through,
+        }
+    }
+}
+
+fn decoration_path(
+    style: &Option<TextDecorationStyle>,
+    clusters: &[&PlacedCluster],
+    line: (f64, f64),
+    transform: Transform,
+) -> Option<Path> {
+    let style = style.as_ref()?;
+    if style.fill.is_none() && style.stroke.is_none() {
+        return None;
+    }
+
+    Some(Path {
+        id: String::new(),
+        transform,
+        fill: style.fill.clone(),
+        stroke: style.stroke.clone(),
+        segments: decoration_segments(clusters, line),
+    })
+}
+
+/// Creates a line along all clusters.
+///
+/// Adjacent clusters without rotation are joined into a single rectangle.
+fn decoration_segments(clusters: &[&PlacedCluster], line: (f64, f64)) -> Vec<PathSegment> {
+    let (top, thickness) = line;
+
+    let mut segments = Vec::new();
+    // A rectangle in the text coordinates: x1, x2, y, scale.
+    let mut current: Option<(f64, f64, f64, f64)> = None;
+    for cluster in clusters {
+        if cluster.advance.is_fuzzy_zero() {
+            continue;
+        }
+
+        if cluster.rotate.is_fuzzy_zero() {
+            if let Some(ref mut rect) = current {
+                if rect.1.fuzzy_eq(&cluster.x) && rect.2.fuzzy_eq(&cluster.y)
+                    && rect.3.fuzzy_eq(&cluster.scale) {
+                    rect.1 = cluster.x + cluster.advance;
+                    continue;
+                }
+            }
+
+            if let Some((x1, x2, y, scale)) = current.take() {
+                push_rect(x1, y - top * scale, x2 - x1, thickness * scale, &mut segments);
+            }
+
+            current = Some((cluster.x, cluster.x + cluster.advance, cluster.y, cluster.scale));
+        } else {
+            let mut rect = Vec::new();
+            push_rect(0.0, top - thickness, cluster.advance / cluster.scale, thickness, &mut rect);
+            segments.extend(path_utils::transform_segments(&rect, &cluster.transform()));
+        }
+    }
+
+    if let Some((x1, x2, y, scale)) = current {
+        push_rect(x1, y - top * scale, x2 - x1, thickness * scale, &mut segments);
+    }
+
+    segments
+}
+
+fn push_rect(x: f64, y: f64, width: f64, height: f64, segments: &mut Vec<PathSegment>) {
+    segments.push(PathSegment::MoveTo { x, y });
+    segments.push(PathSegment::LineTo { x: x + width, y });
+    segments.push(PathSegment::LineTo { x: x + width, y: y + height });
+    segments.push(PathSegment::LineTo { x, y: y + height });
+    segments.push(PathSegment::ClosePath);
+}
+
+/// Returns glyph outline in the font units.
+fn outline_glyph(font: &ttf_parser::Face, id: GlyphId) -> Vec<PathSegment> {
+    let mut builder = PathBuilder {
+        segments: Vec::new(),
+        last: Point::new(0.0, 0.0),
+    };
+
+    match font.outline_glyph(id, &mut builder) {
+        Some(_) => builder.segments,
+        None => Vec::new(),
+    }
+}
+
+struct PathBuilder {
+    segments: Vec<PathSegment>,
+    last: Point,
+}
+
+impl ttf_parser::OutlineBuilder for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.last = Point::new(x as f64, y as f64);
+        self.segments.push(PathSegment::MoveTo { x: x as f64, y: y as f64 });
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.last = Point::new(x as f64, y as f64);
+        self.segments.push(PathSegment::LineTo { x: x as f64, y: y as f64 });
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let p1 = Point::new(x1 as f64, y1 as f64);
+        let p = Point::new(x as f64, y as f64);
+        let curve = CubicBezier::from_quad(self.last, p1, p);
+        self.last = p;
+        self.segments.push(PathSegment::CurveTo {
+            x1: curve.p1.x, y1: curve.p1.y,
+            x2: curve.p2.x, y2: curve.p2.y,
+            x: p.x, y: p.y,
+        });
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.last = Point::new(x as f64, y as f64);
+        self.segments.push(PathSegment::CurveTo {
+            x1: x1 as f64, y1: y1 as f64,
+            x2: x2 as f64, y2: y2 as f64,
+            x: x as f64, y: y as f64,
+        });
+    }
+
+    fn close(&mut self) {
+        self.segments.push(PathSegment::ClosePath);
+    }
+}
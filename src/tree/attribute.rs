@@ -24,7 +24,10 @@ use geom::*;
 ///
 /// Just like `f64` but immutable and asserts in debug if
 /// value is out of 0..1 range.
+///
+/// Deserialization fails for values out of the 0..1 range.
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 pub struct Opacity(f64);
 
 impl Opacity {
@@ -64,6 +67,7 @@ pub type StopOffset = Opacity;
 /// `stroke-linecap` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum LineCap {
     Butt,
     Round,
@@ -75,6 +79,7 @@ pub enum LineCap {
 /// `stroke-linejoin` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum LineJoin {
     Miter,
     Round,
@@ -86,6 +91,7 @@ pub enum LineJoin {
 /// `fill-rule` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FillRule {
     NonZero,
     EvenOdd,
@@ -96,6 +102,7 @@ pub enum FillRule {
 /// `*Units` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Units {
     UserSpaceOnUse,
     ObjectBoundingBox,
@@ -106,6 +113,7 @@ pub enum Units {
 /// `spreadMethod` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum SpreadMethod {
     Pad,
     Reflect,
@@ -118,6 +126,7 @@ pub enum SpreadMethod {
 /// Defines the style of the line that should be rendered.
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextDecorationStyle {
     pub fill: Option<Fill>,
     pub stroke: Option<Stroke>,
@@ -135,6 +144,7 @@ impl Default for TextDecorationStyle {
 
 /// A text decoration.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextDecoration {
     /// Draw underline using specified style.
     ///
@@ -166,17 +176,132 @@ impl Default for TextDecoration {
 /// `text-anchor` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum TextAnchor {
     Start,
     Middle,
     End,
 }
 
+/// A generic font family.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum GenericFontFamily {
+    /// `serif`
+    Serif,
+    /// `sans-serif`
+    SansSerif,
+    /// `monospace`
+    Monospace,
+    /// `cursive`
+    Cursive,
+    /// `fantasy`
+    Fantasy,
+    /// `system-ui`
+    SystemUi,
+}
+
+impl GenericFontFamily {
+    /// Parses a generic family keyword.
+    ///
+    /// Keywords are case-insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "serif" => Some(GenericFontFamily::Serif),
+            "sans-serif" => Some(GenericFontFamily::SansSerif),
+            "monospace" => Some(GenericFontFamily::Monospace),
+            "cursive" => Some(GenericFontFamily::Cursive),
+            "fantasy" => Some(GenericFontFamily::Fantasy),
+            "system-ui" => Some(GenericFontFamily::SystemUi),
+            _ => None,
+        }
+    }
+
+    /// Returns a generic family keyword.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GenericFontFamily::Serif => "serif",
+            GenericFontFamily::SansSerif => "sans-serif",
+            GenericFontFamily::Monospace => "monospace",
+            GenericFontFamily::Cursive => "cursive",
+            GenericFontFamily::Fantasy => "fantasy",
+            GenericFontFamily::SystemUi => "system-ui",
+        }
+    }
+}
+
+/// A font family.
+///
+/// An item of the `font-family` attribute in the SVG.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FontFamily {
+    /// A family name, like `Times New Roman`.
+    ///
+    /// Quotes and escapes are already resolved.
+    Named(String),
+    /// A generic family, like `sans-serif`.
+    Generic(GenericFontFamily),
+}
+
+impl fmt::Display for FontFamily {
+    /// Writes the family as in the `font-family` attribute.
+    ///
+    /// A family name is quoted when it cannot be written as a sequence of identifiers.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            FontFamily::Named(ref name) => name,
+            FontFamily::Generic(generic) => return write!(f, "{}", generic.name()),
+        };
+
+        // A name that matches a keyword must be quoted to be parsed as a name.
+        let is_keyword = GenericFontFamily::from_name(name).is_some()
+            || ["inherit", "initial", "unset", "default"].contains(&name.to_lowercase().as_str());
+
+        if !is_keyword && name.split(' ').all(is_identifier) {
+            return write!(f, "{}", name);
+        }
+
+        write!(f, "'")?;
+        for c in name.chars() {
+            match c {
+                '\'' | '\\' => write!(f, "\\{}", c)?,
+                '\n' => write!(f, "\\a ")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "'")
+    }
+}
+
+/// Checks that the text is a CSS identifier without escapes.
+fn is_identifier(text: &str) -> bool {
+    let is_name_start = |c: char| {
+        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_' || c as u32 >= 0x80
+    };
+    let is_name = |c: char| is_name_start(c) || (c >= '0' && c <= '9') || c == '-';
+
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('-') => {
+            match chars.next() {
+                Some(c) if is_name_start(c) => {}
+                _ => return false,
+            }
+        }
+        Some(c) if is_name_start(c) => {}
+        _ => return false,
+    }
+
+    chars.all(is_name)
+}
+
 /// A font style.
 ///
 /// `font-style` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontStyle {
     Normal,
     Italic,
@@ -188,6 +313,7 @@ pub enum FontStyle {
 /// `font-variant` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontVariant {
     Normal,
     SmallCaps,
@@ -198,6 +324,7 @@ pub enum FontVariant {
 /// `font-weight` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontWeight {
     W100,
     W200,
@@ -215,6 +342,7 @@ pub enum FontWeight {
 /// `font-stretch` attribute in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FontStretch {
     Normal,
     Wider,
@@ -235,9 +363,13 @@ pub enum FontStretch {
 /// `paint` value type in the SVG.
 #[allow(missing_docs)]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Paint {
     /// Paint with a color.
-    Color(Color),
+    Color(
+        #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::color"))]
+        Color
+    ),
     /// Paint using a referenced element.
     Link(String),
 }
@@ -255,6 +387,7 @@ impl fmt::Debug for Paint {
 /// A fill style.
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Fill {
     pub paint: Paint,
     pub opacity: Opacity,
@@ -275,8 +408,10 @@ impl Default for Fill {
 /// A stroke style.
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stroke {
     pub paint: Paint,
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub dasharray: Option<NumberList>,
     pub dashoffset: f64,
     pub miterlimit: f64,
@@ -305,12 +440,13 @@ impl Default for Stroke {
 /// A font description.
 #[allow(missing_docs)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Font {
-    /// Font family.
+    /// A list of font families in the order of preference.
     ///
-    /// Currently, is exactly the same as in the `font-family` attribute.
-    /// So it can look like `Verdana, 'Times New Roman', sans-serif`.
-    pub family: String,
+    /// Never empty.
+    /// Generic families can be mapped to the family names using `Options::font_families`.
+    pub family: Vec<FontFamily>,
     pub size: f64,
     pub style: FontStyle,
     pub variant: FontVariant,
@@ -321,7 +457,7 @@ pub struct Font {
 impl Default for Font {
     fn default() -> Self {
         Font {
-            family: ::DEFAULT_FONT_FAMILY.to_owned(),
+            family: vec![FontFamily::Named(::DEFAULT_FONT_FAMILY.to_owned())],
             size: ::DEFAULT_FONT_SIZE,
             style: FontStyle::Normal,
             variant: FontVariant::Normal,
@@ -334,20 +470,26 @@ impl Default for Font {
 
 /// View box.
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ViewBox {
     /// Value of the `viewBox` attribute.
     pub rect: Rect,
     /// Value of the `preserveAspectRatio` attribute.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::aspect"))]
     pub aspect: AspectRatio,
 }
 
 
 /// A path absolute segment.
 ///
-/// Unlike the SVG spec can contain only `M`, `L`, `C` and `Z` segments.
-/// All other segments will be converted to this one.
+/// Unlike the SVG spec, all segments are absolute and shorthand ones are expanded.
+///
+/// By default, paths contain only `M`, `L`, `C` and `Z` segments and all other
+/// segments are converted into them. `QuadTo` and `ArcTo` segments are present
+/// only with `PathProfile::Extended`.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PathSegment {
     MoveTo {
         x: f64,
@@ -365,5 +507,24 @@ pub enum PathSegment {
         x: f64,
         y: f64,
     },
+    QuadTo {
+        x1: f64,
+        y1: f64,
+        x: f64,
+        y: f64,
+    },
+    /// An elliptical arc in the SVG endpoint parametrization.
+    ///
+    /// Radii are always positive, but can be too small to reach the end point.
+    /// In this case they must be scaled up like the SVG spec requires.
+    ArcTo {
+        rx: f64,
+        ry: f64,
+        x_axis_rotation: f64,
+        large_arc: bool,
+        sweep: bool,
+        x: f64,
+        y: f64,
+    },
     ClosePath,
 }
@@ -162,6 +162,14 @@ fn conv_elements(
                         PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
                             path.push(SvgDomPathSegment::CurveTo { abs: true, x1, y1, x2, y2, x, y });
                         }
+                        PathSegment::QuadTo { x1, y1, x, y } => {
+                            path.push(SvgDomPathSegment::Quadratic { abs: true, x1, y1, x, y });
+                        }
+                        PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+                            path.push(SvgDomPathSegment::EllipticalArc {
+                                abs: true, rx, ry, x_axis_rotation, large_arc, sweep, x, y
+                            });
+                        }
                         PathSegment::ClosePath => {
                             path.push(SvgDomPathSegment::ClosePath { abs: true });
                         }
@@ -173,6 +181,52 @@ fn conv_elements(
                 conv_fill(tree, &p.fill, defs, parent, &mut path_elem);
                 conv_stroke(tree, &p.stroke, defs, &mut path_elem);
             }
+            NodeKind::Shape(ref shape) => {
+                let mut shape_elem = new_doc.create_element(match shape.kind {
+                    ShapeKind::Rect { .. } => EId::Rect,
+                    ShapeKind::Circle { .. } => EId::Circle,
+                    ShapeKind::Ellipse { .. } => EId::Ellipse,
+                    ShapeKind::Line { .. } => EId::Line,
+                    ShapeKind::Polyline { closed: false, .. } => EId::Polyline,
+                    ShapeKind::Polyline { closed: true, .. } => EId::Polygon,
+                });
+                parent.append(shape_elem.clone());
+
+                conv_transform(AId::Transform, &shape.transform, &mut shape_elem);
+                shape_elem.set_id(shape.id.clone());
+
+                match shape.kind {
+                    ShapeKind::Rect { x, y, width, height, rx, ry } => {
+                        conv_rect(Rect::new(x, y, width, height), &mut shape_elem);
+                        shape_elem.set_attribute((AId::Rx, rx));
+                        shape_elem.set_attribute((AId::Ry, ry));
+                    }
+                    ShapeKind::Circle { cx, cy, r } => {
+                        shape_elem.set_attribute((AId::Cx, cx));
+                        shape_elem.set_attribute((AId::Cy, cy));
+                        shape_elem.set_attribute((AId::R, r));
+                    }
+                    ShapeKind::Ellipse { cx, cy, rx, ry } => {
+                        shape_elem.set_attribute((AId::Cx, cx));
+                        shape_elem.set_attribute((AId::Cy, cy));
+                        shape_elem.set_attribute((AId::Rx, rx));
+                        shape_elem.set_attribute((AId::Ry, ry));
+                    }
+                    ShapeKind::Line { x1, y1, x2, y2 } => {
+                        shape_elem.set_attribute((AId::X1, x1));
+                        shape_elem.set_attribute((AId::Y1, y1));
+                        shape_elem.set_attribute((AId::X2, x2));
+                        shape_elem.set_attribute((AId::Y2, y2));
+                    }
+                    ShapeKind::Polyline { ref points, .. } => {
+                        let points: Vec<(f64, f64)> = points.iter().map(|p| (p.x, p.y)).collect();
+                        shape_elem.set_attribute((AId::Points, svgdom::Points::from(points)));
+                    }
+                }
+
+                conv_fill(tree, &shape.fill, defs, parent, &mut shape_elem);
+                conv_stroke(tree, &shape.stroke, defs, &mut shape_elem);
+            }
             NodeKind::Text(ref text) => {
                 let mut text_elem = new_doc.create_element(EId::Text);
                 parent.append(text_elem.clone());
@@ -482,7 +536,8 @@ fn conv_font(
     font: &Font,
     node: &mut svgdom::Node,
 ) {
-    node.set_attribute((AId::FontFamily, font.family.clone()));
+    let family: Vec<String> = font.family.iter().map(|f| f.to_string()).collect();
+    node.set_attribute((AId::FontFamily, family.join(", ")));
     node.set_attribute((AId::FontSize, font.size));
 
     node.set_attribute((AId::FontStyle,
@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use path_utils::{
+    self,
+    Polyline,
+};
+use super::*;
+
+
+/// The maximum distance between a curve and its flattened version in canvas pixels.
+const TOLERANCE: f64 = 0.1;
+
+pub fn nodes_at_point(tree: &Tree, x: f64, y: f64) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let ts = view_box_transform(&tree.root());
+    collect_nodes(tree, &tree.root(), ts, Point::new(x, y), &mut nodes);
+    nodes
+}
+
+fn collect_nodes(tree: &Tree, parent: &Node, parent_ts: Transform, p: Point, nodes: &mut Vec<Node>) {
+    // Children are checked in reverse order, so the topmost node will be the first one.
+    for node in parent.children().rev() {
+        let mut ts = parent_ts;
+        ts.append(&node.transform());
+
+        match *node.borrow() {
+            NodeKind::Group(ref g) => {
+                if let Some(ref id) = g.clip_path {
+                    if !is_in_clip_path(tree, &node, id, ts, p) {
+                        continue;
+                    }
+                }
+
+                collect_nodes(tree, &node, ts, p, nodes);
+            }
+            NodeKind::Path(ref path) => {
+                if is_in_path(path, ts, p, true) {
+                    nodes.push(node.clone());
+                }
+            }
+            NodeKind::Shape(ref shape) => {
+                if is_in_path(&shape.to_path(PathProfile::Extended, 0.0), ts, p, true) {
+                    nodes.push(node.clone());
+                }
+            }
+            NodeKind::Image(ref img) => {
+                if let Some(ts) = ts.inverse() {
+                    let p = ts.map_point(p);
+                    let r = img.view_box.rect;
+                    if p.x >= r.x && p.y >= r.y && p.x <= r.x + r.width && p.y <= r.y + r.height {
+                        nodes.push(node.clone());
+                    }
+                }
+            }
+            _ => {}
+        };
+    }
+}
+
+/// Checks that the point is inside the clip path used by the `node`.
+///
+/// `ts` is the `node` canvas transform.
+fn is_in_clip_path(tree: &Tree, node: &Node, id: &str, ts: Transform, p: Point) -> bool {
+    let clip = match tree.defs_by_id(id) {
+        Some(clip) => clip,
+        None => return true,
+    };
+
+    let mut ts = ts;
+    if let NodeKind::ClipPath(ref cp) = *clip.borrow() {
+        ts.append(&cp.transform);
+
+        if cp.units == Units::ObjectBoundingBox {
+            let bbox = match utils::calc_children_bbox(node) {
+                Some(bbox) => bbox,
+                None => return false,
+            };
+
+            ts.append(&Transform::new(bbox.width, 0.0, 0.0, bbox.height, bbox.x, bbox.y));
+        }
+    } else {
+        return true;
+    }
+
+    for child in clip.children() {
+        let mut child_ts = ts;
+        child_ts.append(&child.transform());
+
+        let is_in = match *child.borrow() {
+            NodeKind::Path(ref path) => is_in_path(path, child_ts, p, false),
+            NodeKind::Shape(ref shape) => is_in_path(&shape.to_path(PathProfile::Extended, 0.0), child_ts, p, false),
+            _ => false,
+        };
+
+        if is_in {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Checks that the point is inside the path fill or stroke.
+///
+/// `ts` is the path canvas transform.
+fn is_in_path(path: &Path, ts: Transform, p: Point, with_stroke: bool) -> bool {
+    let inv_ts = match ts.inverse() {
+        Some(v) => v,
+        None => return false,
+    };
+
+    // Check in the path coordinates, since the stroke width is defined in them.
+    let p = inv_ts.map_point(p);
+    let tolerance = TOLERANCE / utils::max_scale(&ts);
+
+    if let Some(ref fill) = path.fill {
+        let polylines = path_utils::polylines(&path.segments, tolerance);
+        if is_in_fill(&polylines, fill.rule, p) {
+            return true;
+        }
+    }
+
+    if with_stroke {
+        if let Some(ref stroke) = path.stroke {
+            // Use the same outline as `outline_strokes`,
+            // so joins, caps and dashes will be taken into account.
+            let outline = path_utils::stroke_to_path(&path.segments, stroke, tolerance);
+            let polylines = path_utils::polylines(&outline, tolerance);
+            if is_in_fill(&polylines, FillRule::NonZero, p) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+fn is_in_fill(polylines: &[Polyline], rule: FillRule, p: Point) -> bool {
+    // All subpaths are implicitly closed during filling.
+    let mut winding = 0;
+    for polyline in polylines {
+        let points = &polyline.points;
+        for i in 0..points.len() {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+
+            if a.y <= p.y {
+                if b.y > p.y && cross(a, b, p) > 0.0 {
+                    winding += 1;
+                }
+            } else if b.y <= p.y && cross(a, b, p) < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+
+    match rule {
+        FillRule::NonZero => winding != 0,
+        FillRule::EvenOdd => winding % 2 != 0,
+    }
+}
+
+/// Returns a positive value when `p` is to the left of the `a`-`b` line.
+fn cross(a: Point, b: Point, p: Point) -> f64 {
+    (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y)
+}
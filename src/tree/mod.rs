@@ -6,8 +6,12 @@
 
 extern crate rctree;
 
-use std::cell::Ref;
+use std::cell::{
+    Ref,
+    RefCell,
+};
 use std::path;
+use std::rc::Rc;
 
 // external
 use svgdom;
@@ -16,20 +20,41 @@ use svgdom;
 pub use self::node::*;
 pub use self::attribute::*;
 pub use self::io::load_svg_file;
+pub use self::refs::ReferenceIndex;
+pub use self::validate::{
+    ValidationIssue,
+    ValidationIssueKind,
+};
+pub use self::writer::{
+    Indent,
+    WriteOptions,
+};
+use geom::*;
+use utils;
 use {
     Error,
     Options,
+    PathProfile,
 };
+use options::DEFAULT_ARC_TOLERANCE;
 
 mod attribute;
 mod convert;
+mod hit_test;
 mod node;
 mod io;
+pub(crate) mod path_ops;
+mod refs;
+mod subtree;
+mod validate;
+mod writer;
+#[cfg(feature = "serde")] mod serialization;
 
 /// Basic traits for tree manipulations.
 pub mod prelude {
     pub use tree::FuzzyEq;
     pub use tree::FuzzyZero;
+    pub use geom::TransformExt;
     pub use super::NodeExt;
 }
 
@@ -37,8 +62,13 @@ pub mod prelude {
 pub type Node = rctree::Node<NodeKind>;
 
 /// A nodes tree container.
+///
+/// Implements `Serialize` and `Deserialize` when the `serde` feature is enabled.
+/// See `docs/serde_schema.adoc` for the schema.
 pub struct Tree {
     root: Node,
+    path_profile: PathProfile,
+    refs: RefCell<Option<Rc<ReferenceIndex>>>,
 }
 
 impl Tree {
@@ -79,6 +109,8 @@ impl Tree {
     }
 
     /// Creates a new `Tree`.
+    ///
+    /// The tree path profile is `PathProfile::Default`.
     pub fn create(svg: Svg) -> Self {
         let mut root_node = Node::new(NodeKind::Svg(svg));
         let defs_node = Node::new(NodeKind::Defs);
@@ -86,6 +118,8 @@ impl Tree {
 
         Tree {
             root: root_node,
+            path_profile: PathProfile::Default,
+            refs: RefCell::new(None),
         }
     }
 
@@ -94,6 +128,24 @@ impl Tree {
         self.root.clone()
     }
 
+    /// Returns the path profile of the tree.
+    ///
+    /// Defines which path segments the tree paths can contain. Parsed trees have
+    /// the `Options::path_profile` they were parsed with. Deserialized trees
+    /// and trees returned by `NodeExt::tree` have `PathProfile::Extended`,
+    /// since the original profile is unknown.
+    pub fn path_profile(&self) -> PathProfile {
+        self.path_profile
+    }
+
+    /// Sets the path profile of the tree.
+    ///
+    /// Paths are not converted. Should be used after replacing the tree paths,
+    /// so `validate` will check them using the new profile.
+    pub fn set_path_profile(&mut self, profile: PathProfile) {
+        self.path_profile = profile;
+    }
+
     /// Returns the `Svg` node value.
     pub fn svg_node(&self) -> Ref<Svg> {
         Ref::map(self.root.borrow(), |v| {
@@ -117,11 +169,20 @@ impl Tree {
 
     /// Appends `NodeKind` to the `Defs` node.
     pub fn append_to_defs(&mut self, kind: NodeKind) -> Node {
+        self.invalidate_reference_index();
         let new_node = Node::new(kind);
         self.defs().append(new_node.clone());
         new_node
     }
 
+    /// Removes all `defs` elements that are not used by any renderable node.
+    ///
+    /// Elements that are used only by other unused elements will be removed too.
+    pub fn remove_unused_defs(&mut self) {
+        self.invalidate_reference_index();
+        subtree::remove_unused_defs(self)
+    }
+
     /// Returns `defs` child node by ID.
     pub fn defs_by_id(&self, id: &str) -> Option<Node> {
         for n in self.defs().children() {
@@ -153,12 +214,222 @@ impl Tree {
         None
     }
 
+    /// Checks that the tree satisfies all the guarantees described
+    /// in the `docs/usvg_spec.adoc`.
+    ///
+    /// Path segments are checked using the tree `path_profile`.
+    ///
+    /// Trees produced by the parser are always valid, but manually
+    /// edited trees may not be.
+    ///
+    /// Returns an empty list when no issues were found.
+    pub fn validate(&self) -> Vec<ValidationIssue> {
+        validate::validate(self, self.path_profile)
+    }
+
+    /// Returns all nodes that reference a `defs` element with the specified ID.
+    ///
+    /// Links from fill, stroke, text spans, clip paths and masks are checked.
+    ///
+    /// Uses the cached `reference_index`.
+    pub fn users_of(&self, id: &str) -> Vec<Node> {
+        self.reference_index().users_of(id).to_vec()
+    }
+
+    /// Returns a reverse references index.
+    ///
+    /// The index is built on the first call and cached until the tree is modified
+    /// using the `Tree` methods, like `append_to_defs`, `remove_unused_defs`,
+    /// `import` or `outline_strokes`.
+    ///
+    /// Nodes can be modified without the `Tree`, so `invalidate_reference_index`
+    /// must be called after adding, removing or relinking nodes via `Node` handles.
+    pub fn reference_index(&self) -> Rc<ReferenceIndex> {
+        if let Some(ref index) = *self.refs.borrow() {
+            return index.clone();
+        }
+
+        let index = Rc::new(ReferenceIndex::new(self));
+        *self.refs.borrow_mut() = Some(index.clone());
+        index
+    }
+
+    /// Drops the cached reverse references index.
+    ///
+    /// It will be rebuilt on the next `reference_index` or `users_of` call.
+    pub fn invalidate_reference_index(&self) {
+        *self.refs.borrow_mut() = None;
+    }
+
+    /// Converts all shapes into paths, including the `defs` ones.
+    ///
+    /// Shapes are present only when `Options::keep_shapes` is set.
+    ///
+    /// See [`ShapeKind::to_path`] for details.
+    ///
+    /// [`ShapeKind::to_path`]: enum.ShapeKind.html#method.to_path
+    pub fn shapes_to_paths(&mut self, profile: PathProfile, arc_tolerance: f64) {
+        self.invalidate_reference_index();
+        path_ops::shapes_to_paths(self, profile, arc_tolerance)
+    }
+
+    /// Converts curves into lines in all paths, including the `defs` ones.
+    ///
+    /// Shapes are converted into paths first, using the default `Options::arc_tolerance`.
+    ///
+    /// `tolerance` is the maximum distance between a curve and its polyline
+    /// in the path coordinates and must be positive.
+    ///
+    /// See [`path_utils::flatten`] for details.
+    ///
+    /// [`path_utils::flatten`]: ../path_utils/fn.flatten.html
+    pub fn flatten_paths(&mut self, tolerance: f64) {
+        self.invalidate_reference_index();
+        path_ops::flatten_paths(self, tolerance, DEFAULT_ARC_TOLERANCE)
+    }
+
+    /// Removes redundant segments in all paths, including the `defs` ones.
+    ///
+    /// Paths without segments are removed.
+    ///
+    /// See [`path_utils::normalize`] for details.
+    ///
+    /// [`path_utils::normalize`]: ../path_utils/fn.normalize.html
+    pub fn normalize_paths(&mut self) {
+        self.invalidate_reference_index();
+        path_ops::normalize_paths(self)
+    }
+
+    /// Simplifies all renderable paths.
+    ///
+    /// `tolerance` is the maximum distance between the original and the resulting path
+    /// in the canvas coordinates and must be positive.
+    ///
+    /// Paths inside `clipPath`, `mask` and `pattern` are simplified using the canvas
+    /// transforms of the elements that reference them, including the `objectBoundingBox`
+    /// units. Paths of unreferenced elements are left untouched.
+    ///
+    /// See [`path_utils::simplify`] for details.
+    ///
+    /// [`path_utils::simplify`]: ../path_utils/fn.simplify.html
+    pub fn simplify_paths(&mut self, tolerance: f64) {
+        self.invalidate_reference_index();
+        path_ops::simplify_paths(self, tolerance)
+    }
+
+    /// Converts strokes into filled paths in all paths, including the `defs` ones.
+    ///
+    /// Stroked shapes are converted into paths first, using `PathProfile::Default`
+    /// and the default `Options::arc_tolerance`.
+    ///
+    /// A path with both fill and stroke will be split into two paths. The stroke one
+    /// will be inserted right after the original one and will have an empty ID.
+    ///
+    /// Strokes of the `ClipPath` children are ignored, since they are not rendered.
+    ///
+    /// See [`path_utils::stroke_to_path`] for details.
+    ///
+    /// [`path_utils::stroke_to_path`]: ../path_utils/fn.stroke_to_path.html
+    pub fn outline_strokes(&mut self, tolerance: f64) {
+        self.invalidate_reference_index();
+        path_ops::outline_strokes(self, tolerance, PathProfile::Default, DEFAULT_ARC_TOLERANCE)
+    }
+
+    /// Applies dash patterns to the geometry of all dashed paths, including the `defs` ones.
+    ///
+    /// Each dash becomes a separate subpath and `dasharray` is removed from the stroke.
+    /// Dashed shapes are converted into paths first, like in `outline_strokes`.
+    ///
+    /// A path with both fill and a dashed stroke will be split into two paths,
+    /// like in `outline_strokes`.
+    ///
+    /// See [`path_utils::apply_dashes`] for details.
+    ///
+    /// [`path_utils::apply_dashes`]: ../path_utils/fn.apply_dashes.html
+    pub fn expand_dashes(&mut self) {
+        self.invalidate_reference_index();
+        path_ops::expand_dashes(self, PathProfile::Default, DEFAULT_ARC_TOLERANCE)
+    }
+
+    /// Replaces clip paths with the actual clipping of the paths geometry.
+    ///
+    /// A group clip path is applied only when it contains only paths and the group
+    /// contains only paths and groups without clip paths and masks. Also, the group
+    /// content must not use gradients and patterns with `objectBoundingBox` units.
+    /// Other clip paths are left untouched. Shapes inside the clip path and the group
+    /// are converted into paths first, like in `outline_strokes`.
+    ///
+    /// Fills are intersected with the clip path and strokes are converted into fills first,
+    /// like in `outline_strokes`. Paths that are completely clipped are removed.
+    ///
+    /// `tolerance` is the maximum distance between a curve and its polyline
+    /// in the group coordinates and must be positive.
+    ///
+    /// See [`path_utils::boolean`] for details.
+    ///
+    /// [`path_utils::boolean`]: ../path_utils/fn.boolean.html
+    pub fn apply_clip_paths(&mut self, tolerance: f64) {
+        self.invalidate_reference_index();
+        path_ops::apply_clip_paths(self, tolerance, PathProfile::Default, DEFAULT_ARC_TOLERANCE)
+    }
+
+    /// Returns renderable nodes under the specified canvas point.
+    ///
+    /// Nodes are ordered from the topmost to the bottommost one.
+    /// Only `Path`, `Shape` and `Image` nodes are returned. Their parent groups can be
+    /// accessed via `ancestors()`.
+    ///
+    /// Paths are checked using the fill rule and the stroke outline,
+    /// like the one produced by `outline_strokes`. So stroke joins, caps,
+    /// the miter limit and dashes are taken into account.
+    /// Group clip paths are taken into account, but masks and opacity are not.
+    /// `Text` nodes are ignored, since they are not laid out.
+    pub fn nodes_at_point(&self, x: f64, y: f64) -> Vec<Node> {
+        hit_test::nodes_at_point(self, x, y)
+    }
+
+    /// Extracts a renderable node with all its children as a standalone `Tree`.
+    ///
+    /// All the `defs` elements referenced by the node and its children will be copied too.
+    ///
+    /// The new `Tree` size and `viewBox` are set from the node's bounding box,
+    /// including the stroke. Transforms of all the parent nodes are preserved,
+    /// but their opacity, clip paths and masks are not.
+    ///
+    /// Returns `None` when the node is not found or doesn't have a valid bounding box.
+    pub fn extract(&self, id: &str) -> Option<Tree> {
+        subtree::extract(self, id)
+    }
+
+    /// Imports the `other` tree as a new `Group` appended to the `parent` node.
+    ///
+    /// The `parent` node must be a part of the current tree.
+    ///
+    /// The group transform is a `transform` combined with the `other` tree's `viewBox` transform.
+    /// So the imported content will have the `other` tree size in the `parent` coordinates.
+    ///
+    /// All the `defs` elements are merged. Colliding IDs will be renamed to `id-1`, `id-2`
+    /// and so on, using the first unused name. All the links to them will be updated.
+    ///
+    /// Returns the new group node.
+    pub fn import(&mut self, other: &Tree, parent: &mut Node, transform: Transform) -> Node {
+        self.invalidate_reference_index();
+        subtree::import(self, other, parent, transform)
+    }
+
     /// Converts the document to `svgdom::Document`.
     ///
     /// Used to save document to file for debug purposes.
     pub fn to_svgdom(&self) -> svgdom::Document {
         convert::conv_doc(self)
     }
+
+    /// Writes the document to an SVG string.
+    ///
+    /// Unlike `to_svgdom`, doesn't create an intermediate `svgdom::Document`.
+    pub fn to_string(&self, opt: &WriteOptions) -> String {
+        writer::write_tree(self, opt)
+    }
 }
 
 /// Additional `Node` methods.
@@ -182,6 +453,51 @@ pub trait NodeExt {
 
     /// Returns a node's tree.
     fn tree(&self) -> Tree;
+
+    /// Calculates node's bounding box in the parent coordinate system.
+    ///
+    /// See [`utils::calculate_bbox`] for details.
+    ///
+    /// [`utils::calculate_bbox`]: ../utils/fn.calculate_bbox.html
+    fn calculate_bbox(&self, include_stroke: bool) -> Option<Rect>;
+
+    /// Returns node's absolute transform.
+    ///
+    /// Contains transforms of all the parent nodes and of the node itself,
+    /// but not the root `viewBox` transform.
+    fn abs_transform(&self) -> Transform;
+
+    /// Returns node's canvas transform.
+    ///
+    /// Same as `abs_transform`, but also includes the root `viewBox` transform.
+    /// So it maps node's coordinates to the canvas coordinates.
+    fn canvas_transform(&self) -> Transform;
+
+    /// Maps a point from the node coordinate system to the canvas one.
+    fn map_point_to_canvas(&self, p: Point) -> Point;
+
+    /// Maps a point from the canvas coordinate system to the node one.
+    ///
+    /// Returns `None` when the canvas transform is not invertible.
+    fn map_point_from_canvas(&self, p: Point) -> Option<Point>;
+
+    /// Maps a rect from the node coordinate system to the canvas one.
+    ///
+    /// Since a rect can be rotated or skewed, the bounds of the mapped rect will be returned.
+    fn map_rect_to_canvas(&self, r: Rect) -> Rect;
+
+    /// Maps a rect from the canvas coordinate system to the node one.
+    ///
+    /// Since a rect can be rotated or skewed, the bounds of the mapped rect will be returned.
+    ///
+    /// Returns `None` when the canvas transform is not invertible.
+    fn map_rect_from_canvas(&self, r: Rect) -> Option<Rect>;
+
+    /// Calculates node's bounding box in the canvas coordinate system.
+    ///
+    /// Unlike `calculate_bbox`, transforms of all the parent nodes
+    /// and the root `viewBox` are applied.
+    fn calculate_canvas_bbox(&self, include_stroke: bool) -> Option<Rect>;
 }
 
 impl NodeExt for Node {
@@ -200,6 +516,71 @@ impl NodeExt for Node {
     }
 
     fn tree(&self) -> Tree {
-        Tree { root: self.root() }
+        Tree {
+            root: self.root(),
+            // The original profile is unknown, so all segments are allowed.
+            path_profile: PathProfile::Extended,
+            refs: RefCell::new(None),
+        }
+    }
+
+    fn calculate_bbox(&self, include_stroke: bool) -> Option<Rect> {
+        utils::calculate_bbox(self, include_stroke)
+    }
+
+    fn abs_transform(&self) -> Transform {
+        let mut ts = Transform::default();
+        let mut nodes: Vec<Node> = self.ancestors().collect();
+        nodes.reverse();
+        for n in nodes {
+            ts.append(&n.transform());
+        }
+
+        ts
+    }
+
+    fn canvas_transform(&self) -> Transform {
+        let mut ts = view_box_transform(&self.root());
+        ts.append(&self.abs_transform());
+        ts
+    }
+
+    fn map_point_to_canvas(&self, p: Point) -> Point {
+        self.canvas_transform().map_point(p)
+    }
+
+    fn map_point_from_canvas(&self, p: Point) -> Option<Point> {
+        let ts = self.canvas_transform().inverse()?;
+        Some(ts.map_point(p))
+    }
+
+    fn map_rect_to_canvas(&self, r: Rect) -> Rect {
+        r.transform(self.canvas_transform())
+    }
+
+    fn map_rect_from_canvas(&self, r: Rect) -> Option<Rect> {
+        let ts = self.canvas_transform().inverse()?;
+        Some(r.transform(ts))
+    }
+
+    fn calculate_canvas_bbox(&self, include_stroke: bool) -> Option<Rect> {
+        let ts = match self.parent() {
+            Some(parent) => parent.canvas_transform(),
+            None => view_box_transform(self),
+        };
+
+        utils::calc_node_bbox(self, ts, include_stroke)
+    }
+}
+
+/// Returns the root `viewBox` transform.
+///
+/// Returns a default transform if the `root` is not an `Svg` node.
+fn view_box_transform(root: &Node) -> Transform {
+    match *root.borrow() {
+        NodeKind::Svg(ref svg) => {
+            utils::view_box_to_transform(svg.view_box.rect, svg.view_box.aspect, svg.size)
+        }
+        _ => Transform::default(),
     }
 }
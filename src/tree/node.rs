@@ -5,13 +5,20 @@
 use std::path::PathBuf;
 use std::ops::Deref;
 
+// external
+use unicode_segmentation::UnicodeSegmentation;
+
 // self
 use geom::*;
+use path_utils;
+use PathProfile;
 use super::attribute::*;
 
 
 /// Node's kind.
+#[derive(Clone)]
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NodeKind {
     Svg(Svg),
     Defs,
@@ -21,6 +28,7 @@ pub enum NodeKind {
     Mask(Mask),
     Pattern(Pattern),
     Path(Path),
+    Shape(Box<Shape>),
     Text(Text),
     Image(Image),
     Group(Group),
@@ -41,6 +49,7 @@ impl NodeKind {
             NodeKind::Mask(ref e) => e.id.as_str(),
             NodeKind::Pattern(ref e) => e.id.as_str(),
             NodeKind::Path(ref e) => e.id.as_str(),
+            NodeKind::Shape(ref e) => e.id.as_str(),
             NodeKind::Text(ref e) => e.id.as_str(),
             NodeKind::Image(ref e) => e.id.as_str(),
             NodeKind::Group(ref e) => e.id.as_str(),
@@ -61,16 +70,152 @@ impl NodeKind {
             NodeKind::Mask(_) => Transform::default(),
             NodeKind::Pattern(ref e) => e.transform,
             NodeKind::Path(ref e) => e.transform,
+            NodeKind::Shape(ref e) => e.transform,
             NodeKind::Text(ref e) => e.transform,
             NodeKind::Image(ref e) => e.transform,
             NodeKind::Group(ref e) => e.transform,
         }
     }
+
+    /// Returns IDs of the `defs` elements referenced by the node.
+    ///
+    /// Children are not checked.
+    pub(crate) fn links(&self) -> Vec<&str> {
+        let mut links = Vec::new();
+
+        match *self {
+            NodeKind::Path(ref e) => {
+                push_paint_links(&e.fill, &e.stroke, &mut links);
+            }
+            NodeKind::Shape(ref e) => {
+                push_paint_links(&e.fill, &e.stroke, &mut links);
+            }
+            NodeKind::Text(ref e) => {
+                for chunk in &e.chunks {
+                    for span in &chunk.spans {
+                        push_paint_links(&span.fill, &span.stroke, &mut links);
+
+                        let d = &span.decoration;
+                        push_decoration_links(&d.underline, &mut links);
+                        push_decoration_links(&d.overline, &mut links);
+                        push_decoration_links(&d.line_through, &mut links);
+                    }
+                }
+            }
+            NodeKind::Group(ref e) => {
+                if let Some(ref id) = e.clip_path {
+                    links.push(id.as_str());
+                }
+
+                if let Some(ref id) = e.mask {
+                    links.push(id.as_str());
+                }
+            }
+            _ => {}
+        }
+
+        links
+    }
+
+    /// Returns mutable references to IDs of the `defs` elements referenced by the node.
+    ///
+    /// Children are not checked.
+    pub(crate) fn links_mut(&mut self) -> Vec<&mut String> {
+        let mut links = Vec::new();
+
+        match *self {
+            NodeKind::Path(ref mut e) => {
+                push_paint_links_mut(&mut e.fill, &mut e.stroke, &mut links);
+            }
+            NodeKind::Shape(ref mut e) => {
+                push_paint_links_mut(&mut e.fill, &mut e.stroke, &mut links);
+            }
+            NodeKind::Text(ref mut e) => {
+                for chunk in &mut e.chunks {
+                    for span in &mut chunk.spans {
+                        push_paint_links_mut(&mut span.fill, &mut span.stroke, &mut links);
+
+                        let d = &mut span.decoration;
+                        push_decoration_links_mut(&mut d.underline, &mut links);
+                        push_decoration_links_mut(&mut d.overline, &mut links);
+                        push_decoration_links_mut(&mut d.line_through, &mut links);
+                    }
+                }
+            }
+            NodeKind::Group(ref mut e) => {
+                if let Some(ref mut id) = e.clip_path {
+                    links.push(id);
+                }
+
+                if let Some(ref mut id) = e.mask {
+                    links.push(id);
+                }
+            }
+            _ => {}
+        }
+
+        links
+    }
+}
+
+fn push_paint_links<'a>(
+    fill: &'a Option<Fill>,
+    stroke: &'a Option<Stroke>,
+    links: &mut Vec<&'a str>,
+) {
+    if let Some(ref fill) = *fill {
+        if let Paint::Link(ref id) = fill.paint {
+            links.push(id.as_str());
+        }
+    }
+
+    if let Some(ref stroke) = *stroke {
+        if let Paint::Link(ref id) = stroke.paint {
+            links.push(id.as_str());
+        }
+    }
+}
+
+fn push_decoration_links<'a>(
+    style: &'a Option<TextDecorationStyle>,
+    links: &mut Vec<&'a str>,
+) {
+    if let Some(ref style) = *style {
+        push_paint_links(&style.fill, &style.stroke, links);
+    }
+}
+
+fn push_paint_links_mut<'a>(
+    fill: &'a mut Option<Fill>,
+    stroke: &'a mut Option<Stroke>,
+    links: &mut Vec<&'a mut String>,
+) {
+    if let Some(ref mut fill) = *fill {
+        if let Paint::Link(ref mut id) = fill.paint {
+            links.push(id);
+        }
+    }
+
+    if let Some(ref mut stroke) = *stroke {
+        if let Paint::Link(ref mut id) = stroke.paint {
+            links.push(id);
+        }
+    }
+}
+
+fn push_decoration_links_mut<'a>(
+    style: &'a mut Option<TextDecorationStyle>,
+    links: &mut Vec<&'a mut String>,
+) {
+    if let Some(ref mut style) = *style {
+        push_paint_links_mut(&mut style.fill, &mut style.stroke, links);
+    }
 }
 
 
 /// An SVG root element.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Svg {
     /// Image size.
     ///
@@ -89,6 +234,7 @@ pub struct Svg {
 
 /// A path element.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Path {
     /// Element's ID.
     ///
@@ -97,6 +243,7 @@ pub struct Path {
     /// Can be empty.
     pub id: String,
     /// Element transform.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// Fill style.
     pub fill: Option<Fill>,
@@ -105,13 +252,205 @@ pub struct Path {
     /// Segments list.
     ///
     /// All segments are in absolute coordinates.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "::tree::serialization::segments"))]
     pub segments: Vec<PathSegment>,
 }
 
 
+/// A basic shape element.
+///
+/// `rect`, `circle`, `ellipse`, `line`, `polyline` and `polygon` elements in the SVG.
+///
+/// Created only when `Options::keep_shapes` is set. Otherwise, shapes are converted into paths.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Shape {
+    /// Element's ID.
+    ///
+    /// Taken from the SVG itself.
+    /// Isn't automatically generated.
+    /// Can be empty.
+    pub id: String,
+    /// Element transform.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
+    pub transform: Transform,
+    /// Fill style.
+    pub fill: Option<Fill>,
+    /// Stroke style.
+    pub stroke: Option<Stroke>,
+    /// Shape geometry.
+    pub kind: ShapeKind,
+}
+
+impl Shape {
+    /// Converts the shape into a path with the same ID, transform, fill and stroke.
+    ///
+    /// See `ShapeKind::to_path` for details.
+    pub fn to_path(&self, profile: PathProfile, arc_tolerance: f64) -> Path {
+        Path {
+            id: self.id.clone(),
+            transform: self.transform,
+            fill: self.fill.clone(),
+            stroke: self.stroke.clone(),
+            segments: self.kind.to_path(profile, arc_tolerance),
+        }
+    }
+}
+
+
+/// A basic shape geometry.
+///
+/// All values are resolved and in absolute coordinates.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ShapeKind {
+    /// A rectangle.
+    ///
+    /// `width` and `height` are always positive. `rx` and `ry` are already resolved
+    /// and clamped to the half of the `width` and `height`. A zero value of any of them
+    /// means that there are no rounded corners.
+    #[allow(missing_docs)]
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        rx: f64,
+        ry: f64,
+    },
+    /// A circle with a positive radius.
+    #[allow(missing_docs)]
+    Circle {
+        cx: f64,
+        cy: f64,
+        r: f64,
+    },
+    /// An ellipse with positive radii.
+    #[allow(missing_docs)]
+    Ellipse {
+        cx: f64,
+        cy: f64,
+        rx: f64,
+        ry: f64,
+    },
+    /// A line.
+    #[allow(missing_docs)]
+    Line {
+        x1: f64,
+        y1: f64,
+        x2: f64,
+        y2: f64,
+    },
+    /// A polyline with at least two points.
+    ///
+    /// `closed` is set for the `polygon` element.
+    #[allow(missing_docs)]
+    Polyline {
+        points: Vec<Point>,
+        closed: bool,
+    },
+}
+
+impl ShapeKind {
+    /// Converts the shape into path segments.
+    ///
+    /// Produces the same segments as the parser does for shapes when `Options::keep_shapes`
+    /// is not set. Elliptical arcs are preserved with `PathProfile::Extended`
+    /// and are approximated by curves using `arc_tolerance` otherwise.
+    /// See `Options::arc_tolerance` for details.
+    pub fn to_path(&self, profile: PathProfile, arc_tolerance: f64) -> Vec<PathSegment> {
+        let segments = self.to_segments();
+        match profile {
+            PathProfile::Default => {
+                path_utils::arcs_to_curves(&segments, arc_tolerance)
+            }
+            PathProfile::LinesOnly { tolerance } => {
+                let segments = path_utils::arcs_to_curves(&segments, arc_tolerance);
+                path_utils::flatten(&segments, tolerance)
+            }
+            PathProfile::Extended => segments,
+        }
+    }
+
+    /// Converts the shape into path segments with elliptical arcs.
+    ///
+    /// Conversion according to https://www.w3.org/TR/SVG2/shapes.html
+    fn to_segments(&self) -> Vec<PathSegment> {
+        fn arc_to(rx: f64, ry: f64, x: f64, y: f64) -> PathSegment {
+            PathSegment::ArcTo { rx, ry, x_axis_rotation: 0.0, large_arc: false, sweep: true, x, y }
+        }
+
+        match *self {
+            ShapeKind::Rect { x, y, width, height, rx, ry } => {
+                let (r, b) = (x + width, y + height);
+                if rx.is_fuzzy_zero() || ry.is_fuzzy_zero() {
+                    return vec![
+                        PathSegment::MoveTo { x, y },
+                        PathSegment::LineTo { x: r, y },
+                        PathSegment::LineTo { x: r, y: b },
+                        PathSegment::LineTo { x, y: b },
+                        PathSegment::ClosePath,
+                    ];
+                }
+
+                // e-rect-004.svg
+                vec![
+                    PathSegment::MoveTo { x: x + rx, y },
+                    PathSegment::LineTo { x: r - rx, y },
+                    arc_to(rx, ry, r, y + ry),
+                    PathSegment::LineTo { x: r, y: b - ry },
+                    arc_to(rx, ry, r - rx, b),
+                    PathSegment::LineTo { x: x + rx, y: b },
+                    arc_to(rx, ry, x, b - ry),
+                    PathSegment::LineTo { x, y: y + ry },
+                    arc_to(rx, ry, x + rx, y),
+                ]
+            }
+            ShapeKind::Circle { cx, cy, r } => {
+                ShapeKind::Ellipse { cx, cy, rx: r, ry: r }.to_segments()
+            }
+            ShapeKind::Ellipse { cx, cy, rx, ry } => {
+                vec![
+                    PathSegment::MoveTo { x: cx + rx, y: cy },
+                    arc_to(rx, ry, cx, cy + ry),
+                    arc_to(rx, ry, cx - rx, cy),
+                    arc_to(rx, ry, cx, cy - ry),
+                    arc_to(rx, ry, cx + rx, cy),
+                    PathSegment::ClosePath,
+                ]
+            }
+            ShapeKind::Line { x1, y1, x2, y2 } => {
+                vec![
+                    PathSegment::MoveTo { x: x1, y: y1 },
+                    PathSegment::LineTo { x: x2, y: y2 },
+                ]
+            }
+            ShapeKind::Polyline { ref points, closed } => {
+                let mut segments = Vec::with_capacity(points.len() + 1);
+                for (i, p) in points.iter().enumerate() {
+                    if i == 0 {
+                        segments.push(PathSegment::MoveTo { x: p.x, y: p.y });
+                    } else {
+                        segments.push(PathSegment::LineTo { x: p.x, y: p.y });
+                    }
+                }
+
+                if closed {
+                    segments.push(PathSegment::ClosePath);
+                }
+
+                segments
+            }
+        }
+    }
+}
+
+
 /// A text element.
 ///
 /// `text` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Text {
     /// Element's ID.
     ///
@@ -120,13 +459,117 @@ pub struct Text {
     /// Can be empty.
     pub id: String,
     /// Element transform.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// Rotate
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub rotate: Option<NumberList>,
     /// A list of text chunks.
     pub chunks: Vec<TextChunk>,
 }
 
+impl Text {
+    /// Splits the text into grapheme clusters with resolved positions.
+    ///
+    /// The `x`, `y`, `dx` and `dy` lists of each chunk are applied to the chunk clusters
+    /// and the `rotate` list is applied to all clusters of the text.
+    /// Clusters are split using the same rules as the `text` element preprocessing,
+    /// so a base character with combining marks takes a single position.
+    pub fn clusters(&self) -> Vec<TextCluster> {
+        let mut clusters = Vec::new();
+        let mut span_index = 0;
+        let mut text_index = 0;
+        let mut dx = 0.0;
+        let mut dy = 0.0;
+        for chunk in &self.chunks {
+            let mut chunk_index = 0;
+            for span in &chunk.spans {
+                for grapheme in UnicodeSegmentation::graphemes(span.text.as_str(), true) {
+                    let x = list_item(&chunk.x, chunk_index);
+                    let y = list_item(&chunk.y, chunk_index);
+
+                    // Relative shifts are accumulated until the next absolute position.
+                    if x.is_some() {
+                        dx = 0.0;
+                    }
+
+                    if y.is_some() {
+                        dy = 0.0;
+                    }
+
+                    dx += list_item(&chunk.dx, chunk_index).unwrap_or(0.0);
+                    dy += list_item(&chunk.dy, chunk_index).unwrap_or(0.0);
+
+                    // The last angle is used for all the remaining clusters.
+                    let rotate = self.rotate.as_ref()
+                        .and_then(|list| list.get(text_index).or_else(|| list.last()).cloned());
+
+                    clusters.push(TextCluster {
+                        text: grapheme,
+                        x,
+                        y,
+                        dx,
+                        dy,
+                        rotate: rotate.unwrap_or(0.0),
+                        anchor: chunk.anchor,
+                        span,
+                        span_index,
+                    });
+
+                    chunk_index += 1;
+                    text_index += 1;
+                }
+
+                span_index += 1;
+            }
+        }
+
+        clusters
+    }
+}
+
+fn list_item(list: &Option<NumberList>, index: usize) -> Option<f64> {
+    list.as_ref().and_then(|list| list.get(index).cloned())
+}
+
+
+/// A grapheme cluster with a resolved position.
+///
+/// Created by `Text::clusters`.
+#[derive(Clone, Copy)]
+pub struct TextCluster<'a> {
+    /// Cluster text.
+    ///
+    /// Can contain multiple characters, like a base character with combining marks.
+    pub text: &'a str,
+    /// An absolute position along the X-axis.
+    ///
+    /// Set only when the cluster starts a new position.
+    pub x: Option<f64>,
+    /// An absolute position along the Y-axis.
+    ///
+    /// Set only when the cluster starts a new position.
+    pub y: Option<f64>,
+    /// A relative shift along the X-axis.
+    ///
+    /// Accumulated since the last absolute `x` position.
+    /// So the cluster is placed at the last absolute position plus the advance
+    /// of all the previous clusters plus this shift.
+    pub dx: f64,
+    /// A relative shift along the Y-axis.
+    ///
+    /// Accumulated since the last absolute `y` position.
+    pub dy: f64,
+    /// A rotation angle in degrees.
+    pub rotate: f64,
+    /// The anchor of the owning text chunk.
+    pub anchor: TextAnchor,
+    /// The owning text span.
+    pub span: &'a TextSpan,
+    /// The index of the owning text span in the whole text.
+    pub span_index: usize,
+}
+
 
 /// A text chunk.
 ///
@@ -136,14 +579,19 @@ pub struct Text {
 /// Doesn't represented in the SVG directly. Usually, it's a first `tspan` or text node
 /// and any `tspan` that defines either `x` or `y` coordinates.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextChunk {
     /// A list of absolute positions along the X-axis.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub x: Option<NumberList>,
     /// A list of absolute positions along the Y-axis.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub y: Option<NumberList>,
     /// A list of relative positions along the X-axis.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub dx: Option<NumberList>,
     /// A list of relative positions along the Y-axis.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::number_list"))]
     pub dy: Option<NumberList>,
     /// A text anchor/align.
     pub anchor: TextAnchor,
@@ -156,6 +604,7 @@ pub struct TextChunk {
 ///
 /// `tspan` element in the SVG.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextSpan {
     /// Fill style.
     pub fill: Option<Fill>,
@@ -187,6 +636,8 @@ pub struct TextSpan {
 /// A raster image element.
 ///
 /// `image` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Image {
     /// Element's ID.
     ///
@@ -195,6 +646,7 @@ pub struct Image {
     /// Can be empty.
     pub id: String,
     /// Element transform.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// An image rectangle in which it should be fit.
     ///
@@ -209,6 +661,8 @@ pub struct Image {
 
 
 /// A raster image container.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageData {
     /// Path to a PNG, JPEG or SVG(Z) image.
     ///
@@ -228,6 +682,7 @@ pub enum ImageData {
 /// An image codec.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum ImageFormat {
     PNG,
     JPEG,
@@ -241,6 +696,8 @@ pub enum ImageFormat {
 /// Those that left is just an indicator that a new canvas should be created.
 ///
 /// `g` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Group {
     /// Element's ID.
     ///
@@ -249,6 +706,7 @@ pub struct Group {
     /// Can be empty.
     pub id: String,
     /// Element transform.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// Group opacity.
     ///
@@ -264,6 +722,7 @@ pub struct Group {
 
 /// A generic gradient.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BaseGradient {
     /// Coordinate system units.
     ///
@@ -272,6 +731,7 @@ pub struct BaseGradient {
     /// Gradient transform.
     ///
     /// `gradientTransform` in the SVG.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// Gradient spreading method.
     ///
@@ -285,7 +745,9 @@ pub struct BaseGradient {
 /// A linear gradient.
 ///
 /// `linearGradient` element in the SVG.
+#[derive(Clone)]
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct LinearGradient {
     /// Element's ID.
     ///
@@ -312,7 +774,9 @@ impl Deref for LinearGradient {
 /// A radial gradient.
 ///
 /// `radialGradient` element in the SVG.
+#[derive(Clone)]
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RadialGradient {
     /// Element's ID.
     ///
@@ -342,8 +806,10 @@ impl Deref for RadialGradient {
 /// `stop` element in the SVG.
 #[derive(Clone, Copy)]
 #[allow(missing_docs)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Stop {
     pub offset: StopOffset,
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::color"))]
     pub color: Color,
     pub opacity: Opacity,
 }
@@ -352,6 +818,8 @@ pub struct Stop {
 /// A clip-path element.
 ///
 /// `clipPath` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ClipPath {
     /// Element's ID.
     ///
@@ -365,6 +833,7 @@ pub struct ClipPath {
     /// Clip path transform.
     ///
     /// `transform` in the SVG.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
 }
 
@@ -372,6 +841,8 @@ pub struct ClipPath {
 /// A mask element.
 ///
 /// `mask` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Mask {
     /// Element's ID.
     ///
@@ -396,6 +867,8 @@ pub struct Mask {
 /// A pattern element.
 ///
 /// `pattern` element in the SVG.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Pattern {
     /// Element's ID.
     ///
@@ -414,6 +887,7 @@ pub struct Pattern {
     /// Pattern transform.
     ///
     /// `patternTransform` in the SVG.
+    #[cfg_attr(feature = "serde", serde(with = "::tree::serialization::transform"))]
     pub transform: Transform,
     /// Pattern rectangle.
     ///
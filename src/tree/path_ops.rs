@@ -0,0 +1,542 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use path_utils;
+use utils;
+use super::*;
+
+
+pub fn shapes_to_paths(tree: &mut Tree, profile: PathProfile, arc_tolerance: f64) {
+    convert_shapes(&tree.root(), profile, arc_tolerance, |_| true);
+}
+
+pub fn flatten_paths(tree: &mut Tree, tolerance: f64, arc_tolerance: f64) {
+    convert_shapes(&tree.root(), PathProfile::LinesOnly { tolerance }, arc_tolerance, |_| true);
+
+    for mut node in tree.root().descendants() {
+        if let NodeKind::Path(ref mut path) = *node.borrow_mut() {
+            path.segments = path_utils::flatten(&path.segments, tolerance);
+        }
+    }
+}
+
+pub fn normalize_paths(tree: &mut Tree) {
+    let nodes: Vec<Node> = tree.root().descendants().collect();
+    for mut node in nodes {
+        let is_empty = {
+            let mut kind = node.borrow_mut();
+            if let NodeKind::Path(ref mut path) = *kind {
+                path.segments = path_utils::normalize(&path.segments);
+                path.segments.is_empty()
+            } else {
+                false
+            }
+        };
+
+        // A path without segments is not rendered.
+        if is_empty {
+            remove_node(node);
+        }
+    }
+}
+
+pub fn simplify_paths(tree: &mut Tree, tolerance: f64) {
+    let index = ReferenceIndex::new(tree);
+    for mut node in tree.root().descendants() {
+        let is_path = match *node.borrow() {
+            NodeKind::Path(_) => true,
+            _ => false,
+        };
+
+        if !is_path {
+            continue;
+        }
+
+        // A path inside `defs` is rendered once per element that uses it,
+        // so the smallest tolerance is used.
+        let scale = canvas_transforms(&index, &node, &[]).iter()
+            .fold(0.0, |scale: f64, ts| scale.max(utils::max_scale(ts)));
+
+        if let NodeKind::Path(ref mut path) = *node.borrow_mut() {
+            // Paths of unused `defs` elements have no canvas transform.
+            if scale > 0.0 && scale.is_finite() {
+                path.segments = path_utils::simplify(&path.segments, tolerance / scale);
+            }
+        }
+    }
+}
+
+/// Returns the node canvas transforms.
+///
+/// A node inside `defs` has a canvas transform for each element that uses
+/// its `defs` element. `visited` contains `defs` elements that are already
+/// being resolved, to prevent an infinite recursion on cyclic references.
+fn canvas_transforms(index: &ReferenceIndex, node: &Node, visited: &[Node]) -> Vec<Transform> {
+    let element = node.ancestors().find(|n| {
+        n.parent().map(|p| if let NodeKind::Defs = *p.borrow() { true } else { false })
+            .unwrap_or(false)
+    });
+
+    let element = match element {
+        Some(element) => element,
+        None => return vec![node.canvas_transform()],
+    };
+
+    if visited.contains(&element) {
+        return Vec::new();
+    }
+
+    let mut visited = visited.to_vec();
+    visited.push(element.clone());
+
+    let rel_ts = relative_transform(&element, node);
+
+    let mut list = Vec::new();
+    for user in index.users_of(&element.id()) {
+        let content_ts = match content_transform(&element, user) {
+            Some(ts) => ts,
+            None => continue,
+        };
+
+        for mut ts in canvas_transforms(index, user, &visited) {
+            ts.append(&content_ts);
+            ts.append(&rel_ts);
+            list.push(ts);
+        }
+    }
+
+    list
+}
+
+/// Returns the transform from the `defs` element content coordinates
+/// to the `user` ones.
+fn content_transform(element: &Node, user: &Node) -> Option<Transform> {
+    let bbox_ts = || {
+        let bbox = match *user.borrow() {
+            NodeKind::Path(ref path) => {
+                utils::path_bbox(&path.segments, None, &Transform::default())
+            }
+            NodeKind::Shape(ref shape) => {
+                let segments = shape.kind.to_path(PathProfile::Extended, 0.0);
+                utils::path_bbox(&segments, None, &Transform::default())
+            }
+            _ => utils::calc_children_bbox(user),
+        };
+
+        bbox.map(|r| Transform::new(r.width, 0.0, 0.0, r.height, r.x, r.y))
+    };
+
+    let kind = element.borrow();
+    match *kind {
+        NodeKind::ClipPath(ref cp) => {
+            let mut ts = cp.transform;
+            if cp.units == Units::ObjectBoundingBox {
+                ts.append(&bbox_ts()?);
+            }
+
+            Some(ts)
+        }
+        NodeKind::Mask(ref mask) => {
+            if mask.content_units == Units::ObjectBoundingBox {
+                bbox_ts()
+            } else {
+                Some(Transform::default())
+            }
+        }
+        NodeKind::Pattern(ref patt) => {
+            let mut ts = patt.transform;
+            let mut rect = patt.rect;
+            if patt.units == Units::ObjectBoundingBox {
+                rect = rect.transform(bbox_ts()?);
+            }
+
+            ts.translate(rect.x, rect.y);
+            if let Some(vbox) = patt.view_box {
+                let size = Size::new(rect.width, rect.height);
+                ts.append(&utils::view_box_to_transform(vbox.rect, vbox.aspect, size));
+            } else if patt.content_units == Units::ObjectBoundingBox {
+                let bbox_ts = bbox_ts()?;
+                ts.scale(bbox_ts.a, bbox_ts.d);
+            }
+
+            Some(ts)
+        }
+        _ => None,
+    }
+}
+
+pub fn outline_strokes(
+    tree: &mut Tree,
+    tolerance: f64,
+    profile: PathProfile,
+    arc_tolerance: f64,
+) {
+    convert_shapes(&tree.root(), profile, arc_tolerance, |shape| shape.stroke.is_some());
+
+    for node in stroked_paths(tree, |_| true) {
+        outline_stroke(split_stroke(node), tolerance);
+    }
+}
+
+pub fn expand_dashes(tree: &mut Tree, profile: PathProfile, arc_tolerance: f64) {
+    convert_shapes(&tree.root(), profile, arc_tolerance, |shape| {
+        shape.stroke.as_ref().map(|s| s.dasharray.is_some()).unwrap_or(false)
+    });
+
+    for node in stroked_paths(tree, |stroke| stroke.dasharray.is_some()) {
+        let mut node = split_stroke(node);
+        let mut kind = node.borrow_mut();
+        if let NodeKind::Path(ref mut path) = *kind {
+            if let Some(ref mut stroke) = path.stroke {
+                if let Some(dasharray) = stroke.dasharray.take() {
+                    path.segments = path_utils::apply_dashes(&path.segments, &dasharray,
+                                                             stroke.dashoffset);
+                    stroke.dashoffset = 0.0;
+                }
+            }
+        }
+    }
+}
+
+pub fn apply_clip_paths(
+    tree: &mut Tree,
+    tolerance: f64,
+    profile: PathProfile,
+    arc_tolerance: f64,
+) {
+    let groups: Vec<Node> = tree.root().descendants().filter(|node| {
+        match *node.borrow() {
+            NodeKind::Group(ref g) => g.clip_path.is_some(),
+            _ => false,
+        }
+    }).collect();
+
+    // Nested groups are processed first, so their parents can be processed too.
+    for node in groups.into_iter().rev() {
+        apply_clip_path(tree, node, tolerance, profile, arc_tolerance);
+    }
+}
+
+fn apply_clip_path(
+    tree: &Tree,
+    mut group: Node,
+    tolerance: f64,
+    profile: PathProfile,
+    arc_tolerance: f64,
+) {
+    // The group was removed, since all its children were clipped out.
+    if group.parent().is_none() {
+        return;
+    }
+
+    let clip = {
+        let id = match *group.borrow() {
+            NodeKind::Group(ref g) => g.clip_path.clone(),
+            _ => None,
+        };
+
+        match id.and_then(|id| tree.defs_by_id(&id)) {
+            Some(clip) => clip,
+            None => return,
+        }
+    };
+
+    // Shapes are clipped as regular paths.
+    convert_shapes(&clip, profile, arc_tolerance, |_| true);
+    convert_shapes(&group, profile, arc_tolerance, |_| true);
+
+    if !is_simple_clip_path(&clip) || !is_simple_group(tree, &group) {
+        return;
+    }
+
+    let region = clip_region(&group, &clip, tolerance);
+
+    let paths: Vec<Node> = group.descendants().filter(|node| {
+        match *node.borrow() {
+            NodeKind::Path(_) => true,
+            _ => false,
+        }
+    }).collect();
+
+    for node in paths {
+        let ts = relative_transform(&group, &node);
+        let region = match ts.inverse() {
+            Some(ts) => path_utils::transform_segments(&region, &ts),
+            None => {
+                remove_node(node);
+                continue;
+            }
+        };
+
+        let has_stroke = match *node.borrow() {
+            NodeKind::Path(ref path) => path.stroke.is_some(),
+            _ => false,
+        };
+
+        let mut nodes = vec![node.clone()];
+        if has_stroke {
+            let stroke_node = split_stroke(node.clone());
+            if stroke_node != node {
+                nodes.push(stroke_node.clone());
+            }
+
+            outline_stroke(stroke_node, tolerance);
+        }
+
+        for node in nodes {
+            clip_path_fill(node, &region, tolerance);
+        }
+    }
+
+    if let NodeKind::Group(ref mut g) = *group.borrow_mut() {
+        g.clip_path = None;
+    }
+}
+
+/// Checks that the clip path contains only paths.
+fn is_simple_clip_path(clip: &Node) -> bool {
+    if let NodeKind::ClipPath(_) = *clip.borrow() {
+        clip.children().all(|child| {
+            match *child.borrow() {
+                NodeKind::Path(_) => true,
+                _ => false,
+            }
+        })
+    } else {
+        false
+    }
+}
+
+/// Checks that the group content doesn't depend on its bounding box
+/// and can be clipped geometrically.
+fn is_simple_group(tree: &Tree, group: &Node) -> bool {
+    if let NodeKind::Group(ref g) = *group.borrow() {
+        if g.mask.is_some() {
+            return false;
+        }
+    }
+
+    group.descendants().skip(1).all(|node| {
+        match *node.borrow() {
+            NodeKind::Group(ref g) => g.clip_path.is_none() && g.mask.is_none(),
+            NodeKind::Path(ref path) => {
+                let fill = path.fill.as_ref().map(|f| &f.paint);
+                let stroke = path.stroke.as_ref().map(|s| &s.paint);
+                !fill.into_iter().chain(stroke).any(|paint| is_bbox_paint(tree, paint))
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Checks that the paint references an element with `objectBoundingBox` units.
+fn is_bbox_paint(tree: &Tree, paint: &Paint) -> bool {
+    let id = match *paint {
+        Paint::Link(ref id) => id,
+        Paint::Color(_) => return false,
+    };
+
+    let node = match tree.defs_by_id(id) {
+        Some(node) => node,
+        None => return false,
+    };
+
+    let kind = node.borrow();
+    match *kind {
+        NodeKind::LinearGradient(ref lg) => lg.units == Units::ObjectBoundingBox,
+        NodeKind::RadialGradient(ref rg) => rg.units == Units::ObjectBoundingBox,
+        NodeKind::Pattern(ref patt) => {
+               patt.units == Units::ObjectBoundingBox
+            || patt.content_units == Units::ObjectBoundingBox
+        }
+        _ => false,
+    }
+}
+
+/// Returns the union of all clip path children in the group coordinates.
+fn clip_region(group: &Node, clip: &Node, tolerance: f64) -> Vec<PathSegment> {
+    let mut ts = clip.transform();
+
+    let is_bbox_units = match *clip.borrow() {
+        NodeKind::ClipPath(ref cp) => cp.units == Units::ObjectBoundingBox,
+        _ => false,
+    };
+
+    if is_bbox_units {
+        let bbox = match utils::calc_children_bbox(group) {
+            Some(bbox) => bbox,
+            // Nothing can be rendered with an empty bounding box.
+            None => return Vec::new(),
+        };
+
+        ts.append(&Transform::new(bbox.width, 0.0, 0.0, bbox.height, bbox.x, bbox.y));
+    }
+
+    let mut region = Vec::new();
+    for child in clip.children() {
+        if let NodeKind::Path(ref path) = *child.borrow() {
+            let mut child_ts = ts;
+            child_ts.append(&path.transform);
+
+            let rule = path.fill.as_ref().map(|f| f.rule).unwrap_or(FillRule::NonZero);
+            let segments = path_utils::transform_segments(&path.segments, &child_ts);
+            region = path_utils::boolean(&region, FillRule::NonZero, &segments, rule,
+                                         path_utils::BooleanOp::Union, tolerance);
+        }
+    }
+
+    region
+}
+
+/// Returns the `node` transform relative to the `parent` one.
+fn relative_transform(parent: &Node, node: &Node) -> Transform {
+    let mut list = Vec::new();
+    for n in node.ancestors() {
+        if n == *parent {
+            break;
+        }
+
+        list.push(n.transform());
+    }
+
+    let mut ts = Transform::default();
+    for t in list.iter().rev() {
+        ts.append(t);
+    }
+
+    ts
+}
+
+/// Intersects the path fill with the region.
+///
+/// Removes the path when nothing is left.
+fn clip_path_fill(mut node: Node, region: &[PathSegment], tolerance: f64) {
+    let is_empty = {
+        let mut kind = node.borrow_mut();
+        match *kind {
+            NodeKind::Path(ref mut path) => {
+                match path.fill {
+                    Some(ref mut fill) => {
+                        path.segments = path_utils::boolean(
+                            &path.segments, fill.rule, region, FillRule::NonZero,
+                            path_utils::BooleanOp::Intersection, tolerance,
+                        );
+                        fill.rule = FillRule::NonZero;
+                        path.segments.is_empty()
+                    }
+                    // A path without fill and stroke is not rendered anyway.
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    };
+
+    if is_empty {
+        remove_node(node);
+    }
+}
+
+/// Removes the node and all the ancestor groups that became empty.
+fn remove_node(mut node: Node) {
+    let mut parent = node.parent();
+    node.detach();
+
+    while let Some(mut p) = parent {
+        let is_empty_group = match *p.borrow() {
+            NodeKind::Group(_) => !p.has_children(),
+            _ => false,
+        };
+
+        if !is_empty_group {
+            break;
+        }
+
+        parent = p.parent();
+        p.detach();
+    }
+}
+
+/// Converts the path stroke into a fill.
+fn outline_stroke(mut node: Node, tolerance: f64) {
+    let mut kind = node.borrow_mut();
+    if let NodeKind::Path(ref mut path) = *kind {
+        if let Some(stroke) = path.stroke.take() {
+            path.segments = path_utils::stroke_to_path(&path.segments, &stroke, tolerance);
+            path.fill = Some(Fill {
+                paint: stroke.paint,
+                opacity: stroke.opacity,
+                rule: FillRule::NonZero,
+            });
+        }
+    }
+}
+
+/// Replaces shapes that match the predicate with paths.
+fn convert_shapes<F>(root: &Node, profile: PathProfile, arc_tolerance: f64, f: F)
+    where F: Fn(&Shape) -> bool
+{
+    for mut node in root.descendants() {
+        let path = match *node.borrow() {
+            NodeKind::Shape(ref shape) if f(shape) => shape.to_path(profile, arc_tolerance),
+            _ => continue,
+        };
+
+        *node.borrow_mut() = NodeKind::Path(path);
+    }
+}
+
+/// Collects stroked paths that match the predicate.
+///
+/// Children of the `ClipPath` are ignored, since their strokes are not rendered.
+fn stroked_paths<F>(tree: &Tree, f: F) -> Vec<Node>
+    where F: Fn(&Stroke) -> bool
+{
+    tree.root().descendants().filter(|node| {
+        if let Some(parent) = node.parent() {
+            if let NodeKind::ClipPath(_) = *parent.borrow() {
+                return false;
+            }
+        }
+
+        match *node.borrow() {
+            NodeKind::Path(ref path) => path.stroke.as_ref().map(|s| f(s)).unwrap_or(false),
+            _ => false,
+        }
+    }).collect()
+}
+
+/// Moves the stroke of a filled path into a separate path.
+///
+/// The new path will be inserted right after the original one and will have an empty ID.
+///
+/// Returns a node with the stroke.
+fn split_stroke(mut node: Node) -> Node {
+    let new_kind = match *node.borrow_mut() {
+        NodeKind::Path(ref mut path) => {
+            if path.fill.is_some() {
+                Some(NodeKind::Path(Path {
+                    id: String::new(),
+                    transform: path.transform,
+                    fill: None,
+                    stroke: path.stroke.take(),
+                    segments: path.segments.clone(),
+                }))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    let new_kind = match new_kind {
+        Some(v) => v,
+        None => return node,
+    };
+
+    let new_node = Node::new(new_kind);
+    node.insert_after(new_node.clone());
+    new_node
+}
@@ -0,0 +1,55 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::collections::HashMap;
+
+// self
+use super::*;
+
+
+/// A reverse references index.
+///
+/// Maps `defs` elements IDs to the nodes that use them.
+///
+/// The index is a snapshot of the tree. It is cached by the `Tree` and invalidated
+/// by the `Tree` methods that modify it. See `Tree::reference_index` for details.
+pub struct ReferenceIndex {
+    users: HashMap<String, Vec<Node>>,
+}
+
+impl ReferenceIndex {
+    pub(crate) fn new(tree: &Tree) -> Self {
+        let mut users: HashMap<String, Vec<Node>> = HashMap::new();
+
+        for node in tree.root().descendants() {
+            for id in node.borrow().links() {
+                let list = users.entry(id.to_string()).or_insert_with(Vec::new);
+
+                // A node can use the same element more than once. Like fill and stroke.
+                if list.last() != Some(&node) {
+                    list.push(node.clone());
+                }
+            }
+        }
+
+        ReferenceIndex {
+            users,
+        }
+    }
+
+    /// Returns all nodes that reference an element with the specified ID.
+    ///
+    /// Nodes are in the tree order.
+    pub fn users_of(&self, id: &str) -> &[Node] {
+        match self.users.get(id) {
+            Some(list) => list,
+            None => &[],
+        }
+    }
+
+    /// Checks that an element with the specified ID is referenced by any node.
+    pub fn is_used(&self, id: &str) -> bool {
+        self.users.contains_key(id)
+    }
+}
@@ -0,0 +1,233 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `serde` support.
+//!
+//! The schema is described in the `docs/serde_schema.adoc`.
+
+// external
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use serde::de::Error as DeError;
+use serde::ser::{
+    SerializeSeq,
+    SerializeStruct,
+};
+
+// self
+use super::*;
+
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Tree", 3)?;
+        state.serialize_field("svg", &*self.svg_node())?;
+        state.serialize_field("defs", &ChildrenRef(self.defs()))?;
+        state.serialize_field("children", &ChildrenRef(self.root()))?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = TreeData::deserialize(deserializer)?;
+
+        let mut tree = Tree::create(data.svg);
+        // Any segments are allowed during deserialization.
+        tree.set_path_profile(PathProfile::Extended);
+        append_nodes::<D::Error>(tree.defs(), data.defs)?;
+        append_nodes::<D::Error>(tree.root(), data.children)?;
+
+        Ok(tree)
+    }
+}
+
+fn append_nodes<E: DeError>(mut parent: Node, nodes: Vec<NodeData>) -> Result<(), E> {
+    for node in nodes {
+        match node.kind {
+            NodeKind::Svg(_) | NodeKind::Defs => {
+                return Err(E::custom("'Svg' and 'Defs' nodes cannot be nested"));
+            }
+            _ => {}
+        }
+
+        let new_node = parent.append_kind(node.kind);
+        append_nodes(new_node, node.children)?;
+    }
+
+    Ok(())
+}
+
+
+/// Serializes node's children. The `Defs` node is skipped.
+struct ChildrenRef(Node);
+
+impl Serialize for ChildrenRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let is_defs = |n: &Node| if let NodeKind::Defs = *n.borrow() { true } else { false };
+
+        let len = self.0.children().filter(|n| !is_defs(n)).count();
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        for child in self.0.children().filter(|n| !is_defs(n)) {
+            seq.serialize_element(&NodeRef(child))?;
+        }
+        seq.end()
+    }
+}
+
+struct NodeRef(Node);
+
+impl Serialize for NodeRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Node", 2)?;
+        state.serialize_field("kind", &*self.0.borrow())?;
+        state.serialize_field("children", &ChildrenRef(self.0.clone()))?;
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct TreeData {
+    svg: Svg,
+    #[serde(default)]
+    defs: Vec<NodeData>,
+    #[serde(default)]
+    children: Vec<NodeData>,
+}
+
+#[derive(Deserialize)]
+struct NodeData {
+    kind: NodeKind,
+    #[serde(default)]
+    children: Vec<NodeData>,
+}
+
+
+impl<'de> Deserialize<'de> for Opacity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let n = f64::deserialize(deserializer)?;
+        if n >= 0.0 && n <= 1.0 {
+            Ok(Opacity::new(n))
+        } else {
+            Err(D::Error::custom(format!("opacity '{}' is out of the 0..1 range", n)))
+        }
+    }
+}
+
+/// Path segments that must start with `MoveTo`.
+pub fn segments<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathSegment>, D::Error> {
+    let segments: Vec<PathSegment> = Deserialize::deserialize(deserializer)?;
+    match segments.first() {
+        Some(&PathSegment::MoveTo { .. }) => Ok(segments),
+        _ => Err(D::Error::custom("path segments must start with 'MoveTo'")),
+    }
+}
+
+
+/// `Transform` as a `[a, b, c, d, e, f]` array.
+pub mod transform {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(ts: &Transform, serializer: S) -> Result<S::Ok, S::Error> {
+        (ts.a, ts.b, ts.c, ts.d, ts.e, ts.f).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Transform, D::Error> {
+        let (a, b, c, d, e, f) = Deserialize::deserialize(deserializer)?;
+        Ok(Transform::new(a, b, c, d, e, f))
+    }
+}
+
+/// `Color` as a `#RRGGBB` string.
+pub mod color {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(c: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        let s = format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue);
+        serializer.serialize_str(&s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+
+        let parse = |s: &str| -> Option<Color> {
+            if s.len() != 7 || !s.starts_with('#') {
+                return None;
+            }
+
+            let r = u8::from_str_radix(s.get(1..3)?, 16).ok()?;
+            let g = u8::from_str_radix(s.get(3..5)?, 16).ok()?;
+            let b = u8::from_str_radix(s.get(5..7)?, 16).ok()?;
+            Some(Color::new(r, g, b))
+        };
+
+        parse(&s).ok_or_else(|| D::Error::custom(format!("invalid color '{}'", s)))
+    }
+}
+
+/// `Option<NumberList>` as an optional array of numbers.
+pub mod number_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        list: &Option<NumberList>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match *list {
+            Some(ref list) => {
+                let list: Vec<f64> = list.iter().cloned().collect();
+                serializer.serialize_some(&list)
+            }
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<NumberList>, D::Error> {
+        let list: Option<Vec<f64>> = Deserialize::deserialize(deserializer)?;
+        Ok(list.map(NumberList::from))
+    }
+}
+
+/// `AspectRatio` as a struct.
+pub mod aspect {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "Align")]
+    enum AlignDef {
+        None,
+        XMinYMin,
+        XMidYMin,
+        XMaxYMin,
+        XMinYMid,
+        XMidYMid,
+        XMaxYMid,
+        XMinYMax,
+        XMidYMax,
+        XMaxYMax,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(remote = "AspectRatio")]
+    struct AspectRatioDef {
+        defer: bool,
+        #[serde(with = "AlignDef")]
+        align: Align,
+        slice: bool,
+    }
+
+    pub fn serialize<S: Serializer>(v: &AspectRatio, serializer: S) -> Result<S::Ok, S::Error> {
+        AspectRatioDef::serialize(v, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AspectRatio, D::Error> {
+        AspectRatioDef::deserialize(deserializer)
+    }
+}
@@ -0,0 +1,197 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// self
+use super::*;
+
+
+pub fn extract(tree: &Tree, id: &str) -> Option<Tree> {
+    let node = tree.node_by_id(id)?;
+
+    let parent_ts = match node.parent() {
+        Some(parent) => parent.abs_transform(),
+        None => Transform::default(),
+    };
+
+    let bbox = utils::calc_node_bbox(&node, parent_ts, true)?;
+    if bbox.width.is_fuzzy_zero() || bbox.height.is_fuzzy_zero() {
+        return None;
+    }
+
+    let mut new_tree = Tree::create(Svg {
+        size: bbox.size(),
+        view_box: ViewBox {
+            rect: bbox,
+            aspect: AspectRatio::default(),
+        },
+    });
+    new_tree.set_path_profile(tree.path_profile());
+
+    let links = collect_links(tree, vec![node.clone()]);
+    for n in tree.defs().children() {
+        if links.iter().any(|id| *id == *n.id()) {
+            copy_node(&n, &mut new_tree.defs());
+        }
+    }
+
+    let mut parent = new_tree.root();
+    if !parent_ts.is_default() {
+        parent = parent.append_kind(NodeKind::Group(Group {
+            id: String::new(),
+            transform: parent_ts,
+            opacity: None,
+            clip_path: None,
+            mask: None,
+        }));
+    }
+
+    copy_node(&node, &mut parent);
+
+    Some(new_tree)
+}
+
+pub fn remove_unused_defs(tree: &mut Tree) {
+    let nodes: Vec<Node> = tree.root().children().filter(|n| {
+        if let NodeKind::Defs = *n.borrow() { false } else { true }
+    }).collect();
+
+    let links = collect_links(tree, nodes);
+
+    let unused: Vec<Node> = tree.defs().children()
+        .filter(|n| !links.iter().any(|id| *id == *n.id()))
+        .collect();
+
+    for mut n in unused {
+        n.detach();
+    }
+}
+
+/// Collects IDs of all the `defs` elements referenced by the `nodes` and their descendants.
+///
+/// Links inside the referenced elements are resolved too.
+fn collect_links(tree: &Tree, nodes: Vec<Node>) -> Vec<String> {
+    let mut links: Vec<String> = Vec::new();
+    let mut queue = nodes;
+
+    while let Some(node) = queue.pop() {
+        for n in node.descendants() {
+            for id in n.borrow().links() {
+                if links.iter().any(|v| v == id) {
+                    continue;
+                }
+
+                links.push(id.to_string());
+
+                if let Some(link) = tree.defs_by_id(id) {
+                    queue.push(link);
+                }
+            }
+        }
+    }
+
+    links
+}
+
+/// Deep copies the `node` and appends it to the `parent`.
+fn copy_node(node: &Node, parent: &mut Node) -> Node {
+    let mut new_node = parent.append_kind(node.borrow().clone());
+    for child in node.children() {
+        copy_node(&child, &mut new_node);
+    }
+
+    new_node
+}
+
+pub fn import(tree: &mut Tree, other: &Tree, parent: &mut Node, transform: Transform) -> Node {
+    debug_assert!(parent.root() == tree.root());
+
+    // Collect nodes first, so importing a tree into itself will not loop forever.
+    let other_defs: Vec<Node> = other.defs().children().collect();
+    let other_children: Vec<Node> = other.root().children().collect();
+
+    let other_ids: Vec<String> = other_defs.iter().map(|n| n.id().to_string()).collect();
+    let mut ids: Vec<String> = tree.defs().children().map(|n| n.id().to_string()).collect();
+
+    // Rename colliding IDs using the first free `id-N` name.
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    for id in &other_ids {
+        if ids.contains(id) {
+            let mut n = 1;
+            let new_id = loop {
+                let new_id = format!("{}-{}", id, n);
+                if !ids.contains(&new_id) && !other_ids.contains(&new_id) {
+                    break new_id;
+                }
+
+                n += 1;
+            };
+
+            ids.push(new_id.clone());
+            renamed.push((id.clone(), new_id));
+        } else {
+            ids.push(id.clone());
+        }
+    }
+
+    for n in other_defs {
+        let mut new_node = copy_node(&n, &mut tree.defs());
+
+        let new_id = renamed.iter().find(|v| v.0 == *n.id()).map(|v| v.1.clone());
+        if let Some(new_id) = new_id {
+            set_defs_id(&mut new_node.borrow_mut(), new_id);
+        }
+
+        rename_links(&new_node, &renamed);
+    }
+
+    let mut ts = transform;
+    ts.append(&view_box_transform(&other.root()));
+
+    let mut g = parent.append_kind(NodeKind::Group(Group {
+        id: String::new(),
+        transform: ts,
+        opacity: None,
+        clip_path: None,
+        mask: None,
+    }));
+
+    for child in other_children {
+        if let NodeKind::Defs = *child.borrow() {
+            continue;
+        }
+
+        copy_node(&child, &mut g);
+    }
+
+    rename_links(&g, &renamed);
+
+    g
+}
+
+fn set_defs_id(kind: &mut NodeKind, id: String) {
+    match *kind {
+        NodeKind::LinearGradient(ref mut e) => e.id = id,
+        NodeKind::RadialGradient(ref mut e) => e.id = id,
+        NodeKind::ClipPath(ref mut e) => e.id = id,
+        NodeKind::Mask(ref mut e) => e.id = id,
+        NodeKind::Pattern(ref mut e) => e.id = id,
+        _ => {}
+    }
+}
+
+/// Rewrites links in the `root` and its descendants using the `renamed` list.
+fn rename_links(root: &Node, renamed: &[(String, String)]) {
+    if renamed.is_empty() {
+        return;
+    }
+
+    for mut n in root.descendants() {
+        let mut kind = n.borrow_mut();
+        for link in kind.links_mut() {
+            if let Some(v) = renamed.iter().find(|v| v.0 == *link) {
+                *link = v.1.clone();
+            }
+        }
+    }
+}
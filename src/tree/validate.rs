@@ -0,0 +1,585 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+
+// self
+use super::*;
+use PathProfile;
+
+
+/// A tree validation issue.
+///
+/// Returned by `Tree::validate`.
+#[derive(Clone)]
+pub struct ValidationIssue {
+    /// A node with an issue.
+    pub node: Node,
+    /// An issue kind.
+    pub kind: ValidationIssueKind,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = self.node.id();
+        if id.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{} (node '{}')", self.kind, id)
+        }
+    }
+}
+
+/// List of all validation issues.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ValidationIssueKind {
+    /// The root node is not `NodeKind::Svg`.
+    InvalidRoot,
+
+    /// The first child of the root node is not `NodeKind::Defs`.
+    MissingDefs,
+
+    /// `NodeKind::Svg` or `NodeKind::Defs` node is placed not where it should be.
+    MisplacedNode,
+
+    /// A node is not allowed as a child of its parent.
+    ///
+    /// Like `Path` inside the `LinearGradient`.
+    InvalidChild,
+
+    /// A `defs` element has an empty ID.
+    EmptyId,
+
+    /// A `defs` element ID is not unique.
+    DuplicatedId,
+
+    /// A link to a non-existing `defs` element.
+    ///
+    /// Contains the link ID.
+    BrokenLink(String),
+
+    /// A link to a `defs` element of an invalid type.
+    ///
+    /// Like `Group::clip_path` that points to a `Mask`.
+    ///
+    /// Contains the link ID.
+    InvalidLinkType(String),
+
+    /// A group without children.
+    EmptyGroup,
+
+    /// An opacity or a stop offset is outside the 0..1 range.
+    OpacityOutOfRange,
+
+    /// A size is not positive.
+    ///
+    /// Used by `Svg` size and `viewBox`, `Pattern` and `Mask` rects and `Rect` shapes.
+    InvalidSize,
+
+    /// A gradient has less than two stops.
+    NotEnoughStops,
+
+    /// Gradient stop offsets are not unique or not ordered.
+    InvalidStopOffsets,
+
+    /// A radial gradient, circle or ellipse radius is not positive
+    /// or a rect corner radius is negative or bigger than half of the rect size.
+    InvalidRadius,
+
+    /// A radial gradient focal point is outside the circle.
+    InvalidFocalPoint,
+
+    /// A stroke width is not positive.
+    InvalidStrokeWidth,
+
+    /// A stroke miterlimit is less than 1.
+    InvalidMiterlimit,
+
+    /// A stroke dasharray has an odd amount of numbers or negative numbers.
+    InvalidDasharray,
+
+    /// A font size is not positive.
+    InvalidFontSize,
+
+    /// A path without segments.
+    EmptyPath,
+
+    /// The first path segment is not `MoveTo`.
+    MissingMoveTo,
+
+    /// A path segment is not allowed by the path profile.
+    ///
+    /// Like `CurveTo` with `PathProfile::LinesOnly` or `ArcTo` with `PathProfile::Default`.
+    UnsupportedSegment,
+
+    /// A polyline or a polygon has less than two points.
+    NotEnoughPoints,
+
+    /// A text without chunks, a text chunk without spans or a text span without text.
+    EmptyText,
+}
+
+impl fmt::Display for ValidationIssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationIssueKind::InvalidRoot => {
+                write!(f, "the root node must be an svg node")
+            }
+            ValidationIssueKind::MissingDefs => {
+                write!(f, "the first child of the root node must be a defs node")
+            }
+            ValidationIssueKind::MisplacedNode => {
+                write!(f, "svg and defs nodes cannot be nested")
+            }
+            ValidationIssueKind::InvalidChild => {
+                write!(f, "the node is not allowed inside its parent")
+            }
+            ValidationIssueKind::EmptyId => {
+                write!(f, "defs element has an empty ID")
+            }
+            ValidationIssueKind::DuplicatedId => {
+                write!(f, "defs element has a duplicated ID")
+            }
+            ValidationIssueKind::BrokenLink(ref id) => {
+                write!(f, "link to a non-existing element '{}'", id)
+            }
+            ValidationIssueKind::InvalidLinkType(ref id) => {
+                write!(f, "link to an element of an invalid type '{}'", id)
+            }
+            ValidationIssueKind::EmptyGroup => {
+                write!(f, "group without children")
+            }
+            ValidationIssueKind::OpacityOutOfRange => {
+                write!(f, "opacity is out of the 0..1 range")
+            }
+            ValidationIssueKind::InvalidSize => {
+                write!(f, "size must be positive")
+            }
+            ValidationIssueKind::NotEnoughStops => {
+                write!(f, "gradient must have at least two stops")
+            }
+            ValidationIssueKind::InvalidStopOffsets => {
+                write!(f, "gradient stop offsets must be unique and ordered")
+            }
+            ValidationIssueKind::InvalidRadius => {
+                write!(f, "radius must be positive and rect radius must not exceed half of its size")
+            }
+            ValidationIssueKind::InvalidFocalPoint => {
+                write!(f, "gradient focal point must be inside the circle")
+            }
+            ValidationIssueKind::InvalidStrokeWidth => {
+                write!(f, "stroke width must be positive")
+            }
+            ValidationIssueKind::InvalidMiterlimit => {
+                write!(f, "stroke miterlimit must be >= 1")
+            }
+            ValidationIssueKind::InvalidDasharray => {
+                write!(f, "stroke dasharray must have an even amount of non-negative numbers")
+            }
+            ValidationIssueKind::InvalidFontSize => {
+                write!(f, "font size must be positive")
+            }
+            ValidationIssueKind::EmptyPath => {
+                write!(f, "path must have segments")
+            }
+            ValidationIssueKind::MissingMoveTo => {
+                write!(f, "path must start with MoveTo")
+            }
+            ValidationIssueKind::UnsupportedSegment => {
+                write!(f, "path segment is not allowed by the path profile")
+            }
+            ValidationIssueKind::NotEnoughPoints => {
+                write!(f, "polyline must have at least two points")
+            }
+            ValidationIssueKind::EmptyText => {
+                write!(f, "text, text chunk and text span must not be empty")
+            }
+        }
+    }
+}
+
+
+struct Validator {
+    defs: Option<Node>,
+    profile: PathProfile,
+    issues: Vec<ValidationIssue>,
+}
+
+impl Validator {
+    fn push(&mut self, node: &Node, kind: ValidationIssueKind) {
+        self.issues.push(ValidationIssue {
+            node: node.clone(),
+            kind,
+        });
+    }
+}
+
+pub fn validate(tree: &Tree, profile: PathProfile) -> Vec<ValidationIssue> {
+    let root = tree.root();
+
+    let defs = root.first_child().and_then(|n| {
+        if let NodeKind::Defs = *n.borrow() { Some(n.clone()) } else { None }
+    });
+
+    let mut v = Validator {
+        defs,
+        profile,
+        issues: Vec::new(),
+    };
+
+    match *root.borrow() {
+        NodeKind::Svg(ref svg) => {
+            if !is_valid_size(svg.size.width, svg.size.height)
+                || !is_valid_size(svg.view_box.rect.width, svg.view_box.rect.height) {
+                v.push(&root, ValidationIssueKind::InvalidSize);
+            }
+        }
+        _ => v.push(&root, ValidationIssueKind::InvalidRoot),
+    }
+
+    if v.defs.is_none() {
+        v.push(&root, ValidationIssueKind::MissingDefs);
+    }
+
+    check_ids(&mut v);
+
+    for node in root.descendants() {
+        if node == root {
+            continue;
+        }
+
+        if !check_placement(&node, &root, &mut v) {
+            continue;
+        }
+
+        check_node(&node, &mut v);
+    }
+
+    v.issues
+}
+
+fn check_ids(v: &mut Validator) {
+    let defs = match v.defs {
+        Some(ref defs) => defs.clone(),
+        None => return,
+    };
+
+    let mut ids: Vec<String> = Vec::new();
+    for n in defs.children() {
+        let id = n.id().to_string();
+        if id.is_empty() {
+            v.push(&n, ValidationIssueKind::EmptyId);
+        } else if ids.contains(&id) {
+            v.push(&n, ValidationIssueKind::DuplicatedId);
+        } else {
+            ids.push(id);
+        }
+    }
+}
+
+/// Checks that the node is allowed inside its parent.
+///
+/// Returns `false` if the node should not be checked further.
+fn check_placement(node: &Node, root: &Node, v: &mut Validator) -> bool {
+    let parent = match node.parent() {
+        Some(parent) => parent,
+        None => return false,
+    };
+
+    match *node.borrow() {
+        NodeKind::Svg(_) => {
+            v.push(node, ValidationIssueKind::MisplacedNode);
+            return false;
+        }
+        NodeKind::Defs => {
+            if parent != *root || node.previous_sibling().is_some() {
+                v.push(node, ValidationIssueKind::MisplacedNode);
+            }
+
+            return false;
+        }
+        _ => {}
+    }
+
+    let is_valid = match (&*parent.borrow(), &*node.borrow()) {
+        (&NodeKind::Defs, &NodeKind::LinearGradient(_))
+        | (&NodeKind::Defs, &NodeKind::RadialGradient(_))
+        | (&NodeKind::Defs, &NodeKind::ClipPath(_))
+        | (&NodeKind::Defs, &NodeKind::Mask(_))
+        | (&NodeKind::Defs, &NodeKind::Pattern(_)) => true,
+        (&NodeKind::ClipPath(_), &NodeKind::Path(_))
+        | (&NodeKind::ClipPath(_), &NodeKind::Shape(_))
+        | (&NodeKind::ClipPath(_), &NodeKind::Text(_)) => true,
+        (&NodeKind::Svg(_), child)
+        | (&NodeKind::Group(_), child)
+        | (&NodeKind::Mask(_), child)
+        | (&NodeKind::Pattern(_), child) => {
+            match *child {
+                  NodeKind::Group(_)
+                | NodeKind::Path(_)
+                | NodeKind::Shape(_)
+                | NodeKind::Text(_)
+                | NodeKind::Image(_) => true,
+                _ => false,
+            }
+        }
+        _ => false,
+    };
+
+    if !is_valid {
+        v.push(node, ValidationIssueKind::InvalidChild);
+    }
+
+    true
+}
+
+fn check_node(node: &Node, v: &mut Validator) {
+    match *node.borrow() {
+        NodeKind::LinearGradient(ref lg) => {
+            check_base_grad(node, &lg.base, v);
+        }
+        NodeKind::RadialGradient(ref rg) => {
+            check_base_grad(node, &rg.base, v);
+
+            if !(rg.r > 0.0) {
+                v.push(node, ValidationIssueKind::InvalidRadius);
+            } else {
+                let dx = rg.fx - rg.cx;
+                let dy = rg.fy - rg.cy;
+                let d = (dx * dx + dy * dy).sqrt();
+                if d > rg.r && !d.fuzzy_eq(&rg.r) {
+                    v.push(node, ValidationIssueKind::InvalidFocalPoint);
+                }
+            }
+        }
+        NodeKind::Mask(ref mask) => {
+            if !is_valid_size(mask.rect.width, mask.rect.height) {
+                v.push(node, ValidationIssueKind::InvalidSize);
+            }
+        }
+        NodeKind::Pattern(ref pattern) => {
+            if !is_valid_size(pattern.rect.width, pattern.rect.height) {
+                v.push(node, ValidationIssueKind::InvalidSize);
+            }
+
+            if let Some(ref vbox) = pattern.view_box {
+                if !is_valid_size(vbox.rect.width, vbox.rect.height) {
+                    v.push(node, ValidationIssueKind::InvalidSize);
+                }
+            }
+        }
+        NodeKind::Path(ref path) => {
+            check_fill(node, &path.fill, v);
+            check_stroke(node, &path.stroke, v);
+            check_segments(node, &path.segments, v);
+        }
+        NodeKind::Shape(ref shape) => {
+            check_fill(node, &shape.fill, v);
+            check_stroke(node, &shape.stroke, v);
+
+            let is_valid_radius = match shape.kind {
+                ShapeKind::Rect { width, height, rx, ry, .. } => {
+                    if !is_valid_size(width, height) {
+                        v.push(node, ValidationIssueKind::InvalidSize);
+                    }
+
+                    rx >= 0.0 && ry >= 0.0 && rx <= width / 2.0 && ry <= height / 2.0
+                }
+                ShapeKind::Circle { r, .. } => r > 0.0,
+                ShapeKind::Ellipse { rx, ry, .. } => rx > 0.0 && ry > 0.0,
+                ShapeKind::Polyline { ref points, .. } => {
+                    if points.len() < 2 {
+                        v.push(node, ValidationIssueKind::NotEnoughPoints);
+                    }
+
+                    true
+                }
+                ShapeKind::Line { .. } => true,
+            };
+
+            if !is_valid_radius {
+                v.push(node, ValidationIssueKind::InvalidRadius);
+            }
+        }
+        NodeKind::Text(ref text) => {
+            let is_empty = text.chunks.is_empty() || text.chunks.iter().any(|chunk| {
+                chunk.spans.is_empty() || chunk.spans.iter().any(|span| span.text.is_empty())
+            });
+
+            if is_empty {
+                v.push(node, ValidationIssueKind::EmptyText);
+            }
+
+            for chunk in &text.chunks {
+                for span in &chunk.spans {
+                    check_fill(node, &span.fill, v);
+                    check_stroke(node, &span.stroke, v);
+
+                    if !(span.font.size > 0.0) {
+                        v.push(node, ValidationIssueKind::InvalidFontSize);
+                    }
+
+                    let d = &span.decoration;
+                    for style in [&d.underline, &d.overline, &d.line_through].iter() {
+                        if let Some(ref style) = **style {
+                            check_fill(node, &style.fill, v);
+                            check_stroke(node, &style.stroke, v);
+                        }
+                    }
+                }
+            }
+        }
+        NodeKind::Image(ref img) => {
+            if !is_valid_size(img.view_box.rect.width, img.view_box.rect.height) {
+                v.push(node, ValidationIssueKind::InvalidSize);
+            }
+        }
+        NodeKind::Group(ref g) => {
+            if node.first_child().is_none() {
+                v.push(node, ValidationIssueKind::EmptyGroup);
+            }
+
+            if let Some(opacity) = g.opacity {
+                check_opacity(node, opacity, v);
+            }
+
+            if let Some(ref id) = g.clip_path {
+                check_link(node, id, v, |kind| {
+                    if let NodeKind::ClipPath(_) = *kind { true } else { false }
+                });
+            }
+
+            if let Some(ref id) = g.mask {
+                check_link(node, id, v, |kind| {
+                    if let NodeKind::Mask(_) = *kind { true } else { false }
+                });
+            }
+        }
+        NodeKind::Svg(_) | NodeKind::Defs | NodeKind::ClipPath(_) => {}
+    }
+}
+
+fn check_base_grad(node: &Node, g: &BaseGradient, v: &mut Validator) {
+    if g.stops.len() < 2 {
+        v.push(node, ValidationIssueKind::NotEnoughStops);
+    }
+
+    let mut prev_offset = None;
+    let mut is_valid_offsets = true;
+    for stop in &g.stops {
+        check_opacity(node, stop.offset, v);
+        check_opacity(node, stop.opacity, v);
+
+        if let Some(prev) = prev_offset {
+            if !(stop.offset.value() > prev) {
+                is_valid_offsets = false;
+            }
+        }
+
+        prev_offset = Some(stop.offset.value());
+    }
+
+    if !is_valid_offsets {
+        v.push(node, ValidationIssueKind::InvalidStopOffsets);
+    }
+}
+
+fn check_fill(node: &Node, fill: &Option<Fill>, v: &mut Validator) {
+    if let Some(ref fill) = *fill {
+        check_paint(node, &fill.paint, v);
+        check_opacity(node, fill.opacity, v);
+    }
+}
+
+fn check_stroke(node: &Node, stroke: &Option<Stroke>, v: &mut Validator) {
+    if let Some(ref stroke) = *stroke {
+        check_paint(node, &stroke.paint, v);
+        check_opacity(node, stroke.opacity, v);
+
+        if !(stroke.width > 0.0) {
+            v.push(node, ValidationIssueKind::InvalidStrokeWidth);
+        }
+
+        if !(stroke.miterlimit >= 1.0) {
+            v.push(node, ValidationIssueKind::InvalidMiterlimit);
+        }
+
+        if let Some(ref list) = stroke.dasharray {
+            if list.len() % 2 != 0 || list.iter().any(|n| !(*n >= 0.0)) {
+                v.push(node, ValidationIssueKind::InvalidDasharray);
+            }
+        }
+    }
+}
+
+fn check_segments(node: &Node, segments: &[PathSegment], v: &mut Validator) {
+    match segments.first() {
+        Some(&PathSegment::MoveTo { .. }) => {}
+        Some(_) => v.push(node, ValidationIssueKind::MissingMoveTo),
+        None => {
+            v.push(node, ValidationIssueKind::EmptyPath);
+            return;
+        }
+    }
+
+    let is_supported = |seg: &PathSegment| {
+        match (*seg, v.profile) {
+            (PathSegment::MoveTo { .. }, _)
+            | (PathSegment::LineTo { .. }, _)
+            | (PathSegment::ClosePath, _) => true,
+            (PathSegment::CurveTo { .. }, PathProfile::LinesOnly { .. }) => false,
+            (PathSegment::CurveTo { .. }, _) => true,
+            (PathSegment::QuadTo { .. }, PathProfile::Extended)
+            | (PathSegment::ArcTo { .. }, PathProfile::Extended) => true,
+            (PathSegment::QuadTo { .. }, _) | (PathSegment::ArcTo { .. }, _) => false,
+        }
+    };
+
+    if !segments.iter().all(is_supported) {
+        v.push(node, ValidationIssueKind::UnsupportedSegment);
+    }
+}
+
+fn check_paint(node: &Node, paint: &Paint, v: &mut Validator) {
+    if let Paint::Link(ref id) = *paint {
+        check_link(node, id, v, |kind| {
+            match *kind {
+                NodeKind::LinearGradient(_)
+                | NodeKind::RadialGradient(_)
+                | NodeKind::Pattern(_) => true,
+                _ => false,
+            }
+        });
+    }
+}
+
+fn check_link<F>(node: &Node, id: &str, v: &mut Validator, is_valid_type: F)
+    where F: Fn(&NodeKind) -> bool
+{
+    let link = match v.defs {
+        Some(ref defs) => defs.children().find(|n| *n.id() == *id),
+        None => None,
+    };
+
+    match link {
+        Some(link) => {
+            if !is_valid_type(&link.borrow()) {
+                v.push(node, ValidationIssueKind::InvalidLinkType(id.to_string()));
+            }
+        }
+        None => {
+            v.push(node, ValidationIssueKind::BrokenLink(id.to_string()));
+        }
+    }
+}
+
+fn check_opacity(node: &Node, opacity: Opacity, v: &mut Validator) {
+    if !(opacity.value() >= 0.0 && opacity.value() <= 1.0) {
+        v.push(node, ValidationIssueKind::OpacityOutOfRange);
+    }
+}
+
+fn is_valid_size(width: f64, height: f64) -> bool {
+    width > 0.0 && height > 0.0
+}
@@ -0,0 +1,983 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! A native SVG writer.
+//!
+//! Unlike `Tree::to_svgdom`, writes the `Tree` directly to a string.
+
+// external
+use base64;
+
+// self
+use super::*;
+
+
+/// XML nodes indention.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Indent {
+    /// Disable indention and new lines.
+    None,
+    /// Indent with spaces.
+    Spaces(u8),
+    /// Indent with tabs.
+    Tabs,
+}
+
+/// Options that defines SVG writing.
+#[derive(Clone, Debug)]
+pub struct WriteOptions {
+    /// Sets the XML nodes indent.
+    ///
+    /// Default: `Indent::Spaces(4)`
+    pub indent: Indent,
+
+    /// Sets the XML attributes indent.
+    ///
+    /// When set, each attribute will be written on a new line.
+    ///
+    /// Default: `Indent::None`
+    pub attributes_indent: Indent,
+
+    /// Sets the maximum number of digits after the decimal point
+    /// for coordinates and all the other numbers, except transforms.
+    ///
+    /// Trailing zeros are always removed.
+    ///
+    /// Default: 8
+    pub coordinates_precision: u8,
+
+    /// Sets the maximum number of digits after the decimal point
+    /// for transforms.
+    ///
+    /// Default: 8
+    pub transforms_precision: u8,
+
+    /// Writes path data in a compact form.
+    ///
+    /// Repeated commands, separators before negative numbers and
+    /// leading zeros will be removed when possible.
+    ///
+    /// `M 10 20 L 30 -0.5 L 0.5 0.25` -> `M10 20L30-.5.5.25`
+    ///
+    /// Default: `false`
+    pub compact_path: bool,
+
+    /// Skips attributes with default values.
+    ///
+    /// Like `fill-opacity="1"` or `stroke-linecap="butt"`.
+    ///
+    /// Default: `false`
+    pub skip_default_attributes: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> WriteOptions {
+        WriteOptions {
+            indent: Indent::Spaces(4),
+            attributes_indent: Indent::None,
+            coordinates_precision: 8,
+            transforms_precision: 8,
+            compact_path: false,
+            skip_default_attributes: false,
+        }
+    }
+}
+
+
+pub fn write_tree(tree: &Tree, opt: &WriteOptions) -> String {
+    let mut xml = XmlWriter::new(opt);
+
+    let svg = tree.svg_node();
+
+    xml.start_element("svg");
+    xml.write_attribute("xmlns", "http://www.w3.org/2000/svg");
+    xml.write_attribute_num("width", svg.size.width);
+    xml.write_attribute_num("height", svg.size.height);
+    write_viewbox(&svg.view_box, &mut xml);
+    xml.write_attribute("xmlns:xlink", "http://www.w3.org/1999/xlink");
+    xml.write_attribute("xmlns:usvg", "https://github.com/RazrFalcon/usvg");
+    xml.write_attribute("usvg:version", env!("CARGO_PKG_VERSION"));
+
+    xml.start_element("defs");
+    write_defs(tree, &mut xml);
+    xml.end_element();
+
+    write_elements(tree, &tree.root(), false, &mut xml);
+
+    xml.end_element();
+
+    xml.finish()
+}
+
+fn write_defs(tree: &Tree, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    for n in tree.defs().children() {
+        match *n.borrow() {
+            NodeKind::LinearGradient(ref lg) => {
+                xml.start_element("linearGradient");
+                xml.write_attribute("id", &lg.id);
+
+                let is_bbox = lg.units == Units::ObjectBoundingBox;
+                if !(skip && lg.x1.is_fuzzy_zero()) {
+                    xml.write_attribute_num("x1", lg.x1);
+                }
+                if !(skip && lg.y1.is_fuzzy_zero()) {
+                    xml.write_attribute_num("y1", lg.y1);
+                }
+                if !(skip && is_bbox && lg.x2.fuzzy_eq(&1.0)) {
+                    xml.write_attribute_num("x2", lg.x2);
+                }
+                if !(skip && lg.y2.is_fuzzy_zero()) {
+                    xml.write_attribute_num("y2", lg.y2);
+                }
+
+                write_base_grad(&lg.base, xml);
+                xml.end_element();
+            }
+            NodeKind::RadialGradient(ref rg) => {
+                xml.start_element("radialGradient");
+                xml.write_attribute("id", &rg.id);
+
+                let is_bbox = rg.units == Units::ObjectBoundingBox;
+                if !(skip && is_bbox && rg.cx.fuzzy_eq(&0.5)) {
+                    xml.write_attribute_num("cx", rg.cx);
+                }
+                if !(skip && is_bbox && rg.cy.fuzzy_eq(&0.5)) {
+                    xml.write_attribute_num("cy", rg.cy);
+                }
+                if !(skip && is_bbox && rg.r.fuzzy_eq(&0.5)) {
+                    xml.write_attribute_num("r", rg.r);
+                }
+                if !(skip && rg.fx.fuzzy_eq(&rg.cx)) {
+                    xml.write_attribute_num("fx", rg.fx);
+                }
+                if !(skip && rg.fy.fuzzy_eq(&rg.cy)) {
+                    xml.write_attribute_num("fy", rg.fy);
+                }
+
+                write_base_grad(&rg.base, xml);
+                xml.end_element();
+            }
+            NodeKind::ClipPath(ref clip) => {
+                xml.start_element("clipPath");
+                xml.write_attribute("id", &clip.id);
+                write_units("clipPathUnits", clip.units, Units::UserSpaceOnUse, xml);
+                write_transform("transform", &clip.transform, xml);
+                write_elements(tree, &n, true, xml);
+                xml.end_element();
+            }
+            NodeKind::Mask(ref mask) => {
+                xml.start_element("mask");
+                xml.write_attribute("id", &mask.id);
+                write_units("maskUnits", mask.units, Units::ObjectBoundingBox, xml);
+                write_units("maskContentUnits", mask.content_units, Units::UserSpaceOnUse, xml);
+
+                let is_default_rect = mask.units == Units::ObjectBoundingBox
+                    && mask.rect.x.fuzzy_eq(&-0.1)
+                    && mask.rect.y.fuzzy_eq(&-0.1)
+                    && mask.rect.width.fuzzy_eq(&1.2)
+                    && mask.rect.height.fuzzy_eq(&1.2);
+
+                if !(skip && is_default_rect) {
+                    write_rect(mask.rect, xml);
+                }
+
+                write_elements(tree, &n, false, xml);
+                xml.end_element();
+            }
+            NodeKind::Pattern(ref pattern) => {
+                xml.start_element("pattern");
+                xml.write_attribute("id", &pattern.id);
+                write_rect(pattern.rect, xml);
+
+                if let Some(ref vbox) = pattern.view_box {
+                    write_viewbox(vbox, xml);
+                }
+
+                write_units("patternUnits", pattern.units, Units::ObjectBoundingBox, xml);
+                write_units("patternContentUnits", pattern.content_units,
+                            Units::UserSpaceOnUse, xml);
+                write_transform("patternTransform", &pattern.transform, xml);
+                write_elements(tree, &n, false, xml);
+                xml.end_element();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_elements(tree: &Tree, root: &Node, is_clip_path: bool, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    for n in root.children() {
+        match *n.borrow() {
+            NodeKind::Path(ref p) => {
+                xml.start_element("path");
+                write_id(&p.id, xml);
+                write_fill(tree, &p.fill, is_clip_path, xml);
+                write_stroke(tree, &p.stroke, xml);
+                write_transform("transform", &p.transform, xml);
+
+                let d = path_data(&p.segments, xml.opt);
+                xml.write_attribute("d", &d);
+                xml.end_element();
+            }
+            NodeKind::Shape(ref shape) => {
+                xml.start_element(match shape.kind {
+                    ShapeKind::Rect { .. } => "rect",
+                    ShapeKind::Circle { .. } => "circle",
+                    ShapeKind::Ellipse { .. } => "ellipse",
+                    ShapeKind::Line { .. } => "line",
+                    ShapeKind::Polyline { closed: false, .. } => "polyline",
+                    ShapeKind::Polyline { closed: true, .. } => "polygon",
+                });
+                write_id(&shape.id, xml);
+                write_fill(tree, &shape.fill, is_clip_path, xml);
+                write_stroke(tree, &shape.stroke, xml);
+                write_transform("transform", &shape.transform, xml);
+                write_shape_kind(&shape.kind, xml);
+                xml.end_element();
+            }
+            NodeKind::Text(ref text) => {
+                xml.start_element("text");
+                write_id(&text.id, xml);
+                write_transform("transform", &text.transform, xml);
+
+                if let Some(ref rotate) = text.rotate {
+                    xml.write_attribute_num_list("rotate", rotate);
+                }
+
+                let is_preserve_required = text.chunks.iter()
+                    .any(|c| c.spans.iter().any(|s| s.text.contains("  ")));
+
+                if is_preserve_required {
+                    xml.write_attribute("xml:space", "preserve");
+                }
+
+                // Whitespaces are significant inside a text element,
+                // so it must be written without indent.
+                xml.set_preserve_whitespaces(true);
+
+                for chunk in &text.chunks {
+                    xml.start_element("tspan");
+
+                    if let Some(ref x) = chunk.x {
+                        xml.write_attribute_num_list("x", x);
+                    }
+
+                    if let Some(ref y) = chunk.y {
+                        xml.write_attribute_num_list("y", y);
+                    }
+
+                    if let Some(ref dx) = chunk.dx {
+                        xml.write_attribute_num_list("dx", dx);
+                    }
+
+                    if let Some(ref dy) = chunk.dy {
+                        xml.write_attribute_num_list("dy", dy);
+                    }
+
+                    if chunk.anchor != TextAnchor::Start {
+                        xml.write_attribute("text-anchor", match chunk.anchor {
+                            TextAnchor::Start => "start",
+                            TextAnchor::Middle => "middle",
+                            TextAnchor::End => "end",
+                        });
+                    }
+
+                    for tspan in &chunk.spans {
+                        xml.start_element("tspan");
+                        write_fill(tree, &tspan.fill, false, xml);
+                        write_stroke(tree, &tspan.stroke, xml);
+                        write_font(&tspan.font, xml);
+                        xml.write_text(&tspan.text);
+                        xml.end_element();
+                    }
+
+                    xml.end_element();
+                }
+
+                xml.end_element();
+                xml.set_preserve_whitespaces(false);
+            }
+            NodeKind::Image(ref img) => {
+                xml.start_element("image");
+                write_id(&img.id, xml);
+
+                let r = img.view_box.rect;
+                if !(skip && r.x.is_fuzzy_zero()) {
+                    xml.write_attribute_num("x", r.x);
+                }
+                if !(skip && r.y.is_fuzzy_zero()) {
+                    xml.write_attribute_num("y", r.y);
+                }
+                xml.write_attribute_num("width", r.width);
+                xml.write_attribute_num("height", r.height);
+                write_aspect(img.view_box.aspect, xml);
+                write_transform("transform", &img.transform, xml);
+
+                let href = match img.data {
+                    ImageData::Path(ref path) => path.to_string_lossy().into_owned(),
+                    ImageData::Raw(ref data) => {
+                        let base64_conf = base64::Config::new(
+                            base64::CharacterSet::Standard,
+                            true,
+                            true,
+                            base64::LineWrap::Wrap(64, base64::LineEnding::LF),
+                        );
+
+                        let mut d = String::with_capacity(data.len() + 20);
+
+                        d.push_str("data:image/");
+                        match img.format {
+                            ImageFormat::PNG => d.push_str("png"),
+                            ImageFormat::JPEG => d.push_str("jpg"),
+                            ImageFormat::SVG => d.push_str("svg+xml"),
+                        }
+                        d.push_str(";base64,\n");
+                        d.push_str(&base64::encode_config(data, base64_conf));
+
+                        d
+                    }
+                };
+
+                xml.write_attribute("xlink:href", &href);
+                xml.end_element();
+            }
+            NodeKind::Group(ref g) => {
+                xml.start_element("g");
+                write_id(&g.id, xml);
+
+                if let Some(ref id) = g.clip_path {
+                    write_link("clip-path", tree, id, xml);
+                }
+
+                if let Some(ref id) = g.mask {
+                    write_link("mask", tree, id, xml);
+                }
+
+                if let Some(opacity) = g.opacity {
+                    if !(skip && opacity.fuzzy_eq(&1.0)) {
+                        xml.write_attribute_num("opacity", opacity.value());
+                    }
+                }
+
+                write_transform("transform", &g.transform, xml);
+                write_elements(tree, &n, false, xml);
+                xml.end_element();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn write_id(id: &str, xml: &mut XmlWriter) {
+    if !id.is_empty() {
+        xml.write_attribute("id", id);
+    }
+}
+
+fn write_link(name: &str, tree: &Tree, id: &str, xml: &mut XmlWriter) {
+    if tree.defs_by_id(id).is_some() {
+        xml.write_attribute(name, &format!("url(#{})", id));
+    }
+}
+
+fn write_viewbox(view_box: &ViewBox, xml: &mut XmlWriter) {
+    let r = view_box.rect;
+    xml.write_attribute_num_list("viewBox", &[r.x, r.y, r.width, r.height]);
+    write_aspect(view_box.aspect, xml);
+}
+
+fn write_aspect(aspect: AspectRatio, xml: &mut XmlWriter) {
+    if xml.opt.skip_default_attributes && aspect == AspectRatio::default() {
+        return;
+    }
+
+    let mut s = String::new();
+
+    if aspect.defer {
+        s.push_str("defer ");
+    }
+
+    s.push_str(match aspect.align {
+        Align::None => "none",
+        Align::XMinYMin => "xMinYMin",
+        Align::XMidYMin => "xMidYMin",
+        Align::XMaxYMin => "xMaxYMin",
+        Align::XMinYMid => "xMinYMid",
+        Align::XMidYMid => "xMidYMid",
+        Align::XMaxYMid => "xMaxYMid",
+        Align::XMinYMax => "xMinYMax",
+        Align::XMidYMax => "xMidYMax",
+        Align::XMaxYMax => "xMaxYMax",
+    });
+
+    if aspect.slice {
+        s.push_str(" slice");
+    }
+
+    xml.write_attribute("preserveAspectRatio", &s);
+}
+
+fn write_rect(r: Rect, xml: &mut XmlWriter) {
+    xml.write_attribute_num("x", r.x);
+    xml.write_attribute_num("y", r.y);
+    xml.write_attribute_num("width", r.width);
+    xml.write_attribute_num("height", r.height);
+}
+
+fn write_shape_kind(kind: &ShapeKind, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    match *kind {
+        ShapeKind::Rect { x, y, width, height, rx, ry } => {
+            if !(skip && x.is_fuzzy_zero()) {
+                xml.write_attribute_num("x", x);
+            }
+            if !(skip && y.is_fuzzy_zero()) {
+                xml.write_attribute_num("y", y);
+            }
+            xml.write_attribute_num("width", width);
+            xml.write_attribute_num("height", height);
+
+            // Zero radii are always skipped, since a single radius will be
+            // used for both of them otherwise.
+            if !rx.is_fuzzy_zero() || !ry.is_fuzzy_zero() {
+                xml.write_attribute_num("rx", rx);
+                xml.write_attribute_num("ry", ry);
+            }
+        }
+        ShapeKind::Circle { cx, cy, r } => {
+            xml.write_attribute_num("cx", cx);
+            xml.write_attribute_num("cy", cy);
+            xml.write_attribute_num("r", r);
+        }
+        ShapeKind::Ellipse { cx, cy, rx, ry } => {
+            xml.write_attribute_num("cx", cx);
+            xml.write_attribute_num("cy", cy);
+            xml.write_attribute_num("rx", rx);
+            xml.write_attribute_num("ry", ry);
+        }
+        ShapeKind::Line { x1, y1, x2, y2 } => {
+            xml.write_attribute_num("x1", x1);
+            xml.write_attribute_num("y1", y1);
+            xml.write_attribute_num("x2", x2);
+            xml.write_attribute_num("y2", y2);
+        }
+        ShapeKind::Polyline { ref points, .. } => {
+            let mut list = Vec::with_capacity(points.len() * 2);
+            for p in points {
+                list.push(p.x);
+                list.push(p.y);
+            }
+
+            xml.write_attribute_num_list("points", &list);
+        }
+    }
+}
+
+fn write_units(name: &str, units: Units, default: Units, xml: &mut XmlWriter) {
+    if xml.opt.skip_default_attributes && units == default {
+        return;
+    }
+
+    xml.write_attribute(name, match units {
+        Units::UserSpaceOnUse => "userSpaceOnUse",
+        Units::ObjectBoundingBox => "objectBoundingBox",
+    });
+}
+
+fn write_transform(name: &str, ts: &Transform, xml: &mut XmlWriter) {
+    if ts.is_default() {
+        return;
+    }
+
+    let precision = xml.opt.transforms_precision;
+
+    let mut s = String::from("matrix(");
+    for (i, n) in [ts.a, ts.b, ts.c, ts.d, ts.e, ts.f].iter().enumerate() {
+        if i != 0 {
+            s.push(' ');
+        }
+
+        write_num(*n, precision, false, &mut s);
+    }
+    s.push(')');
+
+    xml.write_attribute(name, &s);
+}
+
+fn write_paint(name: &str, tree: &Tree, paint: &Paint, xml: &mut XmlWriter) {
+    match *paint {
+        Paint::Color(c) => xml.write_attribute(name, &color_to_string(c)),
+        Paint::Link(ref id) => write_link(name, tree, id, xml),
+    }
+}
+
+fn write_fill(tree: &Tree, fill: &Option<Fill>, is_clip_path: bool, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    match *fill {
+        Some(ref fill) => {
+            let is_black = match fill.paint {
+                Paint::Color(c) => c == Color::black(),
+                Paint::Link(_) => false,
+            };
+
+            if !(skip && is_black) {
+                write_paint("fill", tree, &fill.paint, xml);
+            }
+
+            if !(skip && fill.opacity.fuzzy_eq(&1.0)) {
+                xml.write_attribute_num("fill-opacity", fill.opacity.value());
+            }
+
+            if !(skip && fill.rule == FillRule::NonZero) {
+                let rule = if fill.rule == FillRule::NonZero { "nonzero" } else { "evenodd" };
+                let name = if is_clip_path { "clip-rule" } else { "fill-rule" };
+                xml.write_attribute(name, rule);
+            }
+        }
+        None => {
+            xml.write_attribute("fill", "none");
+        }
+    }
+}
+
+fn write_stroke(tree: &Tree, stroke: &Option<Stroke>, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    match *stroke {
+        Some(ref stroke) => {
+            write_paint("stroke", tree, &stroke.paint, xml);
+
+            if !(skip && stroke.opacity.fuzzy_eq(&1.0)) {
+                xml.write_attribute_num("stroke-opacity", stroke.opacity.value());
+            }
+
+            if !(skip && stroke.dashoffset.is_fuzzy_zero()) {
+                xml.write_attribute_num("stroke-dashoffset", stroke.dashoffset);
+            }
+
+            if !(skip && stroke.miterlimit.fuzzy_eq(&4.0)) {
+                xml.write_attribute_num("stroke-miterlimit", stroke.miterlimit);
+            }
+
+            if !(skip && stroke.width.fuzzy_eq(&1.0)) {
+                xml.write_attribute_num("stroke-width", stroke.width);
+            }
+
+            if !(skip && stroke.linecap == LineCap::Butt) {
+                xml.write_attribute("stroke-linecap", match stroke.linecap {
+                    LineCap::Butt => "butt",
+                    LineCap::Round => "round",
+                    LineCap::Square => "square",
+                });
+            }
+
+            if !(skip && stroke.linejoin == LineJoin::Miter) {
+                xml.write_attribute("stroke-linejoin", match stroke.linejoin {
+                    LineJoin::Miter => "miter",
+                    LineJoin::Round => "round",
+                    LineJoin::Bevel => "bevel",
+                });
+            }
+
+            if let Some(ref array) = stroke.dasharray {
+                xml.write_attribute_num_list("stroke-dasharray", array);
+            } else if !skip {
+                xml.write_attribute("stroke-dasharray", "none");
+            }
+        }
+        None => {
+            if !skip {
+                xml.write_attribute("stroke", "none");
+            }
+        }
+    }
+}
+
+fn write_base_grad(g: &BaseGradient, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    write_units("gradientUnits", g.units, Units::ObjectBoundingBox, xml);
+
+    if !(skip && g.spread_method == SpreadMethod::Pad) {
+        xml.write_attribute("spreadMethod", match g.spread_method {
+            SpreadMethod::Pad => "pad",
+            SpreadMethod::Reflect => "reflect",
+            SpreadMethod::Repeat => "repeat",
+        });
+    }
+
+    write_transform("gradientTransform", &g.transform, xml);
+
+    for s in &g.stops {
+        xml.start_element("stop");
+
+        if !(skip && s.offset.is_fuzzy_zero()) {
+            xml.write_attribute_num("offset", s.offset.value());
+        }
+
+        if !(skip && s.color == Color::black()) {
+            xml.write_attribute("stop-color", &color_to_string(s.color));
+        }
+
+        if !(skip && s.opacity.fuzzy_eq(&1.0)) {
+            xml.write_attribute_num("stop-opacity", s.opacity.value());
+        }
+
+        xml.end_element();
+    }
+}
+
+fn write_font(font: &Font, xml: &mut XmlWriter) {
+    let skip = xml.opt.skip_default_attributes;
+
+    let family: Vec<String> = font.family.iter().map(|f| f.to_string()).collect();
+    xml.write_attribute("font-family", &family.join(", "));
+    xml.write_attribute_num("font-size", font.size);
+
+    if !(skip && font.style == FontStyle::Normal) {
+        xml.write_attribute("font-style", match font.style {
+            FontStyle::Normal => "normal",
+            FontStyle::Italic => "italic",
+            FontStyle::Oblique => "oblique",
+        });
+    }
+
+    if !(skip && font.variant == FontVariant::Normal) {
+        xml.write_attribute("font-variant", match font.variant {
+            FontVariant::Normal => "normal",
+            FontVariant::SmallCaps => "small-caps",
+        });
+    }
+
+    if !(skip && font.weight == FontWeight::W400) {
+        xml.write_attribute("font-weight", match font.weight {
+            FontWeight::W100 => "100",
+            FontWeight::W200 => "200",
+            FontWeight::W300 => "300",
+            FontWeight::W400 => "400",
+            FontWeight::W500 => "500",
+            FontWeight::W600 => "600",
+            FontWeight::W700 => "700",
+            FontWeight::W800 => "800",
+            FontWeight::W900 => "900",
+        });
+    }
+
+    if !(skip && font.stretch == FontStretch::Normal) {
+        xml.write_attribute("font-stretch", match font.stretch {
+            FontStretch::Normal => "normal",
+            FontStretch::Wider => "wider",
+            FontStretch::Narrower => "narrower",
+            FontStretch::UltraCondensed => "ultra-condensed",
+            FontStretch::ExtraCondensed => "extra-condensed",
+            FontStretch::Condensed => "condensed",
+            FontStretch::SemiCondensed => "semi-condensed",
+            FontStretch::SemiExpanded => "semi-expanded",
+            FontStretch::Expanded => "expanded",
+            FontStretch::ExtraExpanded => "extra-expanded",
+            FontStretch::UltraExpanded => "ultra-expanded",
+        });
+    }
+}
+
+fn color_to_string(c: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.red, c.green, c.blue)
+}
+
+fn path_data(segments: &[PathSegment], opt: &WriteOptions) -> String {
+    let mut p = PathWriter {
+        buf: String::with_capacity(segments.len() * 16),
+        opt,
+        prev_cmd: 0,
+        is_after_cmd: false,
+        prev_has_dot: false,
+    };
+
+    for seg in segments {
+        match *seg {
+            PathSegment::MoveTo { x, y } => {
+                p.write_cmd(b'M');
+                p.write_num(x);
+                p.write_num(y);
+            }
+            PathSegment::LineTo { x, y } => {
+                p.write_cmd(b'L');
+                p.write_num(x);
+                p.write_num(y);
+            }
+            PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                p.write_cmd(b'C');
+                p.write_num(x1);
+                p.write_num(y1);
+                p.write_num(x2);
+                p.write_num(y2);
+                p.write_num(x);
+                p.write_num(y);
+            }
+            PathSegment::QuadTo { x1, y1, x, y } => {
+                p.write_cmd(b'Q');
+                p.write_num(x1);
+                p.write_num(y1);
+                p.write_num(x);
+                p.write_num(y);
+            }
+            PathSegment::ArcTo { rx, ry, x_axis_rotation, large_arc, sweep, x, y } => {
+                p.write_cmd(b'A');
+                p.write_num(rx);
+                p.write_num(ry);
+                p.write_num(x_axis_rotation);
+                p.write_num(if large_arc { 1.0 } else { 0.0 });
+                p.write_num(if sweep { 1.0 } else { 0.0 });
+                p.write_num(x);
+                p.write_num(y);
+            }
+            PathSegment::ClosePath => {
+                p.write_cmd(b'Z');
+            }
+        }
+    }
+
+    p.buf
+}
+
+struct PathWriter<'a> {
+    buf: String,
+    opt: &'a WriteOptions,
+    prev_cmd: u8,
+    is_after_cmd: bool,
+    prev_has_dot: bool,
+}
+
+impl<'a> PathWriter<'a> {
+    fn write_cmd(&mut self, cmd: u8) {
+        if self.opt.compact_path {
+            // An implicit command is allowed only for the same command.
+            // `M` is an exception, since subsequent pairs are treated as `L`.
+            if cmd == self.prev_cmd && cmd != b'M' && cmd != b'Z' {
+                return;
+            }
+        } else if !self.buf.is_empty() {
+            self.buf.push(' ');
+        }
+
+        self.buf.push(cmd as char);
+        self.prev_cmd = cmd;
+        self.is_after_cmd = true;
+    }
+
+    fn write_num(&mut self, n: f64) {
+        let mut s = String::new();
+        write_num(n, self.opt.coordinates_precision, self.opt.compact_path, &mut s);
+
+        if self.opt.compact_path {
+            let is_sep_required = if self.is_after_cmd {
+                false
+            } else if s.starts_with('-') {
+                false
+            } else if s.starts_with('.') {
+                // `.5.5` is parsed as `.5 .5`.
+                !self.prev_has_dot
+            } else {
+                true
+            };
+
+            if is_sep_required {
+                self.buf.push(' ');
+            }
+        } else {
+            self.buf.push(' ');
+        }
+
+        self.prev_has_dot = s.contains('.');
+        self.is_after_cmd = false;
+        self.buf.push_str(&s);
+    }
+}
+
+/// Writes a number with the specified precision.
+///
+/// Trailing zeros are removed.
+fn write_num(n: f64, precision: u8, remove_leading_zero: bool, buf: &mut String) {
+    let s = format!("{:.*}", precision as usize, n);
+
+    let mut s = s.as_str();
+    if s.contains('.') {
+        s = s.trim_right_matches('0').trim_right_matches('.');
+    }
+
+    if s == "-0" {
+        s = "0";
+    }
+
+    if remove_leading_zero {
+        if s.starts_with("0.") {
+            s = &s[1..];
+        } else if s.starts_with("-0.") {
+            buf.push('-');
+            s = &s[2..];
+        }
+    }
+
+    buf.push_str(s);
+}
+
+
+struct XmlWriter<'a> {
+    buf: String,
+    opt: &'a WriteOptions,
+    /// Names of the currently opened elements.
+    stack: Vec<&'static str>,
+    /// Indicates that the last element start tag is not closed yet.
+    is_open_tag: bool,
+    preserve_whitespaces: bool,
+}
+
+impl<'a> XmlWriter<'a> {
+    fn new(opt: &'a WriteOptions) -> Self {
+        XmlWriter {
+            buf: String::new(),
+            opt,
+            stack: Vec::new(),
+            is_open_tag: false,
+            preserve_whitespaces: false,
+        }
+    }
+
+    fn start_element(&mut self, name: &'static str) {
+        self.close_open_tag();
+
+        if !self.preserve_whitespaces {
+            let depth = self.stack.len();
+            self.write_new_line(depth);
+        }
+
+        self.buf.push('<');
+        self.buf.push_str(name);
+        self.stack.push(name);
+        self.is_open_tag = true;
+    }
+
+    fn write_attribute(&mut self, name: &str, value: &str) {
+        debug_assert!(self.is_open_tag);
+
+        if self.opt.attributes_indent == Indent::None {
+            self.buf.push(' ');
+        } else {
+            self.buf.push('\n');
+            let depth = self.stack.len() - 1;
+            push_indent(self.opt.indent, depth, &mut self.buf);
+            push_indent(self.opt.attributes_indent, 1, &mut self.buf);
+        }
+
+        self.buf.push_str(name);
+        self.buf.push_str("=\"");
+        push_escaped(value, true, &mut self.buf);
+        self.buf.push('"');
+    }
+
+    fn write_attribute_num(&mut self, name: &str, n: f64) {
+        let mut s = String::new();
+        write_num(n, self.opt.coordinates_precision, false, &mut s);
+        self.write_attribute(name, &s);
+    }
+
+    fn write_attribute_num_list(&mut self, name: &str, list: &[f64]) {
+        let mut s = String::new();
+        for (i, n) in list.iter().enumerate() {
+            if i != 0 {
+                s.push(' ');
+            }
+
+            write_num(*n, self.opt.coordinates_precision, false, &mut s);
+        }
+
+        self.write_attribute(name, &s);
+    }
+
+    fn write_text(&mut self, text: &str) {
+        self.close_open_tag();
+        push_escaped(text, false, &mut self.buf);
+    }
+
+    fn end_element(&mut self) {
+        let name = self.stack.pop().unwrap();
+
+        if self.is_open_tag {
+            self.buf.push_str("/>");
+            self.is_open_tag = false;
+            return;
+        }
+
+        if !self.preserve_whitespaces {
+            let depth = self.stack.len();
+            self.write_new_line(depth);
+        }
+
+        self.buf.push_str("</");
+        self.buf.push_str(name);
+        self.buf.push('>');
+    }
+
+    fn set_preserve_whitespaces(&mut self, flag: bool) {
+        self.preserve_whitespaces = flag;
+    }
+
+    fn finish(mut self) -> String {
+        if self.opt.indent != Indent::None {
+            self.buf.push('\n');
+        }
+
+        self.buf
+    }
+
+    fn close_open_tag(&mut self) {
+        if self.is_open_tag {
+            self.buf.push('>');
+            self.is_open_tag = false;
+        }
+    }
+
+    fn write_new_line(&mut self, depth: usize) {
+        if self.opt.indent == Indent::None || self.buf.is_empty() {
+            return;
+        }
+
+        self.buf.push('\n');
+        push_indent(self.opt.indent, depth, &mut self.buf);
+    }
+}
+
+fn push_indent(indent: Indent, depth: usize, buf: &mut String) {
+    match indent {
+        Indent::None => {}
+        Indent::Spaces(n) => {
+            for _ in 0..(n as usize * depth) {
+                buf.push(' ');
+            }
+        }
+        Indent::Tabs => {
+            for _ in 0..depth {
+                buf.push('\t');
+            }
+        }
+    }
+}
+
+fn push_escaped(text: &str, is_attribute: bool, buf: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' if !is_attribute => buf.push_str("&gt;"),
+            '"' if is_attribute => buf.push_str("&quot;"),
+            _ => buf.push(c),
+        }
+    }
+}
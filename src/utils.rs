@@ -4,6 +4,8 @@
 
 //! Some useful utilities.
 
+use std::f64;
+
 // external
 use svgdom::{
     Align,
@@ -13,6 +15,16 @@ use svgdom::{
 
 // self
 use geom::*;
+use path_utils;
+use tree::{
+    self,
+    LineCap,
+    LineJoin,
+    NodeKind,
+    PathSegment,
+    Stroke,
+};
+use PathProfile;
 
 
 /// Converts `viewBox` to `Transform`.
@@ -47,6 +59,17 @@ pub fn view_box_to_transform(
     Transform::new(sx, 0.0, 0.0, sy, pos.x, pos.y)
 }
 
+/// Returns the maximum scale factor of the transform, which is its largest singular value.
+///
+/// Used to convert a tolerance from the canvas coordinates, so the error
+/// will not exceed it in any direction, even with a non-uniform scale or skew.
+pub(crate) fn max_scale(ts: &Transform) -> f64 {
+    let p = (ts.a * ts.a + ts.b * ts.b - ts.c * ts.c - ts.d * ts.d) / 2.0;
+    let q = ts.a * ts.c + ts.b * ts.d;
+    let mean = (ts.a * ts.a + ts.b * ts.b + ts.c * ts.c + ts.d * ts.d) / 2.0;
+    (mean + (p * p + q * q).sqrt()).sqrt()
+}
+
 /// Returns object aligned position.
 pub fn aligned_pos(align: Align, x: f64, y: f64, w: f64, h: f64) -> Point {
     match align {
@@ -62,3 +85,177 @@ pub fn aligned_pos(align: Align, x: f64, y: f64, w: f64, h: f64) -> Point {
         Align::XMaxYMax => Point::new(x + w,       y + h      ),
     }
 }
+
+
+/// Calculates node's bounding box.
+///
+/// The bbox is in the parent coordinate system, so the node's own
+/// transform is already applied.
+///
+/// When `include_stroke` is set, the stroke width, caps and joins will be
+/// taken into account. The resulting bbox is not always tight in this case,
+/// but it will always contain the whole stroke.
+///
+/// Returns `None` for `Defs`, gradients, `Text`, since it's not laid out,
+/// and for nodes without renderable children.
+pub fn calculate_bbox(node: &tree::Node, include_stroke: bool) -> Option<Rect> {
+    calc_node_bbox(node, Transform::default(), include_stroke)
+}
+
+/// Calculates node's bounding box using the specified parent transform.
+pub(crate) fn calc_node_bbox(
+    node: &tree::Node,
+    parent_ts: Transform,
+    include_stroke: bool,
+) -> Option<Rect> {
+    let mut ts = parent_ts;
+    ts.append(&node.borrow().transform());
+
+    match *node.borrow() {
+        NodeKind::Path(ref path) => {
+            let stroke = if include_stroke { path.stroke.as_ref() } else { None };
+            path_bbox(&path.segments, stroke, &ts)
+        }
+        NodeKind::Shape(ref shape) => {
+            let stroke = if include_stroke { shape.stroke.as_ref() } else { None };
+            path_bbox(&shape.kind.to_path(PathProfile::Extended, 0.0), stroke, &ts)
+        }
+        NodeKind::Image(ref img) => {
+            Some(img.view_box.rect.transform(ts))
+        }
+          NodeKind::Svg(_)
+        | NodeKind::Group(_)
+        | NodeKind::ClipPath(_)
+        | NodeKind::Mask(_)
+        | NodeKind::Pattern(_) => {
+            let mut bbox: Option<Rect> = None;
+            for child in node.children() {
+                if let NodeKind::Defs = *child.borrow() {
+                    continue;
+                }
+
+                if let Some(r) = calc_node_bbox(&child, ts, include_stroke) {
+                    bbox = Some(match bbox {
+                        Some(bbox) => bbox.union(&r),
+                        None => r,
+                    });
+                }
+            }
+
+            bbox
+        }
+          NodeKind::Defs
+        | NodeKind::LinearGradient(_)
+        | NodeKind::RadialGradient(_)
+        | NodeKind::Text(_) => None,
+    }
+}
+
+/// Calculates the bounding box of the `node` children in the `node` coordinates.
+///
+/// Strokes are ignored.
+pub(crate) fn calc_children_bbox(node: &tree::Node) -> Option<Rect> {
+    let mut bbox: Option<Rect> = None;
+    for child in node.children() {
+        if let Some(r) = calc_node_bbox(&child, Transform::default(), false) {
+            bbox = Some(match bbox {
+                Some(bbox) => bbox.union(&r),
+                None => r,
+            });
+        }
+    }
+
+    bbox
+}
+
+/// Calculates path's bounding box.
+///
+/// Segments are transformed by `ts` before the calculation,
+/// so a rotated path will still produce a tight bbox.
+///
+/// When `stroke` is set, the bbox will be expanded to contain the whole stroke,
+/// including joins and caps.
+///
+/// Returns `None` when segments are empty.
+pub fn path_bbox(
+    segments: &[PathSegment],
+    stroke: Option<&Stroke>,
+    ts: &Transform,
+) -> Option<Rect> {
+    let mut bbox: Option<Rect> = None;
+    let mut prev = Point::new(0.0, 0.0);
+    let mut start = prev;
+
+    {
+        let mut add_rect = |r: Rect| {
+            bbox = Some(match bbox {
+                Some(bbox) => bbox.union(&r),
+                None => r,
+            });
+        };
+
+        for seg in segments {
+            match *seg {
+                PathSegment::MoveTo { x, y } => {
+                    prev = ts.apply(x, y).into();
+                    start = prev;
+                    add_rect(Rect::new(prev.x, prev.y, 0.0, 0.0));
+                }
+                PathSegment::LineTo { x, y } => {
+                    prev = ts.apply(x, y).into();
+                    add_rect(Rect::new(prev.x, prev.y, 0.0, 0.0));
+                }
+                PathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                    let curve = CubicBezier::new(
+                        prev,
+                        ts.apply(x1, y1).into(),
+                        ts.apply(x2, y2).into(),
+                        ts.apply(x, y).into(),
+                    );
+
+                    prev = curve.p3;
+                    add_rect(curve.bbox());
+                }
+                PathSegment::QuadTo { x, y, .. } | PathSegment::ArcTo { x, y, .. } => {
+                    let seg = path_utils::transform_segments(&[*seg], ts)[0];
+                    for curve in path_utils::extended_curves(prev, &seg) {
+                        add_rect(curve.bbox());
+                    }
+
+                    prev = ts.apply(x, y).into();
+                    add_rect(Rect::new(prev.x, prev.y, 0.0, 0.0));
+                }
+                PathSegment::ClosePath => {
+                    prev = start;
+                }
+            }
+        }
+    }
+
+    let bbox = try_opt!(bbox, None);
+
+    match stroke {
+        Some(stroke) => {
+            // The stroke outline is a curve offset by the half of the stroke width
+            // in the path coordinate system. After transformation, the offset circle
+            // becomes an ellipse, which bbox half-extents can be calculated directly.
+            let mut hw = stroke.width / 2.0;
+
+            // Miter joins and square caps can go beyond the offset circle.
+            let mut k = 1.0;
+            if stroke.linejoin == LineJoin::Miter {
+                k = stroke.miterlimit;
+            }
+            if stroke.linecap == LineCap::Square && k < f64::consts::SQRT_2 {
+                k = f64::consts::SQRT_2;
+            }
+            hw *= k;
+
+            let dx = hw * (ts.a * ts.a + ts.c * ts.c).sqrt();
+            let dy = hw * (ts.b * ts.b + ts.d * ts.d).sqrt();
+            Some(Rect::new(bbox.x - dx, bbox.y - dy,
+                           bbox.width + dx * 2.0, bbox.height + dy * 2.0))
+        }
+        None => Some(bbox),
+    }
+}
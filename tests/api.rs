@@ -5,6 +5,8 @@ use std::mem;
 
 use rustc_version::{Version, version_meta};
 
+use usvg::prelude::*;
+
 #[test]
 fn node_kind_size_1() {
     let size = if version_meta().unwrap().semver == Version::parse("1.22.0").unwrap() {
@@ -15,3 +17,2088 @@ fn node_kind_size_1() {
     };
     assert!(mem::size_of::<usvg::NodeKind>() <= size);
 }
+
+fn create_tree(width: f64, height: f64) -> usvg::Tree {
+    usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(width, height),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, width, height),
+            aspect: usvg::AspectRatio::default(),
+        },
+    })
+}
+
+fn rect_segments(x: f64, y: f64, w: f64, h: f64) -> Vec<usvg::PathSegment> {
+    vec![
+        usvg::PathSegment::MoveTo { x, y },
+        usvg::PathSegment::LineTo { x: x + w, y },
+        usvg::PathSegment::LineTo { x: x + w, y: y + h },
+        usvg::PathSegment::LineTo { x, y: y + h },
+        usvg::PathSegment::ClosePath,
+    ]
+}
+
+/// Returns the maximum distance between two paths, checked in both directions.
+fn max_deviation(a: &[usvg::PathSegment], b: &[usvg::PathSegment]) -> f64 {
+    let points = |segments: &[usvg::PathSegment]| {
+        let mut points = Vec::new();
+        for seg in usvg::path_utils::flatten(segments, 0.001) {
+            match seg {
+                  usvg::PathSegment::MoveTo { x, y }
+                | usvg::PathSegment::LineTo { x, y } => points.push(usvg::Point::new(x, y)),
+                _ => {}
+            }
+        }
+        points
+    };
+
+    let one_sided = |a: &[usvg::Point], b: &[usvg::Point]| {
+        a.iter().fold(0.0, |max: f64, p| {
+            let min = b.windows(2).fold(std::f64::INFINITY, |min: f64, e| {
+                let (dx, dy) = (e[1].x - e[0].x, e[1].y - e[0].y);
+                let len2 = dx * dx + dy * dy;
+                let t = if len2 > 0.0 { ((p.x - e[0].x) * dx + (p.y - e[0].y) * dy) / len2 } else { 0.0 };
+                let t = t.max(0.0).min(1.0);
+                let (x, y) = (e[0].x + dx * t - p.x, e[0].y + dy * t - p.y);
+                min.min((x * x + y * y).sqrt())
+            });
+            max.max(min)
+        })
+    };
+
+    let (a, b) = (points(a), points(b));
+    one_sided(&a, &b).max(one_sided(&b, &a))
+}
+
+fn path_area(segments: &[usvg::PathSegment]) -> f64 {
+    let mut area = 0.0;
+    let mut points = Vec::new();
+    for seg in segments {
+        match *seg {
+            usvg::PathSegment::MoveTo { x, y } => points = vec![(x, y)],
+            usvg::PathSegment::LineTo { x, y } => points.push((x, y)),
+            usvg::PathSegment::ClosePath => {
+                for i in 0..points.len() {
+                    let (x1, y1) = points[i];
+                    let (x2, y2) = points[(i + 1) % points.len()];
+                    area += (x1 * y2 - x2 * y1) / 2.0;
+                }
+            }
+            _ => panic!("unexpected curve"),
+        }
+    }
+
+    area
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+fn assert_rect(r: usvg::Rect, x: f64, y: f64, w: f64, h: f64) {
+    assert!(approx_eq(r.x, x) && approx_eq(r.y, y) && approx_eq(r.width, w) && approx_eq(r.height, h),
+            "{:?} != Rect({} {} {} {})", r, x, y, w, h);
+}
+
+fn assert_ts(ts1: &usvg::Transform, ts2: &usvg::Transform) {
+    assert!(approx_eq(ts1.a, ts2.a) && approx_eq(ts1.b, ts2.b) && approx_eq(ts1.c, ts2.c) &&
+            approx_eq(ts1.d, ts2.d) && approx_eq(ts1.e, ts2.e) && approx_eq(ts1.f, ts2.f),
+            "{:?} != {:?}", ts1, ts2);
+}
+
+#[test]
+fn bbox_with_rotation() {
+    let tree = create_tree(100.0, 100.0);
+    let path = tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        // rotate(90)
+        transform: usvg::Transform::new(0.0, 1.0, -1.0, 0.0, 0.0, 0.0),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(10.0, 0.0, 20.0, 10.0),
+    }));
+
+    assert_rect(path.calculate_bbox(false).unwrap(), -10.0, 10.0, 10.0, 20.0);
+}
+
+#[test]
+fn bbox_with_stroke() {
+    let tree = create_tree(100.0, 100.0);
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0),
+        opacity: None,
+        clip_path: None,
+        mask: None,
+    }));
+
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: None,
+        stroke: Some(usvg::Stroke {
+            width: 2.0,
+            linejoin: usvg::LineJoin::Round,
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(10.0, 10.0, 20.0, 10.0),
+    }));
+
+    assert_rect(g.calculate_bbox(false).unwrap(), 20.0, 20.0, 40.0, 20.0);
+    assert_rect(g.calculate_bbox(true).unwrap(), 18.0, 18.0, 44.0, 24.0);
+    assert!(tree.defs().calculate_bbox(true).is_none());
+}
+
+#[test]
+fn canvas_transform() {
+    let tree = usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(200.0, 200.0),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, 100.0, 100.0),
+            aspect: usvg::AspectRatio::default(),
+        },
+    });
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 10.0, 20.0),
+        opacity: None,
+        clip_path: None,
+        mask: None,
+    }));
+
+    let path = g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        // rotate(90)
+        transform: usvg::Transform::new(0.0, 1.0, -1.0, 0.0, 0.0, 0.0),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 5.0),
+    }));
+
+    assert_ts(&path.abs_transform(), &usvg::Transform::new(0.0, 1.0, -1.0, 0.0, 10.0, 20.0));
+    assert_ts(&path.canvas_transform(), &usvg::Transform::new(0.0, 2.0, -2.0, 0.0, 20.0, 40.0));
+
+    let p = path.map_point_to_canvas(usvg::Point::new(10.0, 0.0));
+    assert!(approx_eq(p.x, 20.0) && approx_eq(p.y, 60.0));
+
+    let p = path.map_point_from_canvas(p).unwrap();
+    assert!(approx_eq(p.x, 10.0) && approx_eq(p.y, 0.0));
+
+    assert_rect(path.map_rect_to_canvas(usvg::Rect::new(0.0, 0.0, 10.0, 5.0)),
+                10.0, 40.0, 10.0, 20.0);
+    assert_rect(path.calculate_canvas_bbox(false).unwrap(), 10.0, 40.0, 10.0, 20.0);
+}
+
+#[test]
+fn rect_transform_with_rotation() {
+    // rotate(45)
+    let (sin, cos) = 45f64.to_radians().sin_cos();
+    let ts = usvg::Transform::new(cos, sin, -sin, cos, 0.0, 0.0);
+    let r = usvg::Rect::new(0.0, 0.0, 10.0, 10.0).transform(ts);
+    let d = 10.0 * cos;
+    assert_rect(r, -d, 0.0, d * 2.0, d * 2.0);
+}
+
+#[test]
+fn transform_inverse() {
+    let ts = usvg::Transform::new(2.0, 0.5, -1.0, 3.0, 10.0, 20.0);
+    let mut ts2 = ts;
+    ts2.append(&ts.inverse().unwrap());
+    assert_ts(&ts2, &usvg::Transform::default());
+
+    assert!(usvg::Transform::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0).inverse().is_none());
+}
+
+#[test]
+fn transform_decompose() {
+    let transforms = [
+        usvg::Transform::new(2.0, 0.5, -1.0, 3.0, 10.0, 20.0),
+        usvg::Transform::new(-2.0, 0.0, 0.0, 3.0, 0.0, 0.0),
+        usvg::Transform::new(0.0, 1.0, -1.0, 0.0, 5.0, 0.0),
+    ];
+
+    for ts in &transforms {
+        let d = ts.decompose().unwrap();
+        assert_ts(&d.to_transform(), ts);
+    }
+
+    let d = transforms[2].decompose().unwrap();
+    assert!(approx_eq(d.angle, 90.0));
+    assert!(approx_eq(d.skew_x, 0.0));
+    assert!(approx_eq(d.scale_x, 1.0) && approx_eq(d.scale_y, 1.0));
+
+    let v = transforms[2].map_vector(usvg::Point::new(1.0, 0.0));
+    assert!(approx_eq(v.x, 0.0) && approx_eq(v.y, 1.0));
+}
+
+#[test]
+fn write_with_precision() {
+    let tree = create_tree(10.0, 10.0);
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 0.123456, 0.0),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: vec![
+            usvg::PathSegment::MoveTo { x: 0.1 + 0.2, y: 1.0 / 3.0 },
+            usvg::PathSegment::LineTo { x: -0.5, y: 2.0 },
+            usvg::PathSegment::ClosePath,
+        ],
+    }));
+
+    let opt = usvg::WriteOptions {
+        indent: usvg::Indent::None,
+        coordinates_precision: 2,
+        transforms_precision: 3,
+        .. usvg::WriteOptions::default()
+    };
+
+    assert_eq!(tree.to_string(&opt),
+        format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\" \
+                 viewBox=\"0 0 10 10\" preserveAspectRatio=\"xMidYMid\" \
+                 xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                 xmlns:usvg=\"https://github.com/RazrFalcon/usvg\" usvg:version=\"{}\">\
+                 <defs/>\
+                 <path fill=\"#000000\" fill-opacity=\"1\" fill-rule=\"nonzero\" stroke=\"none\" \
+                 transform=\"matrix(1 0 0 1 0.123 0)\" d=\"M 0.3 0.33 L -0.5 2 Z\"/>\
+                 </svg>", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn write_compact() {
+    let tree = create_tree(10.0, 10.0);
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: Some(usvg::Stroke {
+            width: 2.0,
+            .. usvg::Stroke::default()
+        }),
+        segments: vec![
+            usvg::PathSegment::MoveTo { x: 10.0, y: 20.0 },
+            usvg::PathSegment::LineTo { x: 30.0, y: -0.5 },
+            usvg::PathSegment::LineTo { x: 0.5, y: 0.25 },
+            usvg::PathSegment::ClosePath,
+            usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+            usvg::PathSegment::MoveTo { x: 1.0, y: 1.0 },
+        ],
+    }));
+
+    let opt = usvg::WriteOptions {
+        indent: usvg::Indent::Spaces(1),
+        compact_path: true,
+        skip_default_attributes: true,
+        .. usvg::WriteOptions::default()
+    };
+
+    assert_eq!(tree.to_string(&opt),
+        format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\" \
+                 viewBox=\"0 0 10 10\" \
+                 xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+                 xmlns:usvg=\"https://github.com/RazrFalcon/usvg\" usvg:version=\"{}\">\n \
+                 <defs/>\n \
+                 <path id=\"path1\" stroke=\"#000000\" stroke-width=\"2\" \
+                 d=\"M10 20L30-.5.5.25ZM0 0M1 1\"/>\n\
+                 </svg>\n", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn extract_node() {
+    let mut tree = create_tree(100.0, 100.0);
+
+    let grad = |id: &str| usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+        id: id.to_string(),
+        x1: 0.0,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 0.0,
+        base: usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            stops: Vec::new(),
+        },
+    });
+
+    tree.append_to_defs(grad("lg1"));
+    tree.append_to_defs(grad("lg2"));
+    let mut clip = tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip1".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+    }));
+    clip.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg2".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 5.0, 5.0),
+    }));
+
+    let mut g1 = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 50.0, 0.0),
+        opacity: None,
+        clip_path: None,
+        mask: None,
+    }));
+    let mut g2 = g1.append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "icon".to_string(),
+        transform: usvg::Transform::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0),
+        opacity: None,
+        clip_path: Some("clip1".to_string()),
+        mask: None,
+    }));
+    g2.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: None,
+        stroke: Some(usvg::Stroke {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            width: 2.0,
+            linejoin: usvg::LineJoin::Round,
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(10.0, 10.0, 10.0, 10.0),
+    }));
+
+    assert!(tree.extract("missing").is_none());
+
+    let new_tree = tree.extract("icon").unwrap();
+
+    let svg = new_tree.svg_node();
+    assert_rect(svg.view_box.rect, 68.0, 18.0, 24.0, 24.0);
+    assert!(approx_eq(svg.size.width, 24.0) && approx_eq(svg.size.height, 24.0));
+
+    let defs: Vec<String> = new_tree.defs().children().map(|n| n.id().to_string()).collect();
+    assert_eq!(defs, vec!["lg1", "lg2", "clip1"]);
+    assert_eq!(new_tree.defs_by_id("clip1").unwrap().children().count(), 1);
+
+    let icon = new_tree.node_by_id("icon").unwrap();
+    assert_ts(&icon.parent().unwrap().transform(),
+              &usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 50.0, 0.0));
+    assert_eq!(icon.children().count(), 1);
+
+    // The original tree is not affected.
+    assert_eq!(tree.root().descendants().count(), 9);
+}
+
+#[test]
+fn import_tree() {
+    fn create_badge(size: f64) -> usvg::Tree {
+        let mut tree = create_tree(size, size);
+        {
+            let mut root = tree.root();
+            let mut kind = root.borrow_mut();
+            if let usvg::NodeKind::Svg(ref mut svg) = *kind {
+                svg.view_box.rect = usvg::Rect::new(0.0, 0.0, 1.0, 1.0);
+            }
+        }
+
+        for id in &["lg1", "lg1-1"] {
+            tree.append_to_defs(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+                id: id.to_string(),
+                x1: 0.0,
+                y1: 0.0,
+                x2: 1.0,
+                y2: 0.0,
+                base: usvg::BaseGradient {
+                    units: usvg::Units::ObjectBoundingBox,
+                    transform: usvg::Transform::default(),
+                    spread_method: usvg::SpreadMethod::Pad,
+                    stops: Vec::new(),
+                },
+            }));
+        }
+
+        let mut clip = tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+            id: "clip1".to_string(),
+            units: usvg::Units::UserSpaceOnUse,
+            transform: usvg::Transform::default(),
+        }));
+        clip.append_kind(usvg::NodeKind::Path(usvg::Path {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill {
+                paint: usvg::Paint::Link("lg1".to_string()),
+                .. usvg::Fill::default()
+            }),
+            stroke: None,
+            segments: rect_segments(0.0, 0.0, 1.0, 1.0),
+        }));
+
+        let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            opacity: None,
+            clip_path: Some("clip1".to_string()),
+            mask: None,
+        }));
+        g.append_kind(usvg::NodeKind::Path(usvg::Path {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill {
+                paint: usvg::Paint::Link("lg1-1".to_string()),
+                .. usvg::Fill::default()
+            }),
+            stroke: None,
+            segments: rect_segments(0.0, 0.0, 1.0, 1.0),
+        }));
+
+        tree
+    }
+
+    let mut tree = create_badge(100.0);
+    let badge = create_badge(10.0);
+
+    let mut root = tree.root();
+    let g = tree.import(&badge, &mut root, usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 20.0, 30.0));
+
+    // Scale by the badge `viewBox` and translate by the specified transform.
+    assert_ts(&g.transform(), &usvg::Transform::new(10.0, 0.0, 0.0, 10.0, 20.0, 30.0));
+
+    let defs: Vec<String> = tree.defs().children().map(|n| n.id().to_string()).collect();
+    assert_eq!(defs, vec!["lg1", "lg1-1", "clip1", "lg1-2", "lg1-1-1", "clip1-1"]);
+
+    let clip_path = tree.defs_by_id("clip1-1").unwrap().first_child().unwrap();
+    match *clip_path.borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            match path.fill.as_ref().unwrap().paint {
+                usvg::Paint::Link(ref id) => assert_eq!(id, "lg1-2"),
+                _ => panic!("invalid paint"),
+            }
+        }
+        _ => panic!("invalid node"),
+    }
+
+    let inner_g = g.first_child().unwrap();
+    match *inner_g.borrow() {
+        usvg::NodeKind::Group(ref g) => assert_eq!(g.clip_path, Some("clip1-1".to_string())),
+        _ => panic!("invalid node"),
+    }
+
+    match *inner_g.first_child().unwrap().borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            match path.fill.as_ref().unwrap().paint {
+                usvg::Paint::Link(ref id) => assert_eq!(id, "lg1-1-1"),
+                _ => panic!("invalid paint"),
+            }
+        }
+        _ => panic!("invalid node"),
+    };
+}
+
+#[test]
+fn users_of() {
+    let mut tree = create_tree(100.0, 100.0);
+
+    tree.append_to_defs(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+        id: "lg1".to_string(),
+        x1: 0.0,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 0.0,
+        base: usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            stops: Vec::new(),
+        },
+    }));
+    let mut mask = tree.append_to_defs(usvg::NodeKind::Mask(usvg::Mask {
+        id: "mask1".to_string(),
+        units: usvg::Units::ObjectBoundingBox,
+        content_units: usvg::Units::UserSpaceOnUse,
+        rect: usvg::Rect::new(-0.1, -0.1, 1.2, 1.2),
+    }));
+    mask.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g1".to_string(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: None,
+        mask: Some("mask1".to_string()),
+    }));
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path2".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: Some(usvg::Stroke {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+
+    let ids = |nodes: &[usvg::Node]| -> Vec<String> {
+        nodes.iter().map(|n| n.id().to_string()).collect()
+    };
+
+    assert_eq!(ids(&tree.users_of("lg1")), vec!["path1", "path2"]);
+    assert_eq!(ids(&tree.users_of("mask1")), vec!["g1"]);
+    assert!(tree.users_of("missing").is_empty());
+
+    let index = tree.reference_index();
+    assert_eq!(ids(index.users_of("lg1")), vec!["path1", "path2"]);
+    assert_eq!(ids(index.users_of("mask1")), vec!["g1"]);
+    assert!(index.is_used("mask1"));
+    assert!(!index.is_used("missing"));
+    assert!(index.users_of("missing").is_empty());
+
+    // The index is cached until the tree is modified.
+    assert!(std::rc::Rc::ptr_eq(&index, &tree.reference_index()));
+
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path3".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+    assert_eq!(ids(&tree.users_of("lg1")), vec!["path1", "path2"]);
+    tree.invalidate_reference_index();
+    assert_eq!(ids(&tree.users_of("lg1")), vec!["path1", "path2", "path3"]);
+
+    tree.outline_strokes(0.1);
+    assert!(!std::rc::Rc::ptr_eq(&index, &tree.reference_index()));
+    assert_eq!(ids(&tree.users_of("lg1")), vec!["path1", "path2", "", "path3"]);
+}
+
+#[test]
+fn validate() {
+    let mut tree = create_tree(100.0, 100.0);
+    assert!(tree.validate().is_empty());
+
+    tree.append_to_defs(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+        id: "lg1".to_string(),
+        x1: 0.0,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 0.0,
+        base: usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            stops: vec![
+                usvg::Stop { offset: 0.0.into(), color: usvg::Color::new(0, 0, 0), opacity: 1.0.into() },
+                usvg::Stop { offset: 1.0.into(), color: usvg::Color::new(0, 0, 0), opacity: 1.0.into() },
+            ],
+        },
+    }));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g1".to_string(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("lg1".to_string()),
+        mask: Some("mask1".to_string()),
+    }));
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: Some(usvg::Stroke {
+            width: 0.0,
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+    tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g2".to_string(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: None,
+        mask: None,
+    }));
+    tree.root().append_kind(usvg::NodeKind::Defs);
+
+    let issues: Vec<(String, usvg::ValidationIssueKind)> = tree.validate().iter()
+        .map(|i| (i.node.id().to_string(), i.kind.clone()))
+        .collect();
+
+    assert_eq!(issues, vec![
+        ("g1".to_string(), usvg::ValidationIssueKind::InvalidLinkType("lg1".to_string())),
+        ("g1".to_string(), usvg::ValidationIssueKind::BrokenLink("mask1".to_string())),
+        ("path1".to_string(), usvg::ValidationIssueKind::InvalidStrokeWidth),
+        ("g2".to_string(), usvg::ValidationIssueKind::EmptyGroup),
+        ("".to_string(), usvg::ValidationIssueKind::MisplacedNode),
+    ]);
+}
+
+#[test]
+fn validate_geometry() {
+    let mut tree = create_tree(100.0, 100.0);
+
+    let path = |id: &str, segments: Vec<usvg::PathSegment>| {
+        usvg::NodeKind::Path(usvg::Path {
+            id: id.to_string(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill::default()),
+            stroke: None,
+            segments,
+        })
+    };
+    let shape = |id: &str, kind: usvg::ShapeKind| {
+        usvg::NodeKind::Shape(Box::new(usvg::Shape {
+            id: id.to_string(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill::default()),
+            stroke: None,
+            kind,
+        }))
+    };
+
+    tree.root().append_kind(path("path1", Vec::new()));
+    tree.root().append_kind(path("path2", vec![
+        usvg::PathSegment::LineTo { x: 10.0, y: 10.0 },
+    ]));
+    tree.root().append_kind(path("path3", vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::QuadTo { x1: 5.0, y1: 0.0, x: 10.0, y: 10.0 },
+    ]));
+    tree.root().append_kind(shape("polyline1", usvg::ShapeKind::Polyline {
+        points: vec![usvg::Point::new(0.0, 0.0)],
+        closed: false,
+    }));
+    tree.root().append_kind(shape("rect1", usvg::ShapeKind::Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 10.0,
+        height: 10.0,
+        rx: 6.0,
+        ry: 5.0,
+    }));
+    tree.root().append_kind(usvg::NodeKind::Text(usvg::Text {
+        id: "text1".to_string(),
+        transform: usvg::Transform::default(),
+        rotate: None,
+        chunks: Vec::new(),
+    }));
+
+    let issues: Vec<(String, usvg::ValidationIssueKind)> = tree.validate().iter()
+        .map(|i| (i.node.id().to_string(), i.kind.clone()))
+        .collect();
+
+    assert_eq!(issues, vec![
+        ("path1".to_string(), usvg::ValidationIssueKind::EmptyPath),
+        ("path2".to_string(), usvg::ValidationIssueKind::MissingMoveTo),
+        ("path3".to_string(), usvg::ValidationIssueKind::UnsupportedSegment),
+        ("polyline1".to_string(), usvg::ValidationIssueKind::NotEnoughPoints),
+        ("rect1".to_string(), usvg::ValidationIssueKind::InvalidRadius),
+        ("text1".to_string(), usvg::ValidationIssueKind::EmptyText),
+    ]);
+
+    // `QuadTo` is allowed by the extended profile.
+    tree.set_path_profile(usvg::PathProfile::Extended);
+    assert_eq!(tree.path_profile(), usvg::PathProfile::Extended);
+    let issues = tree.validate();
+    assert!(!issues.iter().any(|i| i.kind == usvg::ValidationIssueKind::UnsupportedSegment));
+}
+
+#[test]
+fn remove_unused_defs() {
+    let mut tree = create_tree(100.0, 100.0);
+
+    for id in &["lg1", "lg2", "lg3"] {
+        tree.append_to_defs(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+            id: id.to_string(),
+            x1: 0.0,
+            y1: 0.0,
+            x2: 1.0,
+            y2: 0.0,
+            base: usvg::BaseGradient {
+                units: usvg::Units::ObjectBoundingBox,
+                transform: usvg::Transform::default(),
+                spread_method: usvg::SpreadMethod::Pad,
+                stops: Vec::new(),
+            },
+        }));
+    }
+
+    let path = |paint: &str| usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link(paint.to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    });
+
+    // Used by a path inside a used pattern.
+    let mut pattern = tree.append_to_defs(usvg::NodeKind::Pattern(usvg::Pattern {
+        id: "patt1".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        content_units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+        rect: usvg::Rect::new(0.0, 0.0, 10.0, 10.0),
+        view_box: None,
+    }));
+    pattern.append_kind(path("lg1"));
+
+    // Used only by an unused clip path.
+    let mut clip = tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip1".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+    }));
+    clip.append_kind(path("lg2"));
+
+    // Used by a group inside a used mask.
+    let mut clip = tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip2".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+    }));
+    clip.append_kind(path("lg3"));
+
+    let mut mask = tree.append_to_defs(usvg::NodeKind::Mask(usvg::Mask {
+        id: "mask1".to_string(),
+        units: usvg::Units::ObjectBoundingBox,
+        content_units: usvg::Units::UserSpaceOnUse,
+        rect: usvg::Rect::new(-0.1, -0.1, 1.2, 1.2),
+    }));
+    let mut g = mask.append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("clip2".to_string()),
+        mask: None,
+    }));
+    g.append_kind(path("patt1"));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: None,
+        mask: Some("mask1".to_string()),
+    }));
+    g.append_kind(path("patt1"));
+
+    tree.remove_unused_defs();
+
+    let defs: Vec<String> = tree.defs().children().map(|n| n.id().to_string()).collect();
+    assert_eq!(defs, vec!["lg1", "lg3", "patt1", "clip2", "mask1"]);
+}
+
+#[test]
+fn nodes_at_point() {
+    let tree = usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(200.0, 200.0),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, 100.0, 100.0),
+            aspect: usvg::AspectRatio::default(),
+        },
+    });
+
+    // A ring.
+    let mut segments = rect_segments(0.0, 0.0, 40.0, 40.0);
+    segments.extend_from_slice(&rect_segments(10.0, 10.0, 20.0, 20.0));
+    let ring = tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "ring".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            rule: usvg::FillRule::EvenOdd,
+            .. usvg::Fill::default()
+        }),
+        stroke: None,
+        segments,
+    }));
+
+    // An open stroked line.
+    let line = tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "line".to_string(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 38.0),
+        fill: None,
+        stroke: Some(usvg::Stroke {
+            width: 4.0,
+            .. usvg::Stroke::default()
+        }),
+        segments: vec![
+            usvg::PathSegment::MoveTo { x: 5.0, y: 0.0 },
+            usvg::PathSegment::LineTo { x: 60.0, y: 0.0 },
+        ],
+    }));
+
+    let mut tree = tree;
+    tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip1".to_string(),
+        units: usvg::Units::ObjectBoundingBox,
+        transform: usvg::Transform::default(),
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 0.5, 1.0),
+    }));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 50.0, 0.0),
+        opacity: None,
+        clip_path: Some("clip1".to_string()),
+        mask: None,
+    }));
+    let clipped = g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "clipped".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 40.0, 40.0),
+    }));
+
+    // Coordinates are in the canvas, which is scaled by 2.
+    assert!(tree.nodes_at_point(10.0, 10.0) == vec![ring.clone()]);
+    assert!(tree.nodes_at_point(40.0, 40.0).is_empty());
+    assert!(tree.nodes_at_point(20.0, 76.0) == vec![line.clone(), ring.clone()]);
+    assert!(tree.nodes_at_point(8.0, 76.0) == vec![ring.clone()]);
+    assert!(tree.nodes_at_point(118.0, 76.0) == vec![clipped.clone(), line.clone()]);
+    assert!(tree.nodes_at_point(122.0, 76.0) == vec![clipped.clone()]);
+    assert!(tree.nodes_at_point(110.0, 60.0) == vec![clipped.clone()]);
+    assert!(tree.nodes_at_point(150.0, 60.0).is_empty());
+}
+
+#[test]
+fn nodes_at_point_stroke_joins() {
+    let is_stroked = |stroke: usvg::Stroke, x: f64, y: f64| {
+        let tree = create_tree(100.0, 100.0);
+        tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            fill: None,
+            stroke: Some(stroke),
+            segments: vec![
+                usvg::PathSegment::MoveTo { x: 10.0, y: 50.0 },
+                usvg::PathSegment::LineTo { x: 50.0, y: 50.0 },
+                usvg::PathSegment::LineTo { x: 50.0, y: 90.0 },
+            ],
+        }));
+
+        !tree.nodes_at_point(x, y).is_empty()
+    };
+
+    let mut stroke = usvg::Stroke {
+        width: 10.0,
+        linejoin: usvg::LineJoin::Miter,
+        .. usvg::Stroke::default()
+    };
+
+    // The outer corner of the join is at (55, 45).
+    assert!(is_stroked(stroke.clone(), 54.0, 46.0));
+    assert!(is_stroked(stroke.clone(), 51.5, 47.5));
+    assert!(!is_stroked(stroke.clone(), 56.0, 44.0));
+
+    // A right angle requires the miter limit of at least `sqrt(2)`.
+    stroke.miterlimit = 1.2;
+    assert!(!is_stroked(stroke.clone(), 54.0, 46.0));
+
+    stroke.linejoin = usvg::LineJoin::Bevel;
+    stroke.miterlimit = 4.0;
+    assert!(!is_stroked(stroke.clone(), 54.0, 46.0));
+    assert!(is_stroked(stroke.clone(), 51.5, 47.5));
+
+    stroke.linejoin = usvg::LineJoin::Round;
+    assert!(!is_stroked(stroke.clone(), 54.0, 46.0));
+    assert!(is_stroked(stroke.clone(), 53.0, 47.0));
+
+    // Dashes are taken into account too.
+    stroke.dasharray = Some(usvg::NumberList(vec![10.0, 10.0]));
+    assert!(is_stroked(stroke.clone(), 15.0, 50.0));
+    assert!(!is_stroked(stroke.clone(), 25.0, 50.0));
+}
+
+#[test]
+fn path_measurement() {
+    use usvg::path_utils;
+
+    let mut segments = rect_segments(0.0, 0.0, 32.0, 10.0);
+    segments.extend_from_slice(&[
+        usvg::PathSegment::MoveTo { x: 0.0, y: 20.0 },
+        // A half of the circle with a 10 radius.
+        usvg::PathSegment::CurveTo { x1: 0.0, y1: 25.5228, x2: 4.4772, y2: 30.0, x: 10.0, y: 30.0 },
+        usvg::PathSegment::CurveTo { x1: 15.5228, y1: 30.0, x2: 20.0, y2: 25.5228, x: 20.0, y: 20.0 },
+    ]);
+
+    let lengths = path_utils::subpath_lengths(&segments);
+    assert_eq!(lengths.len(), 2);
+    assert!(approx_eq(lengths[0], 84.0));
+    assert!((lengths[1] - 10.0 * std::f64::consts::PI).abs() < 0.01);
+    assert!(approx_eq(path_utils::length(&segments), lengths[0] + lengths[1]));
+
+    let p = path_utils::point_at_length(&segments, 37.0).unwrap();
+    assert!(approx_eq(p.x, 32.0) && approx_eq(p.y, 5.0));
+    assert!(approx_eq(path_utils::angle_at_length(&segments, 37.0).unwrap(), 90.0));
+    // On the ClosePath segment.
+    assert!(approx_eq(path_utils::angle_at_length(&segments, 79.0).unwrap(), -90.0));
+
+    let p = path_utils::point_at_length(&segments, 84.0 + lengths[1] / 2.0).unwrap();
+    assert!((p.x - 10.0).abs() < 0.01 && (p.y - 30.0).abs() < 0.01);
+    assert!(path_utils::angle_at_length(&segments, 84.0 + lengths[1] / 2.0).unwrap().abs() < 0.1);
+
+    let (first, second) = path_utils::split_at_length(&segments, 44.0);
+    assert_eq!(first, vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 32.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 32.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 30.0, y: 10.0 },
+    ]);
+    assert_eq!(second[0..4].to_vec(), vec![
+        usvg::PathSegment::MoveTo { x: 30.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::MoveTo { x: 0.0, y: 20.0 },
+    ]);
+    assert!(approx_eq(path_utils::length(&first) + path_utils::length(&second),
+                      path_utils::length(&segments)));
+
+    let (first, second) = path_utils::split_at_length(&segments, 1000.0);
+    assert_eq!(first, segments);
+    assert!(second.is_empty());
+}
+
+#[test]
+fn flatten_paths() {
+    // A circle with a 20 radius.
+    fn circle_segments() -> Vec<usvg::PathSegment> {
+        let k = 20.0 * 0.5522847;
+        vec![
+            usvg::PathSegment::MoveTo { x: 70.0, y: 50.0 },
+            usvg::PathSegment::CurveTo { x1: 70.0, y1: 50.0 + k, x2: 50.0 + k, y2: 70.0, x: 50.0, y: 70.0 },
+            usvg::PathSegment::CurveTo { x1: 50.0 - k, y1: 70.0, x2: 30.0, y2: 50.0 + k, x: 30.0, y: 50.0 },
+            usvg::PathSegment::CurveTo { x1: 30.0, y1: 50.0 - k, x2: 50.0 - k, y2: 30.0, x: 50.0, y: 30.0 },
+            usvg::PathSegment::CurveTo { x1: 50.0 + k, y1: 30.0, x2: 70.0, y2: 50.0 - k, x: 70.0, y: 50.0 },
+            usvg::PathSegment::ClosePath,
+        ]
+    }
+
+    let mut tree = create_tree(100.0, 100.0);
+
+    tree.append_to_defs(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip1".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: circle_segments(),
+    }));
+
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: circle_segments(),
+    }));
+
+    tree.flatten_paths(0.01);
+
+    let mut paths = 0;
+    for node in tree.root().descendants() {
+        if let usvg::NodeKind::Path(ref path) = *node.borrow() {
+            paths += 1;
+
+            for seg in &path.segments {
+                match *seg {
+                    usvg::PathSegment::CurveTo { .. } => panic!("a curve is not flattened"),
+                    usvg::PathSegment::LineTo { x, y } => {
+                        let d = ((x - 50.0).powi(2) + (y - 50.0).powi(2)).sqrt();
+                        assert!((d - 20.0).abs() < 0.01);
+                    }
+                    _ => {}
+                }
+            }
+
+            let len = usvg::path_utils::length(&path.segments);
+            assert!((len - 2.0 * std::f64::consts::PI * 20.0).abs() < 0.05);
+        }
+    }
+
+    assert_eq!(paths, 2);
+}
+
+#[test]
+fn stroke_to_path() {
+    use usvg::path_utils;
+
+    let line = vec![
+        usvg::PathSegment::MoveTo { x: 10.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 50.0, y: 10.0 },
+    ];
+
+    let mut stroke = usvg::Stroke {
+        width: 10.0,
+        .. usvg::Stroke::default()
+    };
+    assert!(approx_eq(path_area(&path_utils::stroke_to_path(&line, &stroke, 0.01)), 400.0));
+
+    stroke.linecap = usvg::LineCap::Square;
+    assert!(approx_eq(path_area(&path_utils::stroke_to_path(&line, &stroke, 0.01)), 500.0));
+
+    stroke.linecap = usvg::LineCap::Round;
+    let a = path_area(&path_utils::stroke_to_path(&line, &stroke, 0.01));
+    assert!((a - (400.0 + std::f64::consts::PI * 25.0)).abs() < 0.5);
+
+    // A ring with miter joins.
+    //
+    // Inner joins are self-intersecting, so we have to check the filling itself.
+    let is_filled = |segments: Vec<usvg::PathSegment>, x: f64, y: f64| {
+        let tree = create_tree(100.0, 100.0);
+        tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill::default()),
+            stroke: None,
+            segments,
+        }));
+
+        !tree.nodes_at_point(x, y).is_empty()
+    };
+
+    stroke.linecap = usvg::LineCap::Butt;
+    let rect = rect_segments(0.0, 0.0, 40.0, 40.0);
+    let path = path_utils::stroke_to_path(&rect, &stroke, 0.01);
+    assert!(!is_filled(path.clone(), 20.0, 20.0));
+    assert!(!is_filled(path.clone(), -6.0, 20.0));
+    assert!(is_filled(path.clone(), 0.0, 20.0));
+    assert!(is_filled(path.clone(), 37.0, 3.0));
+    assert!(is_filled(path.clone(), -4.0, -4.0));
+
+    // Bevel joins cut the corners.
+    stroke.linejoin = usvg::LineJoin::Bevel;
+    let path = path_utils::stroke_to_path(&rect, &stroke, 0.01);
+    assert!(!is_filled(path.clone(), -4.0, -4.0));
+    assert!(is_filled(path.clone(), -1.0, -1.0));
+
+    // Miter joins are beveled when the miter limit is exceeded.
+    stroke.linejoin = usvg::LineJoin::Miter;
+    stroke.miterlimit = 1.0;
+    let path = path_utils::stroke_to_path(&rect, &stroke, 0.01);
+    assert!(!is_filled(path.clone(), -4.0, -4.0));
+
+    stroke.linejoin = usvg::LineJoin::Round;
+    let path = path_utils::stroke_to_path(&rect, &stroke, 0.01);
+    assert!(!is_filled(path.clone(), -4.0, -4.0));
+    assert!(is_filled(path.clone(), -3.0, -3.0));
+
+    let mut stroke = usvg::Stroke {
+        width: 2.0,
+        dasharray: Some(usvg::NumberList(vec![10.0, 10.0])),
+        dashoffset: 5.0,
+        .. usvg::Stroke::default()
+    };
+    let path = path_utils::stroke_to_path(&line, &stroke, 0.01);
+    let subpaths = path.iter().filter(|s| if let usvg::PathSegment::MoveTo { .. } = **s { true } else { false }).count();
+    assert_eq!(subpaths, 3);
+    assert!(approx_eq(path_area(&path), 40.0));
+
+    stroke.width = 0.0;
+    assert!(path_utils::stroke_to_path(&line, &stroke, 0.01).is_empty());
+}
+
+#[test]
+fn outline_strokes() {
+    let mut tree = create_tree(100.0, 100.0);
+    let path = tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: Some(usvg::Stroke {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(10.0, 10.0, 20.0, 20.0),
+    }));
+
+    tree.outline_strokes(0.1);
+
+    let children: Vec<_> = tree.root().children().skip(1).collect();
+    assert_eq!(children.len(), 2);
+    assert!(children[0] == path);
+
+    match *children[0].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert_eq!(path.id, "path1");
+            assert!(path.fill.is_some());
+            assert!(path.stroke.is_none());
+        }
+        _ => panic!("not a path"),
+    };
+
+    match *children[1].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert_eq!(path.id, "");
+            assert!(path.stroke.is_none());
+            match path.fill.as_ref().unwrap().paint {
+                usvg::Paint::Link(ref id) => assert_eq!(id, "lg1"),
+                _ => panic!("invalid paint"),
+            }
+        }
+        _ => panic!("not a path"),
+    };
+}
+
+#[test]
+fn expand_dashes() {
+    use usvg::path_utils;
+
+    let rect = rect_segments(0.0, 0.0, 20.0, 10.0);
+
+    // The first and the last dashes are merged.
+    let segments = path_utils::apply_dashes(&rect, &[25.0, 10.0], 0.0);
+    assert_eq!(segments, vec![
+        usvg::PathSegment::MoveTo { x: 15.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 5.0 },
+    ]);
+
+    let segments = path_utils::apply_dashes(&rect, &[25.0, 10.0], 5.0);
+    assert_eq!(segments, vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 0.0 },
+        usvg::PathSegment::MoveTo { x: 20.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 5.0 },
+    ]);
+
+    // A closed subpath without gaps is preserved.
+    assert_eq!(path_utils::apply_dashes(&rect, &[100.0, 10.0], 0.0), rect);
+
+    // An odd number of values is repeated, so the period is 10 and not 5.
+    let line = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 0.0 },
+    ];
+    assert_eq!(path_utils::apply_dashes(&line, &[5.0], 5.0), vec![
+        usvg::PathSegment::MoveTo { x: 5.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 0.0 },
+        usvg::PathSegment::MoveTo { x: 15.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 0.0 },
+    ]);
+
+    // A pattern that would produce too many dashes is ignored.
+    assert_eq!(path_utils::apply_dashes(&line, &[0.0, 1e-4], 0.0), line);
+
+    // A curve is split into two curves.
+    let curve = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::CurveTo { x1: 0.0, y1: 10.0, x2: 10.0, y2: 10.0, x: 10.0, y: 0.0 },
+    ];
+    let len = path_utils::length(&curve);
+    let segments = path_utils::apply_dashes(&curve, &[len / 3.0], 0.0);
+    assert_eq!(segments.len(), 4);
+    assert!(approx_eq(path_utils::length(&segments), len * 2.0 / 3.0));
+
+    let mut tree = create_tree(100.0, 100.0);
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: Some(usvg::Stroke {
+            dasharray: Some(usvg::NumberList(vec![5.0, 5.0])),
+            .. usvg::Stroke::default()
+        }),
+        segments: rect.clone(),
+    }));
+
+    tree.expand_dashes();
+
+    let children: Vec<_> = tree.root().children().skip(1).collect();
+    assert_eq!(children.len(), 2);
+
+    match *children[0].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert!(path.stroke.is_none());
+            assert_eq!(path.segments, rect);
+        }
+        _ => panic!("not a path"),
+    };
+
+    match *children[1].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert!(path.fill.is_none());
+            assert!(path.stroke.as_ref().unwrap().dasharray.is_none());
+            assert_eq!(path.segments.len(), 12);
+        }
+        _ => panic!("not a path"),
+    };
+}
+
+#[test]
+fn boolean() {
+    use usvg::path_utils::{self, BooleanOp};
+    use usvg::FillRule;
+
+    let a = rect_segments(0.0, 0.0, 20.0, 20.0);
+    let b = rect_segments(10.0, 10.0, 20.0, 20.0);
+
+    let area = |a: &[usvg::PathSegment], b: &[usvg::PathSegment], rule: FillRule, op: BooleanOp| {
+        path_area(&path_utils::boolean(a, rule, b, FillRule::NonZero, op, 0.1))
+    };
+
+    assert!(approx_eq(area(&a, &b, FillRule::NonZero, BooleanOp::Union), 700.0));
+    assert!(approx_eq(area(&a, &b, FillRule::NonZero, BooleanOp::Intersection), 100.0));
+    assert!(approx_eq(area(&a, &b, FillRule::NonZero, BooleanOp::Difference), 300.0));
+    assert!(approx_eq(area(&a, &b, FillRule::NonZero, BooleanOp::Xor), 600.0));
+
+    // Disjoint paths.
+    let c = rect_segments(50.0, 0.0, 10.0, 10.0);
+    assert!(path_utils::boolean(&a, FillRule::NonZero, &c, FillRule::NonZero,
+                                BooleanOp::Intersection, 0.1).is_empty());
+    assert!(approx_eq(area(&a, &c, FillRule::NonZero, BooleanOp::Union), 500.0));
+
+    // Coincident edges.
+    let d = rect_segments(0.0, 0.0, 10.0, 20.0);
+    assert!(approx_eq(area(&a, &d, FillRule::NonZero, BooleanOp::Union), 400.0));
+    assert!(approx_eq(area(&a, &d, FillRule::NonZero, BooleanOp::Difference), 200.0));
+
+    // A square with a hole, which is filled only with the `NonZero` rule.
+    let mut ring = rect_segments(0.0, 0.0, 30.0, 30.0);
+    ring.extend_from_slice(&rect_segments(10.0, 10.0, 10.0, 10.0));
+    let e = rect_segments(0.0, 0.0, 15.0, 30.0);
+    assert!(approx_eq(area(&ring, &e, FillRule::NonZero, BooleanOp::Intersection), 450.0));
+    assert!(approx_eq(area(&ring, &e, FillRule::EvenOdd, BooleanOp::Intersection), 400.0));
+    assert!(approx_eq(area(&ring, &e, FillRule::EvenOdd, BooleanOp::Union), 850.0));
+
+    // Subpaths with non-finite coordinates are ignored.
+    let mut f = rect_segments(0.0, 0.0, ::std::f64::NAN, 10.0);
+    f.extend_from_slice(&rect_segments(10.0, 10.0, 20.0, 20.0));
+    assert!(approx_eq(area(&a, &f, FillRule::NonZero, BooleanOp::Union), 700.0));
+}
+
+#[test]
+fn boolean_many_edges() {
+    use usvg::path_utils::{self, BooleanOp};
+    use usvg::FillRule;
+
+    // Two combs with 2000 teeth each, which overlap by half of the tooth height.
+    let comb = |y: f64, dir: f64| {
+        let mut segments = vec![usvg::PathSegment::MoveTo { x: 0.0, y }];
+        for i in 0..2000 {
+            let x = i as f64 * 2.0;
+            segments.push(usvg::PathSegment::LineTo { x, y: y + 10.0 * dir });
+            segments.push(usvg::PathSegment::LineTo { x: x + 1.0, y: y + 10.0 * dir });
+            segments.push(usvg::PathSegment::LineTo { x: x + 1.0, y });
+            segments.push(usvg::PathSegment::LineTo { x: x + 2.0, y });
+        }
+        segments.push(usvg::PathSegment::ClosePath);
+        segments
+    };
+
+    let a = comb(0.0, 1.0);
+    let b = comb(15.0, -1.0);
+
+    // The teeth of both combs are at the same positions,
+    // so the intersection consists of 2000 5x1 rectangles.
+    let intersection = path_utils::boolean(&a, FillRule::NonZero, &b, FillRule::NonZero,
+                                           BooleanOp::Intersection, 0.1);
+    assert!(approx_eq(path_area(&intersection), 10000.0));
+
+    let union = path_utils::boolean(&a, FillRule::NonZero, &b, FillRule::NonZero,
+                                    BooleanOp::Union, 0.1);
+    assert!(approx_eq(path_area(&union), 2.0 * 20000.0 - 10000.0));
+}
+
+#[test]
+fn apply_clip_paths() {
+    let mut tree = create_tree(100.0, 100.0);
+
+    tree.defs().append_kind(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip1".to_string(),
+        units: usvg::Units::ObjectBoundingBox,
+        transform: usvg::Transform::default(),
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 0.5, 1.0),
+    }));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g1".to_string(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("clip1".to_string()),
+        mask: None,
+    }));
+
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: Some(usvg::Stroke {
+            width: 2.0,
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(10.0, 10.0, 40.0, 20.0),
+    }));
+
+    // Outside of the clip path.
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path2".to_string(),
+        // translate(60 0)
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 60.0, 0.0),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 10.0, 10.0, 10.0),
+    }));
+
+    tree.apply_clip_paths(0.1);
+
+    match *g.borrow() {
+        usvg::NodeKind::Group(ref g) => assert!(g.clip_path.is_none()),
+        _ => panic!("not a group"),
+    };
+
+    // The bounding box is (10 10 60 20), so the clip region is (10 10 30 20).
+    let children: Vec<_> = g.children().collect();
+    assert_eq!(children.len(), 2);
+
+    match *children[0].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert_eq!(path.id, "path1");
+            assert!(path.stroke.is_none());
+            assert!(approx_eq(path_area(&path.segments), 600.0));
+        }
+        _ => panic!("not a path"),
+    };
+
+    // The stroke outline is clipped too.
+    match *children[1].borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert!(path.stroke.is_none());
+            assert!(path.fill.is_some());
+            assert!(approx_eq(path_area(&path.segments), 30.0 + 30.0 + 18.0));
+        }
+        _ => panic!("not a path"),
+    };
+
+    assert_eq!(g.children().count(), 2);
+
+    // A group that was clipped out completely is removed.
+    tree.defs().append_kind(usvg::NodeKind::ClipPath(usvg::ClipPath {
+        id: "clip2".to_string(),
+        units: usvg::Units::UserSpaceOnUse,
+        transform: usvg::Transform::default(),
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+
+    let mut g2 = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g2".to_string(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("clip2".to_string()),
+        mask: None,
+    }));
+    g2.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path3".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(50.0, 50.0, 10.0, 10.0),
+    }));
+
+    tree.apply_clip_paths(0.1);
+    assert!(g2.parent().is_none());
+    assert!(tree.validate().is_empty());
+}
+
+#[test]
+fn normalize_paths() {
+    use usvg::path_utils;
+    use usvg::PathSegment::*;
+
+    // Consecutive and trailing MoveTo, a zero-length line and collinear lines.
+    let segments = vec![
+        MoveTo { x: 0.0, y: 0.0 },
+        MoveTo { x: 10.0, y: 10.0 },
+        LineTo { x: 10.0, y: 10.0 },
+        LineTo { x: 20.0, y: 10.0 },
+        LineTo { x: 30.0, y: 10.0 },
+        LineTo { x: 30.0, y: 20.0 },
+        MoveTo { x: 50.0, y: 50.0 },
+    ];
+    assert_eq!(path_utils::normalize(&segments), vec![
+        MoveTo { x: 10.0, y: 10.0 },
+        LineTo { x: 30.0, y: 10.0 },
+        LineTo { x: 30.0, y: 20.0 },
+    ]);
+
+    // Lines in the opposite direction are preserved.
+    let segments = vec![
+        MoveTo { x: 10.0, y: 10.0 },
+        LineTo { x: 30.0, y: 10.0 },
+        LineTo { x: 20.0, y: 10.0 },
+    ];
+    assert_eq!(path_utils::normalize(&segments), segments);
+
+    // A flat curve, a zero-length curve and lines covered by ClosePath.
+    let segments = vec![
+        MoveTo { x: 0.0, y: 0.0 },
+        CurveTo { x1: 5.0, y1: 0.0, x2: 8.0, y2: 0.0, x: 10.0, y: 0.0 },
+        CurveTo { x1: 10.0, y1: 0.0, x2: 10.0, y2: 0.0, x: 10.0, y: 0.0 },
+        LineTo { x: 10.0, y: 10.0 },
+        LineTo { x: 0.0, y: 10.0 },
+        LineTo { x: 0.0, y: 5.0 },
+        LineTo { x: 0.0, y: 0.0 },
+        ClosePath,
+    ];
+    assert_eq!(path_utils::normalize(&segments), vec![
+        MoveTo { x: 0.0, y: 0.0 },
+        LineTo { x: 10.0, y: 0.0 },
+        LineTo { x: 10.0, y: 10.0 },
+        LineTo { x: 0.0, y: 10.0 },
+        ClosePath,
+    ]);
+
+    // A curve with control points outside the chord is preserved.
+    let segments = vec![
+        MoveTo { x: 0.0, y: 0.0 },
+        CurveTo { x1: -5.0, y1: 0.0, x2: 8.0, y2: 0.0, x: 10.0, y: 0.0 },
+    ];
+    assert_eq!(path_utils::normalize(&segments), segments);
+
+    // A zero-length subpath is rendered with caps, so it is preserved.
+    let segments = vec![
+        MoveTo { x: 10.0, y: 10.0 },
+        LineTo { x: 10.0, y: 10.0 },
+        LineTo { x: 10.0, y: 10.0 },
+    ];
+    assert_eq!(path_utils::normalize(&segments), vec![
+        MoveTo { x: 10.0, y: 10.0 },
+        LineTo { x: 10.0, y: 10.0 },
+    ]);
+
+    let group = |id: &str| {
+        usvg::NodeKind::Group(usvg::Group {
+            id: id.to_string(),
+            transform: usvg::Transform::default(),
+            opacity: None,
+            clip_path: None,
+            mask: None,
+        })
+    };
+
+    // Groups that became empty are removed too.
+    let mut tree = create_tree(100.0, 100.0);
+    let g1 = tree.root().append_kind(group("g1"));
+    let path = g1.clone().append_kind(group("g2")).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: vec![MoveTo { x: 0.0, y: 0.0 }, MoveTo { x: 10.0, y: 10.0 }],
+    }));
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path2".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 10.0, 10.0),
+    }));
+
+    tree.normalize_paths();
+
+    assert!(path.parent().is_none());
+    assert!(g1.parent().is_none());
+    assert_eq!(tree.root().children().count(), 2);
+    assert!(tree.validate().is_empty());
+}
+
+#[test]
+fn simplify_paths() {
+    use usvg::path_utils;
+
+    // A noisy line.
+    let mut segments = vec![usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 }];
+    for i in 1..101 {
+        let y = if i % 2 == 0 { 0.05 } else { -0.05 };
+        segments.push(usvg::PathSegment::LineTo { x: i as f64, y: if i == 100 { 0.0 } else { y } });
+    }
+    segments.push(usvg::PathSegment::LineTo { x: 100.0, y: 50.0 });
+
+    assert_eq!(path_utils::simplify(&segments, 0.1), vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 100.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 100.0, y: 50.0 },
+    ]);
+
+    // A corner is preserved.
+    assert_eq!(path_utils::simplify(&segments, 0.01), segments);
+
+    // A circle made of many short curves.
+    let mut segments = vec![usvg::PathSegment::MoveTo { x: 50.0, y: 0.0 }];
+    let n = 64;
+    let step = 2.0 * std::f64::consts::PI / n as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan() * 50.0;
+    for i in 0..n {
+        let (a1, a2) = (i as f64 * step, (i + 1) as f64 * step);
+        segments.push(usvg::PathSegment::CurveTo {
+            x1: 50.0 * a1.cos() - k * a1.sin(), y1: 50.0 * a1.sin() + k * a1.cos(),
+            x2: 50.0 * a2.cos() + k * a2.sin(), y2: 50.0 * a2.sin() - k * a2.cos(),
+            x: 50.0 * a2.cos(), y: 50.0 * a2.sin(),
+        });
+    }
+    segments.push(usvg::PathSegment::ClosePath);
+
+    let simplified = path_utils::simplify(&segments, 0.01);
+    assert!(simplified.len() < 16);
+    for i in 0..100 {
+        let p = path_utils::point_at_length(&simplified, path_utils::length(&simplified) * i as f64 / 100.0)
+            .unwrap();
+        assert!(((p.x * p.x + p.y * p.y).sqrt() - 50.0).abs() < 0.01);
+    }
+
+    // An S-curve made of many short curves. The whole result must stay
+    // within the tolerance, not only at the sampled points.
+    let mut segments = vec![usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 }];
+    let n = 16;
+    let step = 2.0 * std::f64::consts::PI / n as f64;
+    let (w, h) = (60.0 / n as f64, 20.0);
+    for i in 0..n {
+        let (a1, a2) = (i as f64 * step, (i + 1) as f64 * step);
+        segments.push(usvg::PathSegment::CurveTo {
+            x1: i as f64 * w + w / 3.0, y1: h * a1.sin() + h * step / 3.0 * a1.cos(),
+            x2: (i + 1) as f64 * w - w / 3.0, y2: h * a2.sin() - h * step / 3.0 * a2.cos(),
+            x: (i + 1) as f64 * w, y: h * a2.sin(),
+        });
+    }
+
+    for &tolerance in &[0.05, 0.5, 2.0] {
+        let simplified = path_utils::simplify(&segments, tolerance);
+        assert!(simplified.len() < segments.len());
+        assert!(max_deviation(&segments, &simplified) <= tolerance);
+    }
+
+    // Extended segments are preserved.
+    let segments = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 0.01 },
+        usvg::PathSegment::LineTo { x: 20.0, y: 0.0 },
+        usvg::PathSegment::QuadTo { x1: 25.0, y1: 10.0, x: 30.0, y: 0.0 },
+        usvg::PathSegment::ArcTo {
+            rx: 5.0, ry: 5.0, x_axis_rotation: 0.0, large_arc: false, sweep: true, x: 40.0, y: 0.0,
+        },
+    ];
+    let mut expected = segments.clone();
+    expected.remove(1);
+    assert_eq!(path_utils::simplify(&segments, 0.1), expected);
+
+    // The tolerance is in the canvas coordinates.
+    let mut tree = create_tree(100.0, 100.0);
+    let path = tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        // scale(10)
+        transform: usvg::Transform::new(10.0, 0.0, 0.0, 10.0, 0.0, 0.0),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: vec![
+            usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+            usvg::PathSegment::LineTo { x: 5.0, y: 0.05 },
+            usvg::PathSegment::LineTo { x: 10.0, y: 0.0 },
+        ],
+    }));
+
+    tree.simplify_paths(0.1);
+
+    match *path.borrow() {
+        usvg::NodeKind::Path(ref path) => assert_eq!(path.segments.len(), 3),
+        _ => panic!("not a path"),
+    };
+
+    // Clip path children are in the bounding box units of the clipped path.
+    let mut tree = create_tree(100.0, 100.0);
+    let clip_path = |tree: &usvg::Tree, id: &str| {
+        tree.defs().append_kind(usvg::NodeKind::ClipPath(usvg::ClipPath {
+            id: id.to_string(),
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+        })).append_kind(usvg::NodeKind::Path(usvg::Path {
+            id: String::new(),
+            transform: usvg::Transform::default(),
+            fill: Some(usvg::Fill::default()),
+            stroke: None,
+            segments: vec![
+                usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+                usvg::PathSegment::LineTo { x: 0.5, y: 0.0005 },
+                usvg::PathSegment::LineTo { x: 1.0, y: 0.0 },
+            ],
+        }))
+    };
+
+    let used = clip_path(&tree, "clip1");
+    let unused = clip_path(&tree, "clip2");
+    tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("clip1".to_string()),
+        mask: None,
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 100.0, 100.0),
+    }));
+
+    // The deviation is 0.05 in the canvas coordinates.
+    tree.simplify_paths(0.1);
+
+    match *used.borrow() {
+        usvg::NodeKind::Path(ref path) => assert_eq!(path.segments.len(), 2),
+        _ => panic!("not a path"),
+    };
+    match *unused.borrow() {
+        usvg::NodeKind::Path(ref path) => assert_eq!(path.segments.len(), 3),
+        _ => panic!("not a path"),
+    };
+
+    // But not when the bounding box is bigger.
+    let mut tree = create_tree(100.0, 100.0);
+    let used = clip_path(&tree, "clip1");
+    tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        opacity: None,
+        clip_path: Some("clip1".to_string()),
+        mask: None,
+    })).append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        segments: rect_segments(0.0, 0.0, 1000.0, 1000.0),
+    }));
+
+    tree.simplify_paths(0.1);
+
+    match *used.borrow() {
+        usvg::NodeKind::Path(ref path) => assert_eq!(path.segments.len(), 3),
+        _ => panic!("not a path"),
+    };
+}
+
+#[test]
+fn arc_conversion() {
+    use usvg::path_utils;
+
+    // A quarter of a large circle centered at (100000 100000).
+    let text = "
+    <svg xmlns='http://www.w3.org/2000/svg' width='200000' height='200000'>
+        <path d='M 100000 0 A 100000 100000 0 0 1 200000 100000' fill='none' stroke='black'/>
+    </svg>";
+
+    let convert = |opt: &usvg::Options| {
+        let tree = usvg::Tree::from_str(text, opt).unwrap();
+        let mut list = Vec::new();
+        for node in tree.root().descendants() {
+            let kind = node.borrow();
+            if let usvg::NodeKind::Path(ref path) = *kind {
+                list.push(path.segments.clone());
+            }
+        }
+
+        list.remove(0)
+    };
+
+    for &tolerance in &[0.01, 0.001] {
+        let segments = convert(&usvg::Options {
+            arc_tolerance: tolerance,
+            .. usvg::Options::default()
+        });
+
+        assert!(segments.len() > 2);
+        // The end point is exact.
+        match segments[segments.len() - 1] {
+            usvg::PathSegment::CurveTo { x, y, .. } => assert_eq!((x, y), (200000.0, 100000.0)),
+            _ => panic!("invalid segment"),
+        }
+
+        let len = path_utils::length(&segments);
+        for i in 0..101 {
+            let p = path_utils::point_at_length(&segments, len * i as f64 / 100.0).unwrap();
+            let (dx, dy) = (p.x - 100000.0, p.y - 100000.0);
+            assert!(((dx * dx + dy * dy).sqrt() - 100000.0).abs() <= tolerance);
+        }
+    }
+
+    // A non-positive tolerance is clamped.
+    let segments = convert(&usvg::Options {
+        arc_tolerance: 0.0,
+        .. usvg::Options::default()
+    });
+    assert!(segments.len() > 2 && segments.len() < 100);
+
+    // The arc is preserved as is.
+    let segments = convert(&usvg::Options {
+        path_profile: usvg::PathProfile::Extended,
+        .. usvg::Options::default()
+    });
+    assert_eq!(segments, vec![
+        usvg::PathSegment::MoveTo { x: 100000.0, y: 0.0 },
+        usvg::PathSegment::ArcTo {
+            rx: 100000.0,
+            ry: 100000.0,
+            x_axis_rotation: 0.0,
+            large_arc: false,
+            sweep: true,
+            x: 200000.0,
+            y: 100000.0,
+        },
+    ]);
+}
+
+#[test]
+fn extended_segments() {
+    use usvg::path_utils;
+
+    // A quadratic curve and a semicircle with the (30 0) center.
+    let segments = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::QuadTo { x1: 10.0, y1: -10.0, x: 20.0, y: 0.0 },
+        usvg::PathSegment::ArcTo {
+            rx: 10.0, ry: 10.0, x_axis_rotation: 0.0, large_arc: false, sweep: true, x: 40.0, y: 0.0,
+        },
+    ];
+
+    // Curves length is approximated.
+    let is_close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+
+    let quad_len = path_utils::length(&segments[..2]);
+    assert!(is_close(path_utils::length(&segments), quad_len + std::f64::consts::PI * 10.0));
+
+    let p = path_utils::point_at_length(&segments, quad_len + std::f64::consts::PI * 5.0).unwrap();
+    assert!(is_close(p.x, 30.0) && is_close(p.y, -10.0));
+
+    let bbox = usvg::utils::path_bbox(&segments, None, &usvg::Transform::default()).unwrap();
+    assert_rect(bbox, 0.0, -10.0, 40.0, 10.0);
+
+    // Arcs stay arcs after transformation, including skew and mirroring.
+    for ts in &[
+        usvg::Transform::new(1.0, 0.5, -0.3, 2.0, 5.0, 7.0),
+        usvg::Transform::new(2.0, 0.0, 0.0, -1.0, 0.0, 0.0),
+    ] {
+        let bbox = usvg::utils::path_bbox(&segments, None, ts).unwrap();
+        let flat_bbox = usvg::utils::path_bbox(&path_utils::flatten(&segments, 0.0001), None, ts)
+            .unwrap();
+        assert!((bbox.x - flat_bbox.x).abs() < 0.001 && (bbox.y - flat_bbox.y).abs() < 0.001);
+        assert!((bbox.width - flat_bbox.width).abs() < 0.001);
+        assert!((bbox.height - flat_bbox.height).abs() < 0.001);
+    }
+
+    // Splitting converts arcs into curves.
+    let (first, second) = path_utils::split_at_length(&segments, quad_len + 1.0);
+    assert!(is_close(path_utils::length(&first), quad_len + 1.0));
+    match first[first.len() - 1] {
+        usvg::PathSegment::CurveTo { .. } => {}
+        _ => panic!("invalid segment"),
+    }
+    assert!(is_close(path_utils::length(&second), std::f64::consts::PI * 10.0 - 1.0));
+
+    // An arc with equal end points is omitted and an arc with a zero radius is a line.
+    let mut extra = segments.clone();
+    extra.push(usvg::PathSegment::ArcTo {
+        rx: 5.0, ry: 5.0, x_axis_rotation: 0.0, large_arc: true, sweep: true, x: 40.0, y: 0.0,
+    });
+    extra.push(usvg::PathSegment::ArcTo {
+        rx: 0.0, ry: 5.0, x_axis_rotation: 0.0, large_arc: true, sweep: true, x: 40.0, y: 10.0,
+    });
+    let mut expected = segments.clone();
+    expected.push(usvg::PathSegment::LineTo { x: 40.0, y: 10.0 });
+    assert_eq!(path_utils::normalize(&extra), expected);
+
+    let tree = create_tree(100.0, 100.0);
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        fill: None,
+        stroke: Some(usvg::Stroke::default()),
+        segments: segments.clone(),
+    }));
+
+    let opt = usvg::WriteOptions {
+        indent: usvg::Indent::None,
+        .. usvg::WriteOptions::default()
+    };
+    assert!(tree.to_string(&opt).contains("d=\"M 0 0 Q 10 -10 20 0 A 10 10 0 0 1 40 0\""));
+}
+
+#[test]
+fn shapes() {
+    use usvg::path_utils;
+    use usvg::PathProfile;
+
+    let rect = usvg::ShapeKind::Rect { x: 10.0, y: 20.0, width: 30.0, height: 40.0, rx: 0.0, ry: 0.0 };
+    assert_eq!(rect.to_path(PathProfile::Default, 0.01), rect_segments(10.0, 20.0, 30.0, 40.0));
+
+    // A rounded rect consists of four lines and four arcs and ends at the start point.
+    let rounded = usvg::ShapeKind::Rect { x: 0.0, y: 0.0, width: 30.0, height: 40.0, rx: 5.0, ry: 10.0 };
+    let segments = rounded.to_path(PathProfile::Default, 0.01);
+    assert_eq!(segments.len(), 9);
+    assert_eq!(segments[..2], [
+        usvg::PathSegment::MoveTo { x: 5.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 25.0, y: 0.0 },
+    ]);
+    match segments[8] {
+        usvg::PathSegment::CurveTo { x, y, .. } => assert_eq!((x, y), (5.0, 0.0)),
+        _ => panic!("not a curve"),
+    }
+
+    let segments = rounded.to_path(PathProfile::Extended, 0.01);
+    assert_eq!(segments.len(), 9);
+    assert_eq!(segments[2], usvg::PathSegment::ArcTo {
+        rx: 5.0, ry: 10.0, x_axis_rotation: 0.0, large_arc: false, sweep: true, x: 30.0, y: 10.0,
+    });
+
+    let segments = rounded.to_path(PathProfile::LinesOnly { tolerance: 0.1 }, 0.01);
+    assert!(segments.len() > 9);
+    assert!(segments.iter().all(|seg| match *seg {
+        usvg::PathSegment::CurveTo { .. } | usvg::PathSegment::ArcTo { .. } => false,
+        _ => true,
+    }));
+
+    // Large arcs are approximated by more curves.
+    let big_circle = usvg::ShapeKind::Circle { cx: 0.0, cy: 0.0, r: 1000.0 };
+    assert!(big_circle.to_path(PathProfile::Default, 0.01).len() > 6);
+
+    let circle = usvg::ShapeKind::Circle { cx: 50.0, cy: 50.0, r: 20.0 };
+    let segments = circle.to_path(PathProfile::Default, 0.01);
+    let len = path_utils::length(&segments);
+    assert!((len - std::f64::consts::PI * 40.0).abs() < 0.05);
+    for i in 0..100 {
+        let p = path_utils::point_at_length(&segments, len * i as f64 / 100.0).unwrap();
+        let (dx, dy) = (p.x - 50.0, p.y - 50.0);
+        assert!(((dx * dx + dy * dy).sqrt() - 20.0).abs() < 0.01);
+    }
+
+    let polygon = usvg::ShapeKind::Polyline {
+        points: vec![usvg::Point::new(0.0, 0.0), usvg::Point::new(10.0, 0.0), usvg::Point::new(10.0, 10.0)],
+        closed: true,
+    };
+    assert_eq!(polygon.to_path(PathProfile::Extended, 0.01), vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 10.0 },
+        usvg::PathSegment::ClosePath,
+    ]);
+
+    let mut tree = create_tree(100.0, 100.0);
+    let node = tree.root().append_kind(usvg::NodeKind::Shape(Box::new(usvg::Shape {
+        id: "circle1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill::default()),
+        stroke: Some(usvg::Stroke {
+            width: 2.0,
+            .. usvg::Stroke::default()
+        }),
+        kind: circle.clone(),
+    })));
+
+    assert_rect(usvg::utils::calculate_bbox(&node, false).unwrap(), 30.0, 30.0, 40.0, 40.0);
+    assert!(tree.nodes_at_point(50.0, 50.0) == vec![node.clone()]);
+    assert!(tree.nodes_at_point(20.0, 20.0).is_empty());
+    assert!(tree.validate().is_empty());
+
+    let opt = usvg::WriteOptions {
+        indent: usvg::Indent::None,
+        skip_default_attributes: true,
+        .. usvg::WriteOptions::default()
+    };
+    assert!(tree.to_string(&opt).contains("<circle id=\"circle1\" stroke=\"#000000\" \
+                                           stroke-width=\"2\" cx=\"50\" cy=\"50\" r=\"20\"/>"));
+
+    tree.shapes_to_paths(PathProfile::Default, 0.01);
+
+    match *node.borrow() {
+        usvg::NodeKind::Path(ref path) => {
+            assert_eq!(path.id, "circle1");
+            assert_eq!(path.segments, circle.to_path(PathProfile::Default, 0.01));
+        }
+        _ => panic!("not a path"),
+    };
+}
+
+#[test]
+fn keep_shapes() {
+    let text = "
+    <svg xmlns='http://www.w3.org/2000/svg' width='100' height='100'>
+        <rect id='rect1' x='10' y='10' width='50' height='20' rx='30'/>
+        <polygon points='0 0 10 0 10 10'/>
+    </svg>";
+
+    let opt = usvg::Options {
+        keep_shapes: true,
+        .. usvg::Options::default()
+    };
+    let tree = usvg::Tree::from_str(text, &opt).unwrap();
+
+    let mut shapes = Vec::new();
+    for node in tree.root().descendants() {
+        let kind = node.borrow();
+        if let usvg::NodeKind::Shape(ref shape) = *kind {
+            shapes.push(shape.kind.clone());
+        }
+    }
+
+    // Radii are resolved and clamped.
+    assert_eq!(shapes, vec![
+        usvg::ShapeKind::Rect { x: 10.0, y: 10.0, width: 50.0, height: 20.0, rx: 25.0, ry: 10.0 },
+        usvg::ShapeKind::Polyline {
+            points: vec![usvg::Point::new(0.0, 0.0), usvg::Point::new(10.0, 0.0), usvg::Point::new(10.0, 10.0)],
+            closed: true,
+        },
+    ]);
+
+    // Shapes are converted into the same segments as during the parsing.
+    for profile in vec![usvg::PathProfile::Default, usvg::PathProfile::Extended] {
+        let opt = usvg::Options {
+            path_profile: profile,
+            arc_tolerance: 0.001,
+            .. usvg::Options::default()
+        };
+        let tree = usvg::Tree::from_str(text, &opt).unwrap();
+        assert_eq!(tree.path_profile(), profile);
+
+        let mut paths = Vec::new();
+        for node in tree.root().descendants() {
+            let kind = node.borrow();
+            if let usvg::NodeKind::Path(ref path) = *kind {
+                paths.push(path.segments.clone());
+            }
+        }
+
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0], shapes[0].to_path(profile, 0.001));
+        assert_eq!(paths[1], shapes[1].to_path(profile, 0.001));
+    }
+}
+
+#[test]
+fn text_clusters() {
+    let span = |text: &str| {
+        usvg::TextSpan {
+            fill: Some(usvg::Fill::default()),
+            stroke: None,
+            font: usvg::Font::default(),
+            decoration: usvg::TextDecoration::default(),
+            text: text.to_string(),
+        }
+    };
+
+    let text = usvg::Text {
+        id: String::new(),
+        transform: usvg::Transform::default(),
+        rotate: Some(vec![10.0, 20.0].into()),
+        chunks: vec![
+            usvg::TextChunk {
+                x: Some(vec![10.0, 20.0].into()),
+                y: Some(vec![30.0].into()),
+                dx: Some(vec![1.0, 2.0, 3.0].into()),
+                dy: None,
+                anchor: usvg::TextAnchor::Middle,
+                spans: vec![span("e\u{301}a"), span("b")],
+            },
+            usvg::TextChunk {
+                x: None,
+                y: None,
+                dx: Some(vec![4.0].into()),
+                dy: Some(vec![5.0].into()),
+                anchor: usvg::TextAnchor::Start,
+                spans: vec![span("c")],
+            },
+        ],
+    };
+
+    let clusters = text.clusters();
+    let values: Vec<_> = clusters.iter().map(|c| {
+        (c.text, c.x, c.y, c.dx, c.dy, c.rotate, c.anchor, c.span_index)
+    }).collect();
+
+    // A combining mark is a part of the cluster, and shifts are accumulated
+    // until the next absolute position.
+    assert_eq!(values, vec![
+        ("e\u{301}", Some(10.0), Some(30.0), 1.0, 0.0, 10.0, usvg::TextAnchor::Middle, 0),
+        ("a", Some(20.0), None, 2.0, 0.0, 20.0, usvg::TextAnchor::Middle, 0),
+        ("b", None, None, 5.0, 0.0, 20.0, usvg::TextAnchor::Middle, 1),
+        ("c", None, None, 9.0, 5.0, 20.0, usvg::TextAnchor::Start, 2),
+    ]);
+
+    assert_eq!(clusters[2].span.text, "b");
+}
+
+#[test]
+fn font_family() {
+    let text = "
+    <svg xmlns='http://www.w3.org/2000/svg' width='100' height='100'>
+        <text font-family=\"Verdana,  Times  New Roman, 'sans-serif', \\41 rial, SANS-SERIF\">Text</text>
+    </svg>";
+
+    let tree = usvg::Tree::from_str(text, &usvg::Options::default()).unwrap();
+
+    let mut families = Vec::new();
+    for node in tree.root().descendants() {
+        let kind = node.borrow();
+        if let usvg::NodeKind::Text(ref text) = *kind {
+            families = text.chunks[0].spans[0].font.family.clone();
+        }
+    }
+
+    let named = |name: &str| usvg::FontFamily::Named(name.to_string());
+    assert_eq!(families, vec![
+        named("Verdana"),
+        named("Times New Roman"),
+        named("sans-serif"),
+        named("Arial"),
+        usvg::FontFamily::Generic(usvg::GenericFontFamily::SansSerif),
+    ]);
+
+    // Names that look like keywords are quoted.
+    let opt = usvg::WriteOptions {
+        indent: usvg::Indent::None,
+        .. usvg::WriteOptions::default()
+    };
+    assert!(tree.to_string(&opt).contains(
+        "font-family=\"Verdana, Times New Roman, 'sans-serif', Arial, sans-serif\""));
+
+    let opt = usvg::Options::default();
+    assert_eq!(opt.font_families.resolve(&families),
+               vec!["Verdana", "Times New Roman", "sans-serif", "Arial", "Arial"]);
+}
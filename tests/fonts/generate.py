@@ -0,0 +1,162 @@
+#!/usr/bin/env python3
+# Generates minimal TrueType fonts for the text conversion tests.
+#
+# All glyphs are simple rectangles with known metrics, so the tests
+# can check the layout without a real font.
+
+import struct
+
+UNITS_PER_EM = 1000
+ASCENDER = 800
+DESCENDER = -200
+
+
+def rect(x1, y1, x2, y2):
+    return [[(x1, y1, True), (x1, y2, True), (x2, y2, True), (x2, y1, True)]]
+
+
+def glyphs(a_advance):
+    # (code point, advance, contours)
+    return [
+        (None, 500, rect(50, 0, 450, 700)),  # .notdef
+        (0x20, 250, []),
+        (0x41, a_advance, rect(0, 0, 500, 500)),
+        (0x56, 600, rect(0, 0, 500, 500)),
+        # Quadratic curves only.
+        (0x4F, 600, [[(0, 0, False), (0, 500, False), (500, 500, False), (500, 0, False)]]),
+        # Combining acute accent.
+        (0x301, 0, rect(200, 600, 300, 700)),
+    ]
+
+
+KERNING = [(0x41, 0x56, -100)]
+
+
+def glyf_data(contours):
+    if not contours:
+        return b''
+
+    points = [p for c in contours for p in c]
+    xs = [p[0] for p in points]
+    ys = [p[1] for p in points]
+    data = struct.pack('>hhhhh', len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack('>H', end)
+    data += struct.pack('>H', 0)
+    data += bytes(1 if p[2] else 0 for p in points)
+    prev = 0
+    for x in xs:
+        data += struct.pack('>h', x - prev)
+        prev = x
+    prev = 0
+    for y in ys:
+        data += struct.pack('>h', y - prev)
+        prev = y
+    if len(data) % 2:
+        data += b'\0'
+    return data
+
+
+def name_table(names):
+    records = b''
+    strings = b''
+    for name_id, text in names:
+        s = text.encode('utf-16-be')
+        records += struct.pack('>HHHHHH', 3, 1, 0x409, name_id, len(s), len(strings))
+        strings += s
+    return struct.pack('>HHH', 0, len(names), 6 + len(records)) + records + strings
+
+
+def cmap_table(glyph_list):
+    groups = b''
+    count = 0
+    for gid, (c, _, _) in enumerate(glyph_list):
+        if c is not None:
+            groups += struct.pack('>III', c, c, gid)
+            count += 1
+    sub = struct.pack('>HHIII', 12, 0, 16 + len(groups), 0, count) + groups
+    return struct.pack('>HHHHI', 0, 1, 3, 10, 12) + sub
+
+
+def kern_table(glyph_list):
+    gids = {c: gid for gid, (c, _, _) in enumerate(glyph_list)}
+    pairs = b''.join(struct.pack('>HHh', gids[l], gids[r], v) for l, r, v in KERNING)
+    sub = struct.pack('>HHHHHHH', 0, 14 + len(pairs), 0x0001, len(KERNING), 6, 0, 0) + pairs
+    return struct.pack('>HH', 0, 1) + sub
+
+
+def os2_table(weight, width, fs_selection, glyph_list):
+    return struct.pack(
+        '>HhHHHhhhhhhhhhhh10s4I4sHHHhhhHHIIhhHHH',
+        4, 500, weight, width, 0,
+        650, 600, 0, 75, 650, 600, 0, 350,
+        50, 250, 0, b'\0' * 10, 0, 0, 0, 0, b'TEST',
+        fs_selection, 0x20, 0x301, ASCENDER, DESCENDER, 0,
+        ASCENDER, -DESCENDER, 1, 0, 500, 500, 0, 0x20, 2)
+
+
+def build(path, family, subfamily, weight, width, fs_selection, mac_style, a_advance):
+    glyph_list = glyphs(a_advance)
+
+    glyf = b''
+    loca = b''
+    hmtx = b''
+    for _, advance, contours in glyph_list:
+        loca += struct.pack('>I', len(glyf))
+        glyf += glyf_data(contours)
+        lsb = min([p[0] for c in contours for p in c] or [0])
+        hmtx += struct.pack('>Hh', advance, lsb)
+    loca += struct.pack('>I', len(glyf))
+
+    head = struct.pack('>IIIIHHqqhhhhHHhhh',
+                       0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0, UNITS_PER_EM, 0, 0,
+                       0, DESCENDER, 1000, ASCENDER, mac_style, 8, 2, 1, 0)
+    hhea = struct.pack('>IhhhHhhhhhhhhhhhH',
+                       0x00010000, ASCENDER, DESCENDER, 0, 1000, 0, 0, 500, 1, 0, 0,
+                       0, 0, 0, 0, 0, len(glyph_list))
+    maxp = struct.pack('>IH', 0x00005000, len(glyph_list))
+    post = struct.pack('>IIhhIIIII', 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0)
+    name = name_table([
+        (1, family),
+        (2, subfamily),
+        (4, family + ' ' + subfamily),
+        (6, (family + '-' + subfamily).replace(' ', '')),
+    ])
+
+    tables = {
+        b'OS/2': os2_table(weight, width, fs_selection, glyph_list),
+        b'cmap': cmap_table(glyph_list),
+        b'glyf': glyf,
+        b'head': head,
+        b'hhea': hhea,
+        b'hmtx': hmtx,
+        b'kern': kern_table(glyph_list),
+        b'loca': loca,
+        b'maxp': maxp,
+        b'name': name,
+        b'post': post,
+    }
+
+    offset = 12 + 16 * len(tables)
+    directory = struct.pack('>IHHHH', 0x00010000, len(tables), 128, 3, 16 * len(tables) - 128)
+    data = b''
+    for tag in sorted(tables):
+        table = tables[tag]
+        directory += struct.pack('>4sIII', tag, 0, offset + len(data), len(table))
+        data += table + b'\0' * (-len(table) % 4)
+
+    with open(path, 'wb') as f:
+        f.write(directory + data)
+
+
+REGULAR = 1 << 6
+BOLD = 1 << 5
+ITALIC = 1 << 0
+
+build('TestSans-Regular.ttf', 'Test Sans', 'Regular', 400, 5, REGULAR, 0, 600)
+build('TestSans-Bold.ttf', 'Test Sans', 'Bold', 700, 5, BOLD, 1, 700)
+build('TestSans-Italic.ttf', 'Test Sans', 'Italic', 400, 5, ITALIC, 2, 650)
+build('TestSans-Condensed.ttf', 'Test Sans', 'Condensed', 400, 3, REGULAR, 0, 550)
+build('TestSerif-Regular.ttf', 'Test Serif', 'Regular', 400, 5, REGULAR, 0, 800)
@@ -0,0 +1,152 @@
+#![cfg(feature = "serde")]
+
+extern crate usvg;
+extern crate serde_json;
+
+use usvg::prelude::*;
+
+#[test]
+fn roundtrip() {
+    let tree = usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(100.0, 100.0),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, 100.0, 100.0),
+            aspect: usvg::AspectRatio::default(),
+        },
+    });
+
+    let mut defs = tree.defs();
+    defs.append_kind(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+        id: "lg1".to_string(),
+        x1: 0.0,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 0.0,
+        base: usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            stops: vec![
+                usvg::Stop {
+                    offset: 0.0.into(),
+                    color: usvg::Color::new(255, 0, 0),
+                    opacity: 1.0.into(),
+                },
+            ],
+        },
+    }));
+
+    let mut g = tree.root().append_kind(usvg::NodeKind::Group(usvg::Group {
+        id: "g1".to_string(),
+        transform: usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 10.0, 20.0),
+        opacity: Some(0.5.into()),
+        clip_path: None,
+        mask: None,
+    }));
+
+    g.append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: Some(usvg::Stroke {
+            dasharray: Some(vec![1.0, 2.0].into()),
+            .. usvg::Stroke::default()
+        }),
+        segments: vec![
+            usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+            usvg::PathSegment::LineTo { x: 10.0, y: 0.0 },
+            usvg::PathSegment::ClosePath,
+        ],
+    }));
+
+    let json = serde_json::to_string(&tree).unwrap();
+    let tree2: usvg::Tree = serde_json::from_str(&json).unwrap();
+    assert_eq!(json, serde_json::to_string(&tree2).unwrap());
+
+    assert_eq!(tree2.defs().children().count(), 1);
+    assert!(tree2.defs_by_id("lg1").is_some());
+
+    let path = tree2.node_by_id("path1").unwrap();
+    assert_eq!(&*path.parent().unwrap().id(), "g1");
+    assert_eq!(path.parent().unwrap().transform().e, 10.0);
+}
+
+#[test]
+fn nested_defs_are_rejected() {
+    let json = r#"{
+        "svg": {
+            "size": { "width": 10.0, "height": 10.0 },
+            "view_box": {
+                "rect": { "x": 0.0, "y": 0.0, "width": 10.0, "height": 10.0 },
+                "aspect": { "defer": false, "align": "XMidYMid", "slice": false }
+            }
+        },
+        "defs": [],
+        "children": [{ "kind": "Defs", "children": [] }]
+    }"#;
+
+    assert!(serde_json::from_str::<usvg::Tree>(json).is_err());
+}
+
+fn tree_with_path(path: &str) -> String {
+    format!(r#"{{
+        "svg": {{
+            "size": {{ "width": 10.0, "height": 10.0 }},
+            "view_box": {{
+                "rect": {{ "x": 0.0, "y": 0.0, "width": 10.0, "height": 10.0 }},
+                "aspect": {{ "defer": false, "align": "XMidYMid", "slice": false }}
+            }}
+        }},
+        "children": [{{ "kind": {{ "Path": {} }} }}]
+    }}"#, path)
+}
+
+#[test]
+fn invalid_opacity_is_rejected() {
+    let path = |opacity: f64| tree_with_path(&format!(r##"{{
+        "id": "",
+        "transform": [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        "fill": {{ "paint": {{ "Color": "#000000" }}, "opacity": {}, "rule": "NonZero" }},
+        "stroke": null,
+        "segments": [{{ "MoveTo": {{ "x": 0.0, "y": 0.0 }} }}, {{ "LineTo": {{ "x": 1.0, "y": 1.0 }} }}]
+    }}"##, opacity));
+
+    assert!(serde_json::from_str::<usvg::Tree>(&path(0.5)).is_ok());
+    assert!(serde_json::from_str::<usvg::Tree>(&path(1.5)).is_err());
+    assert!(serde_json::from_str::<usvg::Tree>(&path(-0.5)).is_err());
+}
+
+#[test]
+fn invalid_segments_are_rejected() {
+    let path = |segments: &str| tree_with_path(&format!(r#"{{
+        "id": "",
+        "transform": [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+        "fill": null,
+        "stroke": null,
+        "segments": {}
+    }}"#, segments));
+
+    assert!(serde_json::from_str::<usvg::Tree>(&path("[]")).is_err());
+    assert!(serde_json::from_str::<usvg::Tree>(&path(r#"[{ "LineTo": { "x": 1.0, "y": 1.0 } }]"#)).is_err());
+}
+
+#[test]
+fn extended_types() {
+    let json = r#"[{"Named": "Times New Roman"}, {"Generic": "Serif"}]"#;
+    let family: Vec<usvg::FontFamily> = serde_json::from_str(json).unwrap();
+    assert_eq!(family, vec![
+        usvg::FontFamily::Named("Times New Roman".to_string()),
+        usvg::FontFamily::Generic(usvg::GenericFontFamily::Serif),
+    ]);
+
+    let json = r#"{"Polyline": {"points": [{"x": 0.0, "y": 0.0}], "closed": true}}"#;
+    let kind: usvg::ShapeKind = serde_json::from_str(json).unwrap();
+    assert_eq!(kind, usvg::ShapeKind::Polyline { points: vec![usvg::Point::new(0.0, 0.0)], closed: true });
+
+    let json = r#"{"QuadTo": {"x1": 10.0, "y1": 10.0, "x": 20.0, "y": 0.0}}"#;
+    let seg: usvg::PathSegment = serde_json::from_str(json).unwrap();
+    assert_eq!(seg, usvg::PathSegment::QuadTo { x1: 10.0, y1: 10.0, x: 20.0, y: 0.0 });
+}
@@ -0,0 +1,199 @@
+#![cfg(feature = "tessellation")]
+
+extern crate usvg;
+
+use usvg::prelude::*;
+use usvg::tessellation::{self, MeshKind, VertexPaint};
+
+fn rect_segments(x: f64, y: f64, w: f64, h: f64) -> Vec<usvg::PathSegment> {
+    vec![
+        usvg::PathSegment::MoveTo { x, y },
+        usvg::PathSegment::LineTo { x: x + w, y },
+        usvg::PathSegment::LineTo { x: x + w, y: y + h },
+        usvg::PathSegment::LineTo { x, y: y + h },
+        usvg::PathSegment::ClosePath,
+    ]
+}
+
+fn triangles_area(points: &[usvg::Point], indices: &[u32]) -> f64 {
+    let mut area = 0.0;
+    for t in indices.chunks(3) {
+        let (a, b, c) = (points[t[0] as usize], points[t[1] as usize], points[t[2] as usize]);
+        let ta = ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)) / 2.0;
+        assert!(ta > 0.0);
+        area += ta;
+    }
+
+    area
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+#[test]
+fn triangulate() {
+    let rule = usvg::FillRule::NonZero;
+
+    let rect = rect_segments(0.0, 0.0, 20.0, 10.0);
+    let (points, indices) = tessellation::triangulate(&rect, rule, 0.1).unwrap();
+    assert_eq!(indices.len(), 6);
+    assert!(approx_eq(triangles_area(&points, &indices), 200.0));
+
+    // A square with a hole.
+    let mut ring = rect_segments(0.0, 0.0, 30.0, 30.0);
+    ring.extend_from_slice(&rect_segments(10.0, 10.0, 10.0, 10.0));
+
+    let (points, indices) = tessellation::triangulate(&ring, usvg::FillRule::EvenOdd, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 800.0));
+
+    // Both subpaths have the same orientation, so there is no hole with `NonZero`.
+    let (points, indices) = tessellation::triangulate(&ring, rule, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 900.0));
+
+    // Multiple holes are connected to the same outline.
+    let mut holes = rect_segments(0.0, 0.0, 40.0, 20.0);
+    holes.extend_from_slice(&rect_segments(5.0, 5.0, 5.0, 10.0));
+    holes.extend_from_slice(&rect_segments(15.0, 5.0, 10.0, 5.0));
+    holes.extend_from_slice(&rect_segments(30.0, 5.0, 5.0, 5.0));
+    let (points, indices) = tessellation::triangulate(&holes, usvg::FillRule::EvenOdd, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 800.0 - 50.0 - 50.0 - 25.0));
+
+    // A self-intersecting bowtie.
+    let bowtie = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 10.0 },
+        usvg::PathSegment::LineTo { x: 10.0, y: 0.0 },
+        usvg::PathSegment::LineTo { x: 0.0, y: 10.0 },
+        usvg::PathSegment::ClosePath,
+    ];
+    let (points, indices) = tessellation::triangulate(&bowtie, rule, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 50.0));
+
+    // A circle.
+    let k = 0.5522847498 * 10.0;
+    let circle = vec![
+        usvg::PathSegment::MoveTo { x: 10.0, y: 0.0 },
+        usvg::PathSegment::CurveTo { x1: 10.0, y1: k, x2: k, y2: 10.0, x: 0.0, y: 10.0 },
+        usvg::PathSegment::CurveTo { x1: -k, y1: 10.0, x2: -10.0, y2: k, x: -10.0, y: 0.0 },
+        usvg::PathSegment::CurveTo { x1: -10.0, y1: -k, x2: -k, y2: -10.0, x: 0.0, y: -10.0 },
+        usvg::PathSegment::CurveTo { x1: k, y1: -10.0, x2: 10.0, y2: -k, x: 10.0, y: 0.0 },
+        usvg::PathSegment::ClosePath,
+    ];
+    let (points, indices) = tessellation::triangulate(&circle, rule, 0.01).unwrap();
+    let area = triangles_area(&points, &indices);
+    assert!((area - std::f64::consts::PI * 100.0).abs() < 0.5);
+}
+
+#[test]
+fn triangulate_self_touching() {
+    let line = |x: f64, y: f64| usvg::PathSegment::LineTo { x, y };
+
+    // Two squares that touch at (10, 10) in a single subpath.
+    let segments = vec![
+        usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 },
+        line(10.0, 0.0),
+        line(10.0, 10.0),
+        line(20.0, 10.0),
+        line(20.0, 20.0),
+        line(10.0, 20.0),
+        line(10.0, 10.0),
+        line(0.0, 10.0),
+        usvg::PathSegment::ClosePath,
+    ];
+    let (points, indices) = tessellation::triangulate(&segments, usvg::FillRule::NonZero, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 200.0));
+
+    // A heptagram, which tips touch each other with `EvenOdd`.
+    let mut star = Vec::new();
+    for i in 0..7 {
+        let a = (i * 6) as f64 * std::f64::consts::PI / 7.0;
+        star.push(line(a.cos() * 10.0, a.sin() * 10.0));
+    }
+    star[0] = usvg::PathSegment::MoveTo { x: 10.0, y: 0.0 };
+    star.push(usvg::PathSegment::ClosePath);
+
+    let (points, indices) = tessellation::triangulate(&star, usvg::FillRule::EvenOdd, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 98.3161461832));
+
+    // A nearly collinear zigzag.
+    let mut zigzag = vec![usvg::PathSegment::MoveTo { x: 0.0, y: 0.0 }];
+    for i in 1..100 {
+        let y = if i % 2 == 0 { 1e-10 } else { -1e-10 };
+        zigzag.push(line(i as f64, y));
+    }
+    zigzag.push(line(100.0, 10.0));
+    zigzag.push(line(0.0, 10.0));
+    zigzag.push(usvg::PathSegment::ClosePath);
+
+    let (points, indices) = tessellation::triangulate(&zigzag, usvg::FillRule::NonZero, 0.1).unwrap();
+    assert!(approx_eq(triangles_area(&points, &indices), 995.0));
+}
+
+#[test]
+fn tessellate() {
+    let tree = usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(200.0, 200.0),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, 100.0, 100.0),
+            aspect: usvg::AspectRatio::default(),
+        },
+    });
+
+    tree.defs().append_kind(usvg::NodeKind::LinearGradient(usvg::LinearGradient {
+        id: "lg1".to_string(),
+        x1: 0.0,
+        y1: 0.0,
+        x2: 1.0,
+        y2: 0.0,
+        base: usvg::BaseGradient {
+            units: usvg::Units::ObjectBoundingBox,
+            transform: usvg::Transform::default(),
+            spread_method: usvg::SpreadMethod::Pad,
+            stops: Vec::new(),
+        },
+    }));
+
+    tree.root().append_kind(usvg::NodeKind::Path(usvg::Path {
+        id: "path1".to_string(),
+        transform: usvg::Transform::default(),
+        fill: Some(usvg::Fill {
+            paint: usvg::Paint::Link("lg1".to_string()),
+            .. usvg::Fill::default()
+        }),
+        stroke: Some(usvg::Stroke {
+            paint: usvg::Paint::Color(usvg::Color::new(255, 0, 0)),
+            width: 2.0,
+            .. usvg::Stroke::default()
+        }),
+        segments: rect_segments(10.0, 10.0, 20.0, 10.0),
+    }));
+
+    let meshes = tessellation::tessellate(&tree, 0.1);
+    assert_eq!(meshes.len(), 2);
+
+    let fill = &meshes[0];
+    assert_eq!(fill.id, "path1");
+    assert_eq!(fill.kind, MeshKind::Fill);
+    assert_eq!(fill.indices.len(), 6);
+    for v in &fill.vertices {
+        // The canvas is scaled by 2.
+        let t = (v.x / 2.0 - 10.0) / 20.0;
+        match v.paint {
+            VertexPaint::LinearGradient { t: vt } => assert!(approx_eq(t, vt)),
+            _ => panic!("invalid paint"),
+        }
+    }
+
+    let stroke = &meshes[1];
+    assert_eq!(stroke.kind, MeshKind::Stroke);
+    let points: Vec<usvg::Point> = stroke.vertices.iter().map(|v| usvg::Point::new(v.x, v.y)).collect();
+    // (22 * 12 - 18 * 8) * 4
+    assert!(approx_eq(triangles_area(&points, &stroke.indices), 480.0));
+    for v in &stroke.vertices {
+        assert_eq!(v.paint, VertexPaint::Color {
+            color: usvg::Color::new(255, 0, 0),
+            opacity: 1.0.into(),
+        });
+    }
+}
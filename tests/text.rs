@@ -0,0 +1,297 @@
+#![cfg(feature = "text")]
+
+extern crate usvg;
+
+use usvg::prelude::*;
+use usvg::text::{self, FontDatabase};
+
+fn fonts_dir() -> String {
+    concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fonts").to_string()
+}
+
+fn load_fonts() -> FontDatabase {
+    let mut db = FontDatabase::new();
+    db.load_fonts_dir(fonts_dir());
+    db
+}
+
+fn named(name: &str) -> usvg::FontFamily {
+    usvg::FontFamily::Named(name.to_string())
+}
+
+fn span(text: &str, family: &str) -> usvg::TextSpan {
+    usvg::TextSpan {
+        fill: Some(usvg::Fill::default()),
+        stroke: None,
+        font: usvg::Font {
+            family: vec![named(family)],
+            size: 10.0,
+            .. usvg::Font::default()
+        },
+        decoration: usvg::TextDecoration::default(),
+        text: text.to_string(),
+    }
+}
+
+fn chunk(spans: Vec<usvg::TextSpan>) -> usvg::TextChunk {
+    usvg::TextChunk {
+        x: Some(vec![0.0].into()),
+        y: Some(vec![0.0].into()),
+        dx: None,
+        dy: None,
+        anchor: usvg::TextAnchor::Start,
+        spans,
+    }
+}
+
+fn create_tree(text: usvg::Text) -> usvg::Tree {
+    let tree = usvg::Tree::create(usvg::Svg {
+        size: usvg::Size::new(100.0, 100.0),
+        view_box: usvg::ViewBox {
+            rect: usvg::Rect::new(0.0, 0.0, 100.0, 100.0),
+            aspect: usvg::AspectRatio::default(),
+        },
+    });
+
+    tree.root().append_kind(usvg::NodeKind::Text(text));
+    tree
+}
+
+fn text(chunks: Vec<usvg::TextChunk>) -> usvg::Text {
+    usvg::Text {
+        id: "text1".to_string(),
+        transform: usvg::Transform::default(),
+        rotate: None,
+        chunks,
+    }
+}
+
+fn convert(text: usvg::Text) -> Vec<usvg::Path> {
+    convert_with_options(text, &usvg::Options::default())
+}
+
+fn convert_with_options(text: usvg::Text, opt: &usvg::Options) -> Vec<usvg::Path> {
+    let mut tree = create_tree(text);
+    text::text_to_paths(&mut tree, &load_fonts(), opt);
+
+    tree.root().children().filter_map(|node| {
+        match *node.borrow() {
+            usvg::NodeKind::Path(ref path) => Some(path.clone()),
+            usvg::NodeKind::Text(_) => panic!("a text node was not converted"),
+            _ => None,
+        }
+    }).collect()
+}
+
+fn bbox(path: &usvg::Path) -> usvg::Rect {
+    usvg::utils::path_bbox(&path.segments, None, &usvg::Transform::default()).unwrap()
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+fn check_rect(rect: usvg::Rect, x: f64, y: f64, w: f64, h: f64) {
+    let expected = usvg::Rect::new(x, y, w, h);
+    assert!(approx_eq(rect.x, x) && approx_eq(rect.y, y)
+            && approx_eq(rect.width, w) && approx_eq(rect.height, h),
+            "{:?} != {:?}", rect, expected);
+}
+
+fn text_bbox(chunk: usvg::TextChunk) -> usvg::Rect {
+    let paths = convert(text(vec![chunk]));
+    assert_eq!(paths.len(), 1);
+    bbox(&paths[0])
+}
+
+fn font_width(family: &str, f: &Fn(&mut usvg::Font)) -> f64 {
+    let mut s = span("AA", family);
+    f(&mut s.font);
+    text_bbox(chunk(vec![s])).width
+}
+
+#[test]
+fn load_faces() {
+    let db = load_fonts();
+    assert_eq!(db.faces().len(), 5);
+
+    let mut faces: Vec<_> = db.faces().iter().map(|f| {
+        (f.family.clone(), f.weight, f.style, f.stretch)
+    }).collect();
+    faces.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+
+    assert_eq!(faces[0].0, "Test Sans");
+    assert!(faces.iter().any(|f| f.1 == 700));
+    assert!(faces.iter().any(|f| f.2 == usvg::FontStyle::Italic));
+    assert!(faces.iter().any(|f| f.3 == usvg::FontStretch::Condensed));
+    assert_eq!(faces[4].0, "Test Serif");
+}
+
+#[test]
+fn load_missing_file() {
+    let mut db = FontDatabase::new();
+    assert!(db.load_font_file(format!("{}/missing.ttf", fonts_dir())).is_err());
+    db.load_font_data(vec![0, 1, 2, 3]);
+    assert!(db.faces().is_empty());
+}
+
+#[test]
+fn empty_database() {
+    let mut tree = create_tree(text(vec![chunk(vec![span("A", "Test Sans")])]));
+    text::text_to_paths(&mut tree, &FontDatabase::new(), &usvg::Options::default());
+    let node = tree.root().last_child().unwrap();
+    assert!(if let usvg::NodeKind::Text(_) = *node.borrow() { true } else { false });
+}
+
+#[test]
+fn select_face() {
+    assert_eq!(font_width("Test Sans", &|_| {}), 11.0);
+    assert_eq!(font_width("Test Sans", &|f| {
+        f.family = vec![named("Unknown"), named("Test Serif"), named("Test Sans")];
+    }), 13.0);
+    assert_eq!(font_width("test sans", &|f| f.weight = usvg::FontWeight::W700), 12.0);
+    assert_eq!(font_width("Test Sans", &|f| f.weight = usvg::FontWeight::W900), 12.0);
+    assert_eq!(font_width("Test Sans", &|f| f.weight = usvg::FontWeight::W500), 11.0);
+    assert_eq!(font_width("Test Sans", &|f| f.style = usvg::FontStyle::Italic), 11.5);
+    assert_eq!(font_width("Test Sans", &|f| f.style = usvg::FontStyle::Oblique), 11.5);
+    assert_eq!(font_width("Test Sans", &|f| f.stretch = usvg::FontStretch::Condensed), 10.5);
+    assert_eq!(font_width("Test Sans", &|f| f.stretch = usvg::FontStretch::Narrower), 10.5);
+    assert_eq!(font_width("Test Sans", &|f| f.stretch = usvg::FontStretch::UltraCondensed), 10.5);
+    assert_eq!(font_width("Test Sans", &|f| f.stretch = usvg::FontStretch::Expanded), 11.0);
+}
+
+#[test]
+fn generic_family() {
+    let mut s = span("AA", "");
+    s.font.family = vec![named("Unknown"), usvg::FontFamily::Generic(usvg::GenericFontFamily::Monospace)];
+
+    let mut opt = usvg::Options::default();
+    opt.font_families.monospace = "Test Serif".to_string();
+
+    let paths = convert_with_options(text(vec![chunk(vec![s])]), &opt);
+    check_rect(bbox(&paths[0]), 0.0, -5.0, 13.0, 5.0);
+}
+
+#[test]
+fn glyph_outline() {
+    // 'A' is a 500x500 units square and the font size is 10.
+    check_rect(text_bbox(chunk(vec![span("A", "Test Sans")])), 0.0, -5.0, 5.0, 5.0);
+
+    // 'O' is made from quadratic curves.
+    let paths = convert(text(vec![chunk(vec![span("O", "Test Sans")])]));
+    assert!(paths[0].segments.iter().any(|seg| {
+        if let usvg::PathSegment::CurveTo { .. } = *seg { true } else { false }
+    }));
+}
+
+#[test]
+fn kerning() {
+    // 'A' advance is 6 and 'AV' kerning is -1.
+    check_rect(text_bbox(chunk(vec![span("AV", "Test Sans")])), 0.0, -5.0, 10.0, 5.0);
+
+    // Kerning is not applied between spans.
+    let c = chunk(vec![span("A", "Test Sans"), span("V", "Test Sans")]);
+    let paths = convert(text(vec![c]));
+    assert_eq!(paths.len(), 2);
+    check_rect(bbox(&paths[1]), 6.0, -5.0, 5.0, 5.0);
+}
+
+#[test]
+fn positions() {
+    let mut c = chunk(vec![span("AAA", "Test Sans")]);
+    c.x = Some(vec![10.0, 20.0].into());
+    c.y = Some(vec![30.0].into());
+    c.dx = Some(vec![0.0, 0.0, 1.0].into());
+    c.dy = Some(vec![2.0].into());
+    check_rect(text_bbox(c), 10.0, 27.0, 22.0, 5.0);
+}
+
+#[test]
+fn grapheme_positions() {
+    // A combining mark doesn't consume a position.
+    let mut c = chunk(vec![span("A\u{301}A", "Test Sans")]);
+    c.x = Some(vec![10.0, 20.0].into());
+    check_rect(text_bbox(c), 10.0, -7.0, 15.0, 7.0);
+}
+
+#[test]
+fn multiple_chunks() {
+    let mut c2 = chunk(vec![span("A", "Test Sans")]);
+    c2.x = Some(vec![50.0].into());
+    c2.y = Some(vec![50.0].into());
+
+    let paths = convert(text(vec![chunk(vec![span("A", "Test Sans")]), c2]));
+    assert_eq!(paths.len(), 2);
+    check_rect(bbox(&paths[0]), 0.0, -5.0, 5.0, 5.0);
+    check_rect(bbox(&paths[1]), 50.0, 45.0, 5.0, 5.0);
+}
+
+#[test]
+fn rotate() {
+    let mut t = text(vec![chunk(vec![span("AA", "Test Sans")])]);
+    t.rotate = Some(vec![90.0].into());
+
+    // The last rotate value is used by all the remaining characters.
+    let paths = convert(t);
+    check_rect(bbox(&paths[0]), 0.0, 0.0, 11.0, 5.0);
+}
+
+#[test]
+fn anchor() {
+    let mut c = chunk(vec![span("AA", "Test Sans")]);
+    c.x = Some(vec![50.0].into());
+
+    c.anchor = usvg::TextAnchor::Middle;
+    check_rect(text_bbox(c.clone()), 44.0, -5.0, 11.0, 5.0);
+
+    c.anchor = usvg::TextAnchor::End;
+    check_rect(text_bbox(c), 38.0, -5.0, 11.0, 5.0);
+}
+
+#[test]
+fn decoration() {
+    let mut s = span("AA", "Test Sans");
+    let style = |color| {
+        Some(usvg::TextDecorationStyle {
+            fill: Some(usvg::Fill {
+                paint: usvg::Paint::Color(color),
+                .. usvg::Fill::default()
+            }),
+            stroke: None,
+        })
+    };
+    s.decoration = usvg::TextDecoration {
+        underline: style(usvg::Color::new(255, 0, 0)),
+        overline: style(usvg::Color::new(0, 255, 0)),
+        line_through: style(usvg::Color::new(0, 0, 255)),
+    };
+
+    let paths = convert(text(vec![chunk(vec![s])]));
+    assert_eq!(paths.len(), 4);
+
+    // Underline.
+    check_rect(bbox(&paths[0]), 0.0, 1.0, 12.0, 0.5);
+    assert_eq!(paths[0].segments.len(), 5);
+    match paths[0].fill.as_ref().unwrap().paint {
+        usvg::Paint::Color(c) => assert_eq!(c, usvg::Color::new(255, 0, 0)),
+        _ => panic!("invalid paint"),
+    }
+    // Overline.
+    check_rect(bbox(&paths[1]), 0.0, -8.0, 12.0, 0.5);
+    // Glyphs.
+    check_rect(bbox(&paths[2]), 0.0, -5.0, 11.0, 5.0);
+    // Line-through.
+    check_rect(bbox(&paths[3]), 0.0, -2.5, 12.0, 0.5);
+}
+
+#[test]
+fn path_attributes() {
+    let mut t = text(vec![chunk(vec![span("A", "Test Sans"), span("A", "Test Sans")])]);
+    t.transform = usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 10.0, 20.0);
+
+    let paths = convert(t);
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].id, "text1");
+    assert_eq!(paths[1].id, "");
+    assert_eq!(paths[1].transform, usvg::Transform::new(1.0, 0.0, 0.0, 1.0, 10.0, 20.0));
+}